@@ -16,3 +16,77 @@ pub struct ErrorResponse {
     pub error: String,
     pub message: Option<String>,
 }
+
+/// Client id of the sync agent at the server's OAuth endpoints
+pub const CLIENT_ID: &str = "cook-sync";
+
+/// Form of a token request that redeems an authorization code
+#[derive(Debug, Serialize)]
+pub struct CodeGrantRequest<'a> {
+    pub grant_type: &'static str,
+    pub client_id: &'static str,
+    pub code: &'a str,
+    pub code_verifier: &'a str,
+    pub redirect_uri: &'a str,
+}
+
+/// Form of a token request that redeems a refresh token
+#[derive(Debug, Serialize)]
+pub struct RefreshGrantRequest<'a> {
+    pub grant_type: &'static str,
+    pub client_id: &'static str,
+    pub refresh_token: &'a str,
+}
+
+/// Grant type of a token request that polls a device login (RFC 8628)
+pub const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Form of a token request that polls a device login
+#[derive(Debug, Serialize)]
+pub struct DeviceGrantRequest<'a> {
+    pub grant_type: &'static str,
+    pub client_id: &'static str,
+    pub device_code: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// The verification page with the user code filled in
+    pub verification_uri_complete: Option<String>,
+    /// Seconds until the codes expire
+    pub expires_in: u64,
+    /// Seconds to wait between polls
+    #[serde(default = "default_poll_interval")]
+    pub interval: u64,
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+/// Answer to a poll of a device login
+#[derive(Debug)]
+pub enum DevicePoll {
+    Tokens(TokenResponse),
+    /// Not approved yet
+    Pending,
+    /// Polled too often; wait longer between polls
+    SlowDown,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    /// Absent when the server keeps the current refresh token
+    pub refresh_token: Option<String>,
+}
+
+/// Error body of the OAuth endpoints (RFC 6749, section 5.2)
+#[derive(Debug, Deserialize)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    pub error_description: Option<String>,
+}
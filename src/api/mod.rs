@@ -18,12 +18,37 @@ impl CookApi {
         Ok(CookApi { client, base_url })
     }
 
-    pub fn base_url(&self) -> &str {
-        &self.base_url
+    /// The site the API belongs to, e.g. `https://cook.md`; it issues the tokens
+    pub fn site_url(&self) -> &str {
+        self.base_url.strip_suffix("/api").unwrap_or(&self.base_url)
+    }
+
+    /// The public keys the server signs its tokens with
+    pub async fn jwks(&self) -> Result<jsonwebtoken::jwk::JwkSet> {
+        let url = format!("{}/.well-known/jwks.json", self.site_url());
+
+        debug!("Fetching token signing keys from: {url}");
+
+        let response = self.client.get(&url).send().await?;
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            status => Err(SyncError::Other(format!(
+                "Fetching token signing keys failed with status {status}"
+            ))),
+        }
     }
 
     pub async fn refresh_token(&self, current_token: &str) -> Result<String> {
-        let url = format!("{}/sessions/renew", self.base_url);
+        self.renew("sessions/renew", current_token).await
+    }
+
+    /// Get a new long-lived device token before the current one expires
+    pub async fn renew_device_token(&self, current_token: &str) -> Result<String> {
+        self.renew("devices/renew", current_token).await
+    }
+
+    async fn renew(&self, path: &str, current_token: &str) -> Result<String> {
+        let url = format!("{}/{path}", self.base_url);
 
         debug!("Refreshing token at: {url}");
 
@@ -53,4 +78,124 @@ impl CookApi {
             }
         }
     }
+
+    /// Redeem the authorization code of a browser login for tokens
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        redirect_uri: &str,
+    ) -> Result<TokenResponse> {
+        self.token_request(&CodeGrantRequest {
+            grant_type: "authorization_code",
+            client_id: CLIENT_ID,
+            code,
+            code_verifier,
+            redirect_uri,
+        })
+        .await
+    }
+
+    /// Get a new access token for a refresh token
+    pub async fn refresh_access_token(&self, refresh_token: &str) -> Result<TokenResponse> {
+        self.token_request(&RefreshGrantRequest {
+            grant_type: "refresh_token",
+            client_id: CLIENT_ID,
+            refresh_token,
+        })
+        .await
+    }
+
+    /// Start a device login: the user approves it on another device with
+    /// the returned user code
+    pub async fn device_authorization(&self) -> Result<DeviceAuthorizationResponse> {
+        let url = format!("{}/oauth/device/code", self.site_url());
+
+        debug!("Requesting device code at: {url}");
+
+        let response = self
+            .client
+            .post(&url)
+            .form(&[("client_id", CLIENT_ID)])
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            status => Err(TokenError {
+                status,
+                body: response.json().await.ok(),
+            }
+            .into_error()),
+        }
+    }
+
+    /// Ask whether the device login of `device_code` was approved yet
+    pub async fn poll_device_token(&self, device_code: &str) -> Result<DevicePoll> {
+        let form = DeviceGrantRequest {
+            grant_type: DEVICE_CODE_GRANT,
+            client_id: CLIENT_ID,
+            device_code,
+        };
+        let error = match self.post_token_form(&form).await? {
+            Ok(tokens) => return Ok(DevicePoll::Tokens(tokens)),
+            Err(error) => error,
+        };
+
+        match error.body.as_ref().map(|body| body.error.as_str()) {
+            Some("authorization_pending") => Ok(DevicePoll::Pending),
+            Some("slow_down") => Ok(DevicePoll::SlowDown),
+            Some("access_denied") => Err(SyncError::Other("The login was denied".to_string())),
+            Some("expired_token") => Err(SyncError::Other(
+                "The login code expired before it was approved".to_string(),
+            )),
+            _ => Err(error.into_error()),
+        }
+    }
+
+    async fn token_request(&self, form: &impl serde::Serialize) -> Result<TokenResponse> {
+        self.post_token_form(form)
+            .await?
+            .map_err(TokenError::into_error)
+    }
+
+    /// POST a grant to the token endpoint; tokens travel only in the bodies
+    async fn post_token_form(
+        &self,
+        form: &impl serde::Serialize,
+    ) -> Result<std::result::Result<TokenResponse, TokenError>> {
+        let url = format!("{}/oauth/token", self.site_url());
+
+        debug!("Requesting tokens at: {url}");
+
+        let response = self.client.post(&url).form(form).send().await?;
+        match response.status() {
+            StatusCode::OK => Ok(Ok(response.json().await?)),
+            status => Ok(Err(TokenError {
+                status,
+                body: response.json().await.ok(),
+            })),
+        }
+    }
+}
+
+/// A refusal from the OAuth endpoints
+struct TokenError {
+    status: StatusCode,
+    body: Option<OAuthErrorResponse>,
+}
+
+impl TokenError {
+    fn into_error(self) -> SyncError {
+        match self.body {
+            // The code or refresh token was used, expired or revoked
+            Some(body) if body.error == "invalid_grant" => SyncError::AuthenticationRequired,
+            _ if self.status == StatusCode::UNAUTHORIZED => SyncError::AuthenticationRequired,
+            body => SyncError::Other(format!(
+                "Token request failed with status {}: {}",
+                self.status,
+                body.map(|e| e.error_description.unwrap_or(e.error))
+                    .unwrap_or_default()
+            )),
+        }
+    }
 }
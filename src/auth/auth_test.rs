@@ -0,0 +1,128 @@
+use super::secure_session::keyring_store::MockKeyring;
+use super::test_keys::{self, SERVER_KEY};
+use super::test_server::TestServer;
+use super::*;
+use crate::config::profiles::DEFAULT_PROFILE;
+
+/// Serves the signing keys and answers the token endpoint with
+/// `token_response`, in which `{access_token}` is replaced by a fresh token
+async fn serve_tokens(status: &'static str, token_response: &'static str) -> TestServer {
+    let access_token = Arc::new(Mutex::new(String::new()));
+
+    let token = Arc::clone(&access_token);
+    let keys = serde_json::to_string(&test_keys::key_set(&[&SERVER_KEY])).unwrap();
+    let server = TestServer::start(move |request| {
+        if request.starts_with("GET /.well-known/jwks.json ") {
+            ("200 OK", keys.clone())
+        } else {
+            assert!(request.starts_with("POST /oauth/token "));
+            let token = token.lock().unwrap();
+            (status, token_response.replace("{access_token}", &token))
+        }
+    })
+    .await;
+
+    // The token names the server as its issuer, so it is made once the port is known
+    *access_token.lock().unwrap() =
+        SERVER_KEY.sign(&test_keys::claims(server.api.site_url(), 3600));
+    server
+}
+
+/// Form fields of the last request to the token endpoint
+fn token_form(server: &TestServer) -> Vec<String> {
+    let request = server
+        .requests()
+        .into_iter()
+        .rev()
+        .find(|r| r.starts_with("POST /oauth/token "))
+        .unwrap();
+    let (_, body) = request.split_once("\r\n\r\n").unwrap();
+    let mut fields: Vec<_> = body.split('&').map(str::to_string).collect();
+    fields.sort();
+    fields
+}
+
+/// A manager whose session holds a token due for renewal and `refresh_token`
+fn logged_in(server: &TestServer, store: &Arc<MockKeyring>, refresh_token: &str) -> AuthManager {
+    let mut claims = test_keys::claims(server.api.site_url(), 60);
+    claims["iat"] = (chrono::Utc::now().timestamp() - 3600).into();
+    SecureSession::new(SERVER_KEY.sign(&claims))
+        .unwrap()
+        .with_refresh_token(Some(refresh_token.to_string()))
+        .save_with_mock(store)
+        .unwrap();
+
+    AuthManager::with_store(DEFAULT_PROFILE, Arc::clone(&server.api), store.clone()).unwrap()
+}
+
+#[tokio::test]
+async fn test_code_exchange_sends_pkce_grant() {
+    let server = serve_tokens(
+        "200 OK",
+        r#"{"access_token":"{access_token}","refresh_token":"refresh-1","token_type":"Bearer"}"#,
+    )
+    .await;
+    let store = Arc::new(MockKeyring::new());
+    let auth =
+        AuthManager::with_store(DEFAULT_PROFILE, Arc::clone(&server.api), store.clone()).unwrap();
+
+    auth.redeem_code("code-1", "verifier-1", "http://127.0.0.1:8765/callback")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        token_form(&server),
+        vec![
+            "client_id=cook-sync",
+            "code=code-1",
+            "code_verifier=verifier-1",
+            "grant_type=authorization_code",
+            "redirect_uri=http%3A%2F%2F127.0.0.1%3A8765%2Fcallback",
+        ]
+    );
+    let session = SecureSession::load_with_mock(&store).unwrap().unwrap();
+    assert_eq!(session.user_id, "42");
+    assert_eq!(session.refresh_token.as_deref(), Some("refresh-1"));
+}
+
+#[tokio::test]
+async fn test_refresh_keeps_refresh_token_the_server_does_not_rotate() {
+    let server = serve_tokens(
+        "200 OK",
+        r#"{"access_token":"{access_token}","token_type":"Bearer"}"#,
+    )
+    .await;
+    let store = Arc::new(MockKeyring::new());
+    let auth = logged_in(&server, &store, "refresh-1");
+    let old_jwt = auth.get_session().unwrap().jwt;
+
+    auth.refresh_if_needed().await.unwrap();
+
+    assert_eq!(
+        token_form(&server),
+        vec![
+            "client_id=cook-sync",
+            "grant_type=refresh_token",
+            "refresh_token=refresh-1",
+        ]
+    );
+    let session = auth.get_session().unwrap();
+    assert_ne!(session.jwt, old_jwt);
+    assert_eq!(session.refresh_token.as_deref(), Some("refresh-1"));
+    let saved = SecureSession::load_with_mock(&store).unwrap().unwrap();
+    assert_eq!(saved.jwt, session.jwt);
+    assert_eq!(saved.refresh_token.as_deref(), Some("refresh-1"));
+}
+
+#[tokio::test]
+async fn test_refused_refresh_clears_session() {
+    let server = serve_tokens("400 Bad Request", r#"{"error":"invalid_grant"}"#).await;
+    let store = Arc::new(MockKeyring::new());
+    let auth = logged_in(&server, &store, "revoked");
+
+    let result = auth.refresh_if_needed().await;
+
+    assert!(matches!(result, Err(SyncError::AuthenticationRequired)));
+    assert!(auth.get_session().is_none());
+    assert!(SecureSession::load_with_mock(&store).unwrap().is_none());
+}
@@ -0,0 +1,457 @@
+//! Loopback HTTP server that receives the browser's redirect after a login.
+//!
+//! Browsers send requests of their own to such a server (`/favicon.ico`,
+//! preconnects, CORS preflights), split requests across packets and reuse
+//! connections. Every connection is therefore served until it closes, and
+//! only a `GET` of the callback path is handed to the login; anything else
+//! gets a short answer and is otherwise ignored.
+
+use crate::error::{Result, SyncError};
+use log::{debug, error};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+pub const CALLBACK_PATH: &str = "/auth/callback";
+
+/// Longest request head read before the connection is dropped
+const MAX_REQUEST_HEAD: usize = 8192;
+/// Longest request body skipped to reach the next request
+const MAX_REQUEST_BODY: usize = 65536;
+/// Connections idle this long are closed
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Answer to CORS preflights, for sign-in pages that call the callback
+/// with `fetch` instead of redirecting
+const CORS_HEADERS: &str = "Access-Control-Allow-Origin: *\r\n\
+    Access-Control-Allow-Methods: GET, OPTIONS\r\n\
+    Access-Control-Allow-Headers: x-csrf-token, x-turbo-request-id\r\n\
+    Access-Control-Max-Age: 86400\r\n";
+
+pub struct CallbackServer {
+    port: u16,
+    callbacks: mpsc::Receiver<Callback>,
+    accept: JoinHandle<()>,
+}
+
+impl CallbackServer {
+    /// Listen on a free port of 127.0.0.1
+    pub async fn bind() -> Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let port = listener.local_addr()?.port();
+        let (sender, callbacks) = mpsc::channel(4);
+
+        Ok(Self {
+            port,
+            callbacks,
+            accept: tokio::spawn(accept_connections(listener, sender)),
+        })
+    }
+
+    pub fn redirect_uri(&self) -> String {
+        format!("http://127.0.0.1:{}{CALLBACK_PATH}", self.port)
+    }
+
+    /// The next request for the callback path
+    pub async fn next_callback(&mut self) -> Result<Callback> {
+        self.callbacks
+            .recv()
+            .await
+            .ok_or_else(|| SyncError::Other("Login callback server stopped".to_string()))
+    }
+}
+
+impl Drop for CallbackServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+/// A request for the callback path, waiting for its answer
+pub struct Callback {
+    query: HashMap<String, String>,
+    connection: Connection,
+}
+
+impl Callback {
+    /// A decoded query parameter
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query.get(name).map(String::as_str)
+    }
+
+    /// Answer with an HTML page and close the connection
+    pub async fn respond(mut self, status: &str, html: &str) -> Result<()> {
+        let headers = "Content-Type: text/html; charset=utf-8\r\n\
+                       Access-Control-Allow-Origin: *\r\n";
+        let stream = &mut self.connection.stream;
+        stream
+            .write_all(&response(status, headers, html, false))
+            .await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+}
+
+async fn accept_connections(listener: TcpListener, callbacks: mpsc::Sender<Callback>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let callbacks = callbacks.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(Connection::new(stream), callbacks).await {
+                        debug!("Login callback connection closed with error: {e}");
+                    }
+                });
+            }
+            Err(e) => {
+                error!("Login callback server stopped: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Answer requests on `connection` until it closes or carries the callback
+async fn serve_connection(
+    mut connection: Connection,
+    callbacks: mpsc::Sender<Callback>,
+) -> Result<()> {
+    loop {
+        let request = match tokio::time::timeout(IDLE_TIMEOUT, connection.next_request()).await {
+            Ok(Ok(Some(request))) => request,
+            Ok(Ok(None)) | Err(_) => return Ok(()),
+            Ok(Err(e)) => {
+                let bad_request = response("400 Bad Request", "", "Bad request\n", false);
+                let _ = connection.stream.write_all(&bad_request).await;
+                return Err(e);
+            }
+        };
+        debug!(
+            "Login callback server got {} {}",
+            request.method,
+            request.path()
+        );
+
+        let answer = match (request.method.as_str(), request.path()) {
+            ("GET", CALLBACK_PATH) => {
+                let query = parse_query(&request.target);
+                // Nobody waits for callbacks any more once the login is over
+                let _ = callbacks.send(Callback { query, connection }).await;
+                return Ok(());
+            }
+            ("OPTIONS", _) => response("200 OK", CORS_HEADERS, "", request.keep_alive),
+            _ => response("404 Not Found", "", "Not found\n", request.keep_alive),
+        };
+        connection.stream.write_all(&answer).await?;
+        if !request.keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+fn response(status: &str, headers: &str, body: &str, keep_alive: bool) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 {status}\r\n\
+         {headers}\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Connection: {}\r\n\r\n\
+         {body}",
+        body.len(),
+        if keep_alive { "keep-alive" } else { "close" }
+    )
+    .into_bytes()
+}
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    target: String,
+    keep_alive: bool,
+    content_length: usize,
+}
+
+impl Request {
+    fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+}
+
+/// A client connection; bytes read past one request are kept for the next
+struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// The next request, or `None` once the client closed the connection
+    async fn next_request(&mut self) -> Result<Option<Request>> {
+        let head_end = loop {
+            // Empty lines before a request are allowed (RFC 9112, section 2.2)
+            let blank = self
+                .buffer
+                .iter()
+                .take_while(|&&b| b == b'\r' || b == b'\n');
+            self.buffer.drain(..blank.count());
+
+            if let Some(end) = self.buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+            if self.buffer.len() > MAX_REQUEST_HEAD {
+                return Err(bad_request("request head too long"));
+            }
+            if !self.fill().await? {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                return Err(bad_request("connection closed within a request"));
+            }
+        };
+
+        let request = parse_head(&self.buffer[..head_end])?;
+        self.buffer.drain(..head_end);
+
+        // The body is not used, but has to be skipped to reach the next request
+        while self.buffer.len() < request.content_length {
+            if !self.fill().await? {
+                return Err(bad_request("connection closed within a request body"));
+            }
+        }
+        self.buffer.drain(..request.content_length);
+        Ok(Some(request))
+    }
+
+    /// Read more from the client; false once it closed the connection
+    async fn fill(&mut self) -> Result<bool> {
+        let mut chunk = [0; 4096];
+        let n = self.stream.read(&mut chunk).await?;
+        self.buffer.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+}
+
+/// Request line and headers of an HTTP/1.x request, up to and including the
+/// blank line that ends them
+fn parse_head(head: &[u8]) -> Result<Request> {
+    let head = std::str::from_utf8(head).map_err(|_| bad_request("request head is not UTF-8"))?;
+    let mut lines = head.split("\r\n");
+
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(target), Some(version), None) = (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) else {
+        return Err(bad_request("malformed request line"));
+    };
+    let mut keep_alive = match version {
+        "HTTP/1.1" => true,
+        "HTTP/1.0" => false,
+        _ => return Err(bad_request("unsupported HTTP version")),
+    };
+
+    let mut content_length = 0;
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| bad_request("malformed header"))?;
+        let (name, value) = (name.trim(), value.trim());
+
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .map_err(|_| bad_request("invalid Content-Length"))?;
+            if content_length > MAX_REQUEST_BODY {
+                return Err(bad_request("request body too long"));
+            }
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(bad_request("chunked request bodies are not supported"));
+        } else if name.eq_ignore_ascii_case("connection") {
+            for option in value.split(',').map(str::trim) {
+                if option.eq_ignore_ascii_case("close") {
+                    keep_alive = false;
+                } else if option.eq_ignore_ascii_case("keep-alive") {
+                    keep_alive = true;
+                }
+            }
+        }
+    }
+
+    Ok(Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        keep_alive,
+        content_length,
+    })
+}
+
+/// Decoded query parameters of a request target
+fn parse_query(target: &str) -> HashMap<String, String> {
+    let Some((_, query)) = target.split_once('?') else {
+        return HashMap::new();
+    };
+    let decode = |value: &str| {
+        urlencoding::decode(&value.replace('+', " "))
+            .ok()
+            .map(|value| value.into_owned())
+    };
+
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((decode(name)?, decode(value)?))
+        })
+        .collect()
+}
+
+fn bad_request(reason: &str) -> SyncError {
+    SyncError::Other(format!("Bad login callback request: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn connect(server: &CallbackServer) -> TcpStream {
+        let port = server.port;
+        TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+            .await
+            .unwrap()
+    }
+
+    /// One response from `stream`, which must carry a Content-Length
+    async fn read_response(stream: &mut TcpStream) -> String {
+        let mut response = Vec::new();
+        let mut chunk = [0; 1024];
+        loop {
+            let text = String::from_utf8_lossy(&response).into_owned();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                if body.len() >= length {
+                    return text;
+                }
+            }
+            let n = stream.read(&mut chunk).await.unwrap();
+            assert!(n > 0, "connection closed within a response");
+            response.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stray_requests_are_ignored_on_a_kept_alive_connection() {
+        let mut server = CallbackServer::bind().await.unwrap();
+        let mut client = connect(&server).await;
+
+        client
+            .write_all(b"GET /favicon.ico HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n")
+            .await
+            .unwrap();
+        let response = read_response(&mut client).await;
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+        assert!(response.contains("Connection: keep-alive\r\n"));
+
+        // The callback arrives on the same connection, split across writes
+        for part in [
+            "GET /auth/callback?code=abc%2F1&st",
+            "ate=xyz HTTP/1.1\r\nHo",
+            "st: 127.0.0.1\r\n",
+            "\r\n",
+        ] {
+            client.write_all(part.as_bytes()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let callback = server.next_callback().await.unwrap();
+        assert_eq!(callback.param("code"), Some("abc/1"));
+        assert_eq!(callback.param("state"), Some("xyz"));
+        callback.respond("200 OK", "<p>Done</p>").await.unwrap();
+
+        let response = read_response(&mut client).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with("<p>Done</p>"));
+    }
+
+    #[tokio::test]
+    async fn test_idle_and_pipelined_connections() {
+        let mut server = CallbackServer::bind().await.unwrap();
+
+        // A preconnected socket that never sends anything does not hold up
+        // the callback on another connection
+        let _idle = connect(&server).await;
+
+        // A preflight with a body, then the callback, in one write
+        let mut client = connect(&server).await;
+        client
+            .write_all(
+                b"\r\nOPTIONS /auth/callback HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello\
+                  GET /auth/callback?code=a+b HTTP/1.1\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        let callback = server.next_callback().await.unwrap();
+        assert_eq!(callback.param("code"), Some("a b"));
+        assert_eq!(callback.param("state"), None);
+        callback.respond("400 Bad Request", "no").await.unwrap();
+
+        let mut responses = String::new();
+        client.read_to_string(&mut responses).await.unwrap();
+        let (preflight, callback) = responses.split_once("\r\n\r\nHTTP/1.1 ").unwrap();
+        assert!(preflight.starts_with("HTTP/1.1 200 OK\r\n"), "{preflight}");
+        assert!(preflight.contains("Access-Control-Allow-Origin: *\r\n"));
+        assert!(callback.starts_with("400 Bad Request\r\n"), "{callback}");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_requests_are_refused() {
+        let server = CallbackServer::bind().await.unwrap();
+        let mut client = connect(&server).await;
+
+        client.write_all(b"NONSENSE\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+            "{response}"
+        );
+    }
+
+    #[test]
+    fn test_parse_head() {
+        let request = parse_head(b"GET /auth/callback?x=1 HTTP/1.0\r\nHost: a\r\n\r\n").unwrap();
+        assert_eq!(request.path(), "/auth/callback");
+        assert!(!request.keep_alive);
+
+        let request =
+            parse_head(b"POST / HTTP/1.0\r\nconnection: Keep-Alive\r\ncontent-length: 3\r\n\r\n")
+                .unwrap();
+        assert!(request.keep_alive);
+        assert_eq!(request.content_length, 3);
+
+        assert!(parse_head(b"GET / HTTP/2\r\n\r\n").is_err());
+        assert!(parse_head(b"GET /\r\n\r\n").is_err());
+        assert!(parse_head(b"GET / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Device authorization grant (RFC 8628), for logging in on machines without
+//! a browser, such as servers reached over SSH.
+//!
+//! The user approves a short code on any other device while the agent polls
+//! the token endpoint, as slowly as the server asks it to.
+
+use super::AuthManager;
+use crate::api::endpoints::{DeviceAuthorizationResponse, DevicePoll, TokenResponse};
+use crate::api::CookApi;
+use crate::error::{Result, SyncError};
+use log::{debug, warn};
+use std::future::Future;
+use tokio::time::{sleep, Duration};
+
+/// Added to the poll interval whenever the server asks to slow down
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+/// Polls that fail to reach the server back off up to this interval
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+impl AuthManager {
+    /// Log in by approving a user code elsewhere. `show_code` tells the user
+    /// where to enter it; this returns once the login is approved, denied or
+    /// expired.
+    pub async fn device_login(
+        &self,
+        show_code: impl FnOnce(&DeviceAuthorizationResponse),
+    ) -> Result<()> {
+        let authorization = self.api.device_authorization().await?;
+        show_code(&authorization);
+
+        let tokens = poll_for_tokens(&self.api, &authorization, sleep).await?;
+        self.set_session(tokens.access_token, tokens.refresh_token)
+            .await
+    }
+}
+
+/// Poll the token endpoint until the login is approved, denied or expired,
+/// calling `wait` before each poll. The time spent waiting counts towards the
+/// expiry of the codes.
+async fn poll_for_tokens<W: Future<Output = ()>>(
+    api: &CookApi,
+    authorization: &DeviceAuthorizationResponse,
+    mut wait: impl FnMut(Duration) -> W,
+) -> Result<TokenResponse> {
+    let expires_in = Duration::from_secs(authorization.expires_in);
+    let mut waited = Duration::ZERO;
+    let mut interval = Duration::from_secs(authorization.interval.max(1));
+    loop {
+        wait(interval).await;
+        waited += interval;
+        if waited >= expires_in {
+            return Err(SyncError::Other(
+                "The login code expired before it was approved".to_string(),
+            ));
+        }
+
+        match api.poll_device_token(&authorization.device_code).await {
+            Ok(DevicePoll::Tokens(tokens)) => return Ok(tokens),
+            Ok(DevicePoll::Pending) => debug!("Device login not approved yet"),
+            Ok(DevicePoll::SlowDown) => {
+                interval = (interval + SLOW_DOWN_STEP).min(MAX_POLL_INTERVAL);
+                debug!("Polling device login every {}s", interval.as_secs());
+            }
+            // Keep polling through network trouble, but less often
+            Err(e @ (SyncError::Network(_) | SyncError::Server(_))) => {
+                warn!("Could not poll device login: {e}");
+                interval = back_off(interval);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn back_off(interval: Duration) -> Duration {
+    (interval * 2).min(MAX_POLL_INTERVAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_server::TestServer;
+
+    #[tokio::test]
+    async fn test_device_login_exchanges() {
+        let server = TestServer::replay(vec![
+            (
+                "POST /oauth/device/code ",
+                "200 OK",
+                r#"{"device_code":"dev-1","user_code":"WDJB-MJHT","verification_uri":"https://cook.md/device","expires_in":900}"#,
+            ),
+            (
+                "device_code=dev-1",
+                "400 Bad Request",
+                r#"{"error":"authorization_pending"}"#,
+            ),
+            (
+                "grant-type%3Adevice_code",
+                "400 Bad Request",
+                r#"{"error":"slow_down"}"#,
+            ),
+            (
+                "client_id=cook-sync",
+                "200 OK",
+                r#"{"access_token":"access","refresh_token":"refresh","token_type":"Bearer"}"#,
+            ),
+            (
+                "device_code=dev-1",
+                "400 Bad Request",
+                r#"{"error":"access_denied"}"#,
+            ),
+        ])
+        .await;
+        let api = &server.api;
+
+        let authorization = api.device_authorization().await.unwrap();
+        assert_eq!(authorization.user_code, "WDJB-MJHT");
+        assert_eq!(authorization.interval, 5);
+
+        let poll = || api.poll_device_token("dev-1");
+        assert!(matches!(poll().await, Ok(DevicePoll::Pending)));
+        assert!(matches!(poll().await, Ok(DevicePoll::SlowDown)));
+        match poll().await {
+            Ok(DevicePoll::Tokens(tokens)) => {
+                assert_eq!(tokens.access_token, "access");
+                assert_eq!(tokens.refresh_token.as_deref(), Some("refresh"));
+            }
+            other => panic!("expected tokens, got {other:?}"),
+        }
+        assert!(poll().await.is_err());
+    }
+
+    fn authorization(expires_in: u64, interval: u64) -> DeviceAuthorizationResponse {
+        DeviceAuthorizationResponse {
+            device_code: "dev-1".to_string(),
+            user_code: "WDJB-MJHT".to_string(),
+            verification_uri: "https://cook.md/device".to_string(),
+            verification_uri_complete: None,
+            expires_in,
+            interval,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_polling_slows_down_until_approved() {
+        let pending = r#"{"error":"authorization_pending"}"#;
+        let slow_down = r#"{"error":"slow_down"}"#;
+        let server = TestServer::replay(vec![
+            ("device_code=dev-1", "400 Bad Request", pending),
+            ("device_code=dev-1", "400 Bad Request", slow_down),
+            ("device_code=dev-1", "400 Bad Request", slow_down),
+            ("device_code=dev-1", "400 Bad Request", slow_down),
+            (
+                "device_code=dev-1",
+                "200 OK",
+                r#"{"access_token":"access","refresh_token":"refresh","token_type":"Bearer"}"#,
+            ),
+        ])
+        .await;
+
+        let mut waits = Vec::new();
+        let tokens = poll_for_tokens(&server.api, &authorization(900, 50), |interval| {
+            waits.push(interval.as_secs());
+            async {}
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(tokens.access_token, "access");
+        // Each slow_down adds a step, up to the longest interval
+        assert_eq!(waits, vec![50, 50, 55, 60, 60]);
+    }
+
+    #[tokio::test]
+    async fn test_expired_login_ends_polling() {
+        let server = TestServer::replay(vec![(
+            "device_code=dev-1",
+            "400 Bad Request",
+            r#"{"error":"expired_token"}"#,
+        )])
+        .await;
+        let result = poll_for_tokens(&server.api, &authorization(900, 5), |_| async {}).await;
+        assert!(result.is_err());
+        assert_eq!(server.requests().len(), 1);
+
+        // Codes that run out while waiting are not polled again
+        let pending = r#"{"error":"authorization_pending"}"#;
+        let server = TestServer::replay(vec![
+            ("device_code=dev-1", "400 Bad Request", pending),
+            ("device_code=dev-1", "400 Bad Request", pending),
+        ])
+        .await;
+        let result = poll_for_tokens(&server.api, &authorization(12, 5), |_| async {}).await;
+        assert!(result.is_err());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_failed_polls_back_off() {
+        assert_eq!(back_off(Duration::from_secs(5)), Duration::from_secs(10));
+        assert_eq!(back_off(Duration::from_secs(45)), MAX_POLL_INTERVAL);
+    }
+}
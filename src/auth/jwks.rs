@@ -0,0 +1,120 @@
+//! Verification of the tokens the server issues.
+//!
+//! A token from a login or a refresh is only stored once its signature,
+//! issuer, audience and expiry check out. The server's public keys come from
+//! its JWKS document and are cached; a token naming a key that is not cached
+//! makes the verifier fetch them again, as the server may have rotated keys.
+
+use super::jwt::JwtToken;
+use crate::api::CookApi;
+use crate::error::Result;
+use jsonwebtoken::jwk::JwkSet;
+use log::{info, warn};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Audience of the tokens issued to the sync agent
+pub const AUDIENCE: &str = "cook-sync";
+
+/// Cached keys are fetched again after this long
+const MAX_KEY_AGE: Duration = Duration::from_secs(24 * 3600);
+/// Unknown key ids make the verifier fetch the keys at most this often
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(300);
+
+pub struct TokenVerifier {
+    api: Arc<CookApi>,
+    keys: Mutex<Option<(JwkSet, Instant)>>,
+}
+
+impl TokenVerifier {
+    pub fn new(api: Arc<CookApi>) -> Self {
+        Self {
+            api,
+            keys: Mutex::new(None),
+        }
+    }
+
+    /// Check `token` against the server's keys and the expected issuer,
+    /// which is the site the API belongs to
+    pub async fn verify(&self, token: String) -> Result<JwtToken> {
+        let kid = jsonwebtoken::decode_header(&token)?.kid;
+        let cached = self.keys.lock().unwrap().clone();
+
+        let keys = match cached {
+            Some((keys, fetched_at)) if !needs_fetch(&keys, fetched_at, kid.as_deref()) => keys,
+            cached => match self.api.jwks().await {
+                Ok(keys) => {
+                    info!("Fetched {} token signing key(s)", keys.keys.len());
+                    *self.keys.lock().unwrap() = Some((keys.clone(), Instant::now()));
+                    keys
+                }
+                // Keys known so far still verify tokens signed with them
+                Err(e) => match cached {
+                    Some((keys, _)) => {
+                        warn!("Could not refresh token signing keys: {e}");
+                        keys
+                    }
+                    None => return Err(e),
+                },
+            },
+        };
+
+        JwtToken::verify(token, &keys, self.api.site_url(), AUDIENCE)
+    }
+}
+
+/// Whether the cached `keys` are too old, or lack the key `kid` and were not
+/// fetched just now
+fn needs_fetch(keys: &JwkSet, fetched_at: Instant, kid: Option<&str>) -> bool {
+    let age = fetched_at.elapsed();
+    let unknown_key = kid.is_some_and(|kid| keys.find(kid).is_none());
+    age > MAX_KEY_AGE || (unknown_key && age > MIN_REFETCH_INTERVAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_keys::{self, OTHER_KEY, SERVER_KEY};
+    use crate::auth::test_server::TestServer;
+
+    /// Serves `keys` as the server's JWKS document
+    async fn serve_keys(keys: JwkSet) -> TestServer {
+        let body = serde_json::to_string(&keys).unwrap();
+        TestServer::start(move |request| {
+            assert!(request.starts_with("GET /.well-known/jwks.json "));
+            ("200 OK", body.clone())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_fetched_once_and_cached() {
+        let server = serve_keys(test_keys::key_set(&[&SERVER_KEY])).await;
+        let api = &server.api;
+        let claims = test_keys::claims(api.site_url(), 3600);
+        let verifier = TokenVerifier::new(Arc::clone(api));
+
+        let jwt = verifier.verify(SERVER_KEY.sign(&claims)).await.unwrap();
+        assert_eq!(jwt.user_id(), "42");
+        verifier.verify(SERVER_KEY.sign(&claims)).await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+
+        // A key the server does not publish is refused; the keys were only
+        // just fetched, so they are not fetched again
+        assert!(verifier.verify(OTHER_KEY.sign(&claims)).await.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_fetched_again() {
+        let keys = test_keys::key_set(&[&SERVER_KEY]);
+        let now = Instant::now();
+        let a_while_ago = now - MIN_REFETCH_INTERVAL * 2;
+
+        assert!(!needs_fetch(&keys, a_while_ago, Some(SERVER_KEY.kid)));
+        assert!(!needs_fetch(&keys, a_while_ago, None));
+        assert!(needs_fetch(&keys, a_while_ago, Some(OTHER_KEY.kid)));
+        assert!(!needs_fetch(&keys, now, Some(OTHER_KEY.kid)));
+        assert!(needs_fetch(&keys, now - MAX_KEY_AGE * 2, None));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::error::{Result, SyncError};
 use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{AlgorithmFamily, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 
 #[cfg(test)]
@@ -23,6 +26,18 @@ impl UserId {
     }
 }
 
+/// What a token is for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    /// A long-lived token the server issues for provisioning a machine
+    Device,
+    /// A login session or an access token, and kinds this version does not know
+    #[default]
+    #[serde(other)]
+    Session,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub uid: UserId, // User ID (can be integer or string for compatibility)
@@ -30,6 +45,8 @@ pub struct Claims {
     #[serde(default)]
     pub iat: i64, // Issued at (optional for backward compatibility)
     pub email: Option<String>,
+    #[serde(default)]
+    pub kind: TokenKind,
 }
 
 pub struct JwtToken {
@@ -39,8 +56,43 @@ pub struct JwtToken {
 }
 
 impl JwtToken {
+    /// Check a token from the server against its signing `keys`: the
+    /// signature, the issuer, the audience and that it has not expired
+    pub fn verify(token: String, keys: &JwkSet, issuer: &str, audience: &str) -> Result<Self> {
+        let header = jsonwebtoken::decode_header(&token)?;
+        let jwk = match &header.kid {
+            Some(kid) => keys.find(kid),
+            // Without a key id only a single key is unambiguous
+            None if keys.keys.len() == 1 => keys.keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| SyncError::Other("Token is signed with an unknown key".to_string()))?;
+
+        // The key decides the algorithm, so a token cannot pick another one
+        let algorithm = match jwk.common.key_algorithm {
+            Some(algorithm) => algorithm.to_string().parse()?,
+            None => header.alg,
+        };
+        if AlgorithmFamily::Hmac.algorithms().contains(&algorithm) {
+            return Err(SyncError::Jwt(ErrorKind::InvalidAlgorithm.into()));
+        }
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        let data =
+            jsonwebtoken::decode::<Claims>(&token, &DecodingKey::from_jwk(jwk)?, &validation)?;
+
+        Ok(JwtToken {
+            token,
+            claims: data.claims,
+        })
+    }
+
+    /// Claims of a token that was verified before it was stored; the
+    /// signature is not checked again
     pub fn from_string(token: String) -> Result<Self> {
-        // Extract claims without verification for now (similar to desktop app)
         let parts: Vec<&str> = token.split('.').collect();
         if parts.len() != 3 {
             return Err(SyncError::Jwt(jsonwebtoken::errors::Error::from(
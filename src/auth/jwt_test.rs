@@ -1,4 +1,5 @@
 use crate::auth::jwt::{JwtToken, UserId};
+use crate::auth::test_keys::{self, OTHER_KEY, SERVER_KEY};
 use base64::{engine::general_purpose, Engine as _};
 use serde_json::json;
 
@@ -204,3 +205,79 @@ fn test_user_id_as_string() {
     let str_id = UserId::String("user_123".to_string());
     assert_eq!(str_id.as_string(), "user_123");
 }
+
+const ISSUER: &str = "https://cook.md";
+
+fn verify(token: String) -> crate::Result<JwtToken> {
+    let keys = test_keys::key_set(&[&SERVER_KEY]);
+    JwtToken::verify(token, &keys, ISSUER, test_keys::AUDIENCE)
+}
+
+#[test]
+fn test_verify_accepts_server_tokens() {
+    let jwt = verify(SERVER_KEY.sign(&test_keys::claims(ISSUER, 3600))).unwrap();
+
+    assert_eq!(jwt.user_id(), "42");
+    assert_eq!(jwt.claims.email, Some("cook@example.com".to_string()));
+    assert!(!jwt.is_expired());
+}
+
+#[test]
+fn test_verify_rejects_tampered_tokens() {
+    let token = SERVER_KEY.sign(&test_keys::claims(ISSUER, 3600));
+
+    // Someone else's user id with the original signature
+    let mut claims = test_keys::claims(ISSUER, 3600);
+    claims["uid"] = json!(1);
+    let payload = general_purpose::URL_SAFE_NO_PAD.encode(claims.to_string());
+    let parts: Vec<&str> = token.split('.').collect();
+    let tampered = format!("{}.{payload}.{}", parts[0], parts[2]);
+    assert!(verify(tampered).is_err());
+
+    // Signed by a key the server does not publish, claiming a known key id
+    let forged = OTHER_KEY.sign_with_kid(SERVER_KEY.kid, &test_keys::claims(ISSUER, 3600));
+    assert!(verify(forged).is_err());
+    assert!(verify(OTHER_KEY.sign(&test_keys::claims(ISSUER, 3600))).is_err());
+
+    // Unsigned tokens as accepted by from_string
+    assert!(verify(create_jwt_with_claims(test_keys::claims(ISSUER, 3600))).is_err());
+}
+
+#[test]
+fn test_verify_checks_issuer_audience_and_expiry() {
+    let mut wrong_audience = test_keys::claims(ISSUER, 3600);
+    wrong_audience["aud"] = json!("another-app");
+    let mut no_audience = test_keys::claims(ISSUER, 3600);
+    no_audience.as_object_mut().unwrap().remove("aud");
+
+    for claims in [
+        test_keys::claims("https://evil.example", 3600),
+        wrong_audience,
+        no_audience,
+        test_keys::claims(ISSUER, -3600),
+    ] {
+        assert!(verify(SERVER_KEY.sign(&claims)).is_err(), "{claims}");
+    }
+}
+
+#[test]
+fn test_jwt_token_kind() {
+    use crate::auth::jwt::TokenKind;
+
+    let now = chrono::Utc::now().timestamp();
+    let kind = |kind: Option<&str>| {
+        let mut claims = json!({"uid": 1, "exp": now + 3600});
+        if let Some(kind) = kind {
+            claims["kind"] = json!(kind);
+        }
+        JwtToken::from_string(create_jwt_with_claims(claims))
+            .unwrap()
+            .claims
+            .kind
+    };
+
+    assert_eq!(kind(None), TokenKind::Session);
+    assert_eq!(kind(Some("device")), TokenKind::Device);
+    // Kinds added to the server later are treated like sessions
+    assert_eq!(kind(Some("kiosk")), TokenKind::Session);
+}
@@ -1,45 +1,107 @@
+#[cfg(test)]
+#[path = "auth_test.rs"]
+mod auth_test;
+mod callback;
+mod device;
+pub mod jwks;
 pub mod jwt;
+pub mod pkce;
 pub mod secure_session;
+#[cfg(test)]
+pub(crate) mod test_keys;
+#[cfg(test)]
+pub(crate) mod test_server;
 
 use crate::api::CookApi;
 use crate::config::AppPaths;
 use crate::error::{Result, SyncError};
 use log::{debug, error, info};
 use std::sync::{Arc, Mutex};
-use tokio::time::{interval, Duration};
+use tokio::time::{sleep, Duration};
 
+use self::callback::{Callback, CallbackServer};
+use self::jwks::TokenVerifier;
+use self::jwt::TokenKind;
+use self::pkce::Pkce;
+use self::secure_session::keyring_store::{self, KeyringStore};
 use self::secure_session::SecureSession;
 
+/// Environment variable with a token to log in with, for kiosks and CI jobs
+pub const TOKEN_ENV: &str = "COOK_SYNC_TOKEN";
+
+/// The token in `COOK_SYNC_TOKEN`, if it is set
+pub fn token_from_env() -> Option<String> {
+    std::env::var(TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+}
+
 pub struct AuthManager {
     api: Arc<CookApi>,
+    verifier: TokenVerifier,
+    /// Profile whose keyring entries hold the session
+    profile: Mutex<String>,
     session: Arc<Mutex<Option<SecureSession>>>,
+    /// Keyring, or session file on Linux, the sessions are kept in
+    store: Arc<dyn KeyringStore>,
+    /// Held while a refresh runs; a refresh token may only be redeemed once
+    refreshing: tokio::sync::Mutex<()>,
 }
 
 impl AuthManager {
-    pub fn new(_paths: Arc<AppPaths>, api: Arc<CookApi>) -> Result<Self> {
+    pub fn new(paths: Arc<AppPaths>, api: Arc<CookApi>) -> Result<Self> {
+        let store = Arc::new(keyring_store::default_store());
+        Self::with_store(&paths.profile, api, store)
+    }
+
+    fn with_store(profile: &str, api: Arc<CookApi>, store: Arc<dyn KeyringStore>) -> Result<Self> {
         // Load session from keyring
-        let session = SecureSession::load()?;
+        let session = SecureSession::load_from(store.as_ref(), profile)?;
 
         Ok(Self {
+            verifier: TokenVerifier::new(Arc::clone(&api)),
             api,
+            profile: Mutex::new(profile.to_string()),
             session: Arc::new(Mutex::new(session)),
+            store,
+            refreshing: tokio::sync::Mutex::new(()),
         })
     }
 
+    /// Use the session of another profile from now on
+    pub fn switch_profile(&self, profile: &str) -> Result<()> {
+        let session = SecureSession::load_from(self.store.as_ref(), profile)?;
+
+        *self.profile.lock().unwrap() = profile.to_string();
+        *self.session.lock().unwrap() = session;
+        Ok(())
+    }
+
+    fn profile(&self) -> String {
+        self.profile.lock().unwrap().clone()
+    }
+
     pub fn get_session(&self) -> Option<SecureSession> {
         self.session.lock().unwrap().clone()
     }
 
-    pub fn set_session(&self, jwt_token: String) -> Result<()> {
-        let session = SecureSession::new(jwt_token)?;
-        session.save()?;
+    /// Store a token from the server, and the refresh token that renews it,
+    /// once the token is verified
+    pub async fn set_session(
+        &self,
+        jwt_token: String,
+        refresh_token: Option<String>,
+    ) -> Result<()> {
+        let jwt_token = self.verifier.verify(jwt_token).await?.token;
+        let session = SecureSession::new(jwt_token)?.with_refresh_token(refresh_token);
+        session.save_to(self.store.as_ref(), &self.profile())?;
 
         *self.session.lock().unwrap() = Some(session);
         Ok(())
     }
 
     pub fn clear_session(&self) -> Result<()> {
-        SecureSession::delete()?;
+        SecureSession::delete_with_store(self.store.as_ref(), &self.profile())?;
         *self.session.lock().unwrap() = None;
         Ok(())
     }
@@ -58,123 +120,169 @@ impl AuthManager {
 
     pub fn start_refresh_task(self: Arc<Self>) {
         tokio::spawn(async move {
-            let mut refresh_interval = interval(Duration::from_secs(3600)); // Check every hour
-
             loop {
-                refresh_interval.tick().await;
-
-                if let Some(session) = self.get_session() {
-                    match session.jwt_token() {
-                        Ok(jwt) if jwt.should_refresh() => {
-                            info!("JWT token needs refresh");
-
-                            match self.api.refresh_token(&session.jwt).await {
-                                Ok(new_token) => {
-                                    if let Err(e) = self.set_session(new_token) {
-                                        error!("Failed to save refreshed token: {e}");
-                                    } else {
-                                        info!("JWT token refreshed successfully");
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Failed to refresh JWT token: {e}");
-
-                                    // Clear invalid session
-                                    if let Err(e) = self.clear_session() {
-                                        error!("Failed to clear invalid session: {e}");
-                                    }
-                                }
-                            }
-                        }
-                        Ok(_) => {
-                            // Token still valid
-                        }
-                        Err(e) => {
-                            error!("Invalid JWT token: {e}");
+                // Failures are logged by refresh_if_needed and retried later
+                let _ = self.refresh_if_needed().await;
+                sleep(self.next_refresh_check()).await;
+            }
+        });
+    }
+
+    /// When the session's token is due for renewal, checked at least hourly
+    /// and at most every 30 seconds
+    fn next_refresh_check(&self) -> Duration {
+        let due = self.get_session().and_then(|session| {
+            let jwt = session.jwt_token().ok()?;
+            Some(session.refresh_in(&jwt).num_seconds())
+        });
+        Duration::from_secs(due.unwrap_or(3600).clamp(30, 3600) as u64)
+    }
 
-                            // Clear invalid session
+    /// Refresh the JWT if it is close to expiring. An invalid session, or one
+    /// the server refuses to renew, is cleared; after other failures it is
+    /// kept for the next attempt.
+    pub async fn refresh_if_needed(&self) -> Result<()> {
+        // A caller that waited here finds the session already renewed
+        let _refreshing = self.refreshing.lock().await;
+        let Some(session) = self.get_session() else {
+            return Ok(());
+        };
+
+        match session.jwt_token() {
+            Ok(jwt) if session.refresh_due(&jwt) => {
+                info!("JWT token needs refresh");
+
+                match self.renew(&session).await {
+                    Ok(()) => {
+                        info!("JWT token refreshed successfully");
+                        Ok(())
+                    }
+                    Err(e) => {
+                        error!("Failed to refresh JWT token: {e}");
+
+                        if matches!(e, SyncError::AuthenticationRequired) {
                             if let Err(e) = self.clear_session() {
                                 error!("Failed to clear invalid session: {e}");
                             }
                         }
+                        Err(e)
                     }
                 }
             }
-        });
+            Ok(_) => {
+                // Token still valid
+                Ok(())
+            }
+            Err(e) => {
+                error!("Invalid JWT token: {e}");
+
+                // Clear invalid session
+                if let Err(e) = self.clear_session() {
+                    error!("Failed to clear invalid session: {e}");
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Get a new token for `session`, with its refresh token if it has one
+    async fn renew(&self, session: &SecureSession) -> Result<()> {
+        match &session.refresh_token {
+            Some(refresh_token) => {
+                let tokens = self.api.refresh_access_token(refresh_token).await?;
+                // A server that does not rotate refresh tokens keeps the current one
+                let refresh_token = tokens
+                    .refresh_token
+                    .unwrap_or_else(|| refresh_token.clone());
+                self.set_session(tokens.access_token, Some(refresh_token))
+                    .await
+            }
+            None => {
+                let token = match session.jwt_token()?.claims.kind {
+                    TokenKind::Device => self.api.renew_device_token(&session.jwt).await?,
+                    TokenKind::Session => self.api.refresh_token(&session.jwt).await?,
+                };
+                self.set_session(token, None).await
+            }
+        }
+    }
+
+    /// Log in with a token issued by the server, e.g. a device token for
+    /// provisioning a machine
+    pub async fn token_login(&self, token: &str) -> Result<()> {
+        self.set_session(token.trim().to_string(), None).await
+    }
+
+    /// Log in with the token in `COOK_SYNC_TOKEN` unless already logged in,
+    /// so a token renewed since is not replaced by the provisioned one
+    pub async fn login_from_env(&self) -> Result<()> {
+        let Some(token) = token_from_env() else {
+            return Ok(());
+        };
+        if self.is_authenticated() {
+            return Ok(());
+        }
+
+        info!("Logging in with the token from {TOKEN_ENV}");
+        self.token_login(&token).await
     }
 
     pub async fn browser_login(&self) -> Result<()> {
         use std::time::Duration;
-        use tokio::io::{AsyncReadExt, AsyncWriteExt};
-        use tokio::net::TcpListener;
         use tokio::time::timeout;
 
         // Start local HTTP server on random port
-        let listener = TcpListener::bind("127.0.0.1:0").await?;
-        let port = listener.local_addr()?.port();
+        let mut server = CallbackServer::bind().await?;
 
         // Generate state for CSRF protection
         let state = uuid::Uuid::new_v4().to_string();
+        let pkce = Pkce::new()?;
 
-        // Construct callback URL
-        let callback_url = format!("http://127.0.0.1:{port}/auth/callback");
-        let encoded_callback = urlencoding::encode(&callback_url);
+        // The server redirects back here with an authorization code; the
+        // tokens themselves never appear in a URL
+        let redirect_uri = server.redirect_uri();
 
-        // Get base URL from API endpoint
-        let api_endpoint = self.api.base_url();
-        let base_url = api_endpoint.strip_suffix("/api").unwrap_or(api_endpoint);
+        let base_url = self.api.site_url();
 
-        // Open browser to desktop authentication page
-        let login_url =
-            format!("{base_url}/auth/desktops?callback={encoded_callback}&state={state}");
+        // Open browser to the authorization page
+        let login_url = format!(
+            "{base_url}/oauth/authorize?response_type=code&client_id={}&redirect_uri={}\
+             &state={state}&code_challenge={}&code_challenge_method={}",
+            crate::api::endpoints::CLIENT_ID,
+            urlencoding::encode(&redirect_uri),
+            pkce.challenge,
+            pkce::CHALLENGE_METHOD,
+        );
 
         info!("Opening browser for authentication: {login_url}");
         open::that(&login_url)?;
 
         // Wait for callback (with timeout)
         let result = timeout(Duration::from_secs(300), async {
-            let (mut socket, _) = listener.accept().await?;
-
-            // Read initial request
-            let mut buffer = [0; 4096];
-            let n = socket.read(&mut buffer).await?;
-            let request = String::from_utf8_lossy(&buffer[..n]);
-
-            // Check if this is a CORS preflight request
-            if request.starts_with("OPTIONS ") {
-                debug!("Handling CORS preflight request");
-                // Send CORS headers for preflight
-                let cors_response = "HTTP/1.1 200 OK\r\n\
-                    Access-Control-Allow-Origin: *\r\n\
-                    Access-Control-Allow-Methods: GET, OPTIONS\r\n\
-                    Access-Control-Allow-Headers: x-csrf-token, x-turbo-request-id\r\n\
-                    Access-Control-Max-Age: 86400\r\n\
-                    Content-Length: 0\r\n\r\n";
-                socket.write_all(cors_response.as_bytes()).await?;
-
-                // The browser might reuse the same connection or open a new one
-                // Try to read another request on the same connection first
-                let mut buffer = [0; 4096];
-                match tokio::time::timeout(Duration::from_secs(5), socket.read(&mut buffer)).await {
-                    Ok(Ok(n)) if n > 0 => {
-                        let request = String::from_utf8_lossy(&buffer[..n]);
-                        self.handle_callback_request(&mut socket, &request, &state)
-                            .await
+            loop {
+                let callback = server.next_callback().await?;
+
+                // Callbacks of other logins, e.g. from a stale browser tab, are not ours
+                if callback.param("state") != Some(state.as_str()) {
+                    debug!("Ignoring login callback with another state");
+                    let _ = Self::answer_callback(callback, false).await;
+                    continue;
+                }
+
+                // Save the session first, so the browser only reports success for a valid token
+                let saved = match (callback.param("code"), callback.param("error")) {
+                    (Some(code), _) => {
+                        debug!("Authorization code received");
+                        self.redeem_code(code, &pkce.verifier, &redirect_uri).await
                     }
-                    _ => {
-                        // If no data on same connection, accept a new connection
-                        let (mut new_socket, _) = listener.accept().await?;
-                        let mut buffer = [0; 4096];
-                        let n = new_socket.read(&mut buffer).await?;
-                        let request = String::from_utf8_lossy(&buffer[..n]);
-                        self.handle_callback_request(&mut new_socket, &request, &state)
-                            .await
+                    (None, Some(error)) => {
+                        Err(SyncError::Other(format!("Login was refused: {error}")))
                     }
-                }
-            } else {
-                // Regular GET request
-                self.handle_callback_request(&mut socket, &request, &state)
-                    .await
+                    (None, None) => Err(SyncError::AuthenticationRequired),
+                };
+
+                Self::answer_callback(callback, saved.is_ok()).await?;
+                return saved;
             }
         })
         .await;
@@ -186,21 +294,11 @@ impl AuthManager {
         }
     }
 
-    async fn handle_callback_request(
-        &self,
-        socket: &mut tokio::net::TcpStream,
-        request: &str,
-        expected_state: &str,
-    ) -> Result<()> {
-        use tokio::io::AsyncWriteExt;
-
-        debug!("Parsing request for token extraction");
-        if let Some(token) = Self::extract_token_from_request(request, expected_state) {
-            debug!("Token extracted successfully");
-
+    /// Show the browser whether the login succeeded
+    async fn answer_callback(callback: Callback, success: bool) -> Result<()> {
+        if success {
             // Send success response with styled HTML - show "All Done" directly
-            let response = "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nAccess-Control-Allow-Origin: *\r\n\r\n\
-<!DOCTYPE html>
+            let page = "<!DOCTYPE html>
 <html>
 <head>
   <title>Authentication Complete - Cook.md</title>
@@ -279,17 +377,10 @@ impl AuthManager {
   </script>
 </body>
 </html>";
-            socket.write_all(response.as_bytes()).await?;
-
-            // Save the session
-            self.set_session(token)?;
-            Ok(())
+            callback.respond("200 OK", page).await
         } else {
-            debug!("Failed to extract token from request");
-
             // Send error response with styled HTML
-            let response = "HTTP/1.1 400 Bad Request\r\nContent-Type: text/html; charset=utf-8\r\nAccess-Control-Allow-Origin: *\r\n\r\n\
-<!DOCTYPE html>
+            let page = "<!DOCTYPE html>
 <html>
 <head>
   <title>Authentication Failed - Cook.md</title>
@@ -366,60 +457,17 @@ impl AuthManager {
   </div>
 </body>
 </html>";
-            socket.write_all(response.as_bytes()).await?;
-            Err(SyncError::AuthenticationRequired)
+            callback.respond("400 Bad Request", page).await
         }
     }
 
-    fn extract_token_from_request(request: &str, expected_state: &str) -> Option<String> {
-        // Parse GET request for token and state parameters
-        debug!("Full request:\n{request}");
-        let lines: Vec<&str> = request.lines().collect();
-        if lines.is_empty() {
-            return None;
-        }
-
-        let first_line = lines[0];
-        if !first_line.starts_with("GET ") {
-            return None;
-        }
-
-        // Extract path and query string
-        let parts: Vec<&str> = first_line.split(' ').collect();
-        if parts.len() < 2 {
-            return None;
-        }
-
-        let path_and_query = parts[1];
-        debug!("Path and query: {path_and_query}");
-        if let Some(query_start) = path_and_query.find('?') {
-            let query = &path_and_query[query_start + 1..];
-
-            let mut token = None;
-            let mut state = None;
-
-            // Parse query parameters
-            for param in query.split('&') {
-                if let Some(eq_pos) = param.find('=') {
-                    let key = &param[..eq_pos];
-                    let value = &param[eq_pos + 1..];
-
-                    match key {
-                        "token" => token = Some(urlencoding::decode(value).ok()?.into_owned()),
-                        "state" => state = Some(urlencoding::decode(value).ok()?.into_owned()),
-                        _ => {}
-                    }
-                }
-            }
-
-            // Verify state matches
-            if state.as_deref() == Some(expected_state) {
-                token
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+    /// Exchange the code from the callback for tokens and store them
+    async fn redeem_code(&self, code: &str, code_verifier: &str, redirect_uri: &str) -> Result<()> {
+        let tokens = self
+            .api
+            .exchange_code(code, code_verifier, redirect_uri)
+            .await?;
+        self.set_session(tokens.access_token, tokens.refresh_token)
+            .await
     }
 }
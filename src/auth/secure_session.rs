@@ -1,9 +1,11 @@
-use super::jwt::JwtToken;
+use super::jwt::{JwtToken, TokenKind};
+use crate::config::profiles::DEFAULT_PROFILE;
 use crate::error::Result;
+use chrono::Duration;
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 
-mod keyring_store;
+pub(super) mod keyring_store;
 use keyring_store::KeyringStore;
 
 #[cfg(test)]
@@ -21,12 +23,31 @@ const SERVICE_NAME: &str = "cook.md-sync-agent";
 const JWT_KEY: &str = "jwt_token";
 const USER_ID_KEY: &str = "user_id";
 const EMAIL_KEY: &str = "user_email";
+const REFRESH_TOKEN_KEY: &str = "refresh_token";
+
+/// Short-lived access tokens are renewed at most this long before they expire
+const ACCESS_TOKEN_MARGIN: Duration = Duration::minutes(5);
+/// Device tokens are renewed at most this long before they expire
+const DEVICE_TOKEN_MARGIN: Duration = Duration::days(7);
+
+/// Keyring entry name of `key` for `profile`; the default profile keeps the
+/// entries written before profiles existed
+fn profile_key(profile: &str, key: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        key.to_string()
+    } else {
+        format!("{profile}/{key}")
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecureSession {
     pub jwt: String,
     pub user_id: String,
     pub email: Option<String>,
+    /// Renews `jwt` when it is a short-lived access token from an OAuth login
+    #[serde(default)]
+    pub refresh_token: Option<String>,
 }
 
 impl SecureSession {
@@ -37,13 +58,41 @@ impl SecureSession {
             jwt: jwt_string,
             user_id: jwt.user_id(),
             email: jwt.claims.email.clone(),
+            refresh_token: None,
         })
     }
 
-    pub fn load() -> Result<Option<Self>> {
-        info!("Loading session from secure store");
-        let store = keyring_store::default_store();
-        let result = Self::load_with_store(&store);
+    pub fn with_refresh_token(mut self, refresh_token: Option<String>) -> Self {
+        self.refresh_token = refresh_token;
+        self
+    }
+
+    /// Time until `jwt`, the token of this session, should be renewed: an hour
+    /// before a session token expires, and in the last quarter of the lifetime
+    /// of an access token that comes with a refresh token or of a device token
+    pub fn refresh_in(&self, jwt: &JwtToken) -> Duration {
+        let margin = match (&self.refresh_token, jwt.claims.kind) {
+            (Some(_), _) => quarter_lifetime(jwt, ACCESS_TOKEN_MARGIN),
+            (None, TokenKind::Device) => quarter_lifetime(jwt, DEVICE_TOKEN_MARGIN),
+            (None, TokenKind::Session) => Duration::hours(1),
+        };
+        jwt.expires_in() - margin
+    }
+
+    pub fn refresh_due(&self, jwt: &JwtToken) -> bool {
+        match (&self.refresh_token, jwt.claims.kind) {
+            (None, TokenKind::Session) => jwt.should_refresh(),
+            _ => self.refresh_in(jwt) <= Duration::zero(),
+        }
+    }
+
+    pub fn load(profile: &str) -> Result<Option<Self>> {
+        Self::load_from(&keyring_store::default_store(), profile)
+    }
+
+    pub(super) fn load_from(store: &dyn KeyringStore, profile: &str) -> Result<Option<Self>> {
+        info!("Loading session of profile '{profile}' from secure store");
+        let result = Self::load_with_store(store, profile);
         match &result {
             Ok(Some(_)) => info!("Session loaded successfully from secure store"),
             Ok(None) => info!("No session found in secure store"),
@@ -52,48 +101,52 @@ impl SecureSession {
         result
     }
 
-    fn load_with_store(store: &dyn KeyringStore) -> Result<Option<Self>> {
+    fn load_with_store(store: &dyn KeyringStore, profile: &str) -> Result<Option<Self>> {
         // Try to load JWT from keyring
-        let jwt = match store.get_password(SERVICE_NAME, JWT_KEY)? {
+        let jwt = match store.get_password(SERVICE_NAME, &profile_key(profile, JWT_KEY))? {
             Some(jwt) => jwt,
             None => return Ok(None),
         };
 
+        let refresh_token =
+            store.get_password(SERVICE_NAME, &profile_key(profile, REFRESH_TOKEN_KEY))?;
+
         // Validate JWT is not expired
         let jwt_token = match JwtToken::from_string(jwt.clone()) {
             Ok(token) => token,
             Err(_) => {
                 // Invalid JWT, clean up
-                let _ = Self::delete_with_store(store);
+                let _ = Self::delete_with_store(store, profile);
                 return Ok(None);
             }
         };
 
-        if jwt_token.is_expired() {
+        // An expired access token is renewed with the refresh token
+        if jwt_token.is_expired() && refresh_token.is_none() {
             // Clean up expired token
-            let _ = Self::delete_with_store(store);
+            let _ = Self::delete_with_store(store, profile);
             return Ok(None);
         }
 
         // Load user_id
         let user_id = store
-            .get_password(SERVICE_NAME, USER_ID_KEY)?
+            .get_password(SERVICE_NAME, &profile_key(profile, USER_ID_KEY))?
             .unwrap_or_else(|| jwt_token.user_id());
 
         // Load email (optional)
-        let email = store.get_password(SERVICE_NAME, EMAIL_KEY)?;
+        let email = store.get_password(SERVICE_NAME, &profile_key(profile, EMAIL_KEY))?;
 
         Ok(Some(SecureSession {
             jwt,
             user_id,
             email,
+            refresh_token,
         }))
     }
 
-    pub fn save(&self) -> Result<()> {
-        info!("Saving session to secure store");
-        let store = keyring_store::default_store();
-        let result = self.save_with_store(&store);
+    pub(super) fn save_to(&self, store: &dyn KeyringStore, profile: &str) -> Result<()> {
+        info!("Saving session of profile '{profile}' to secure store");
+        let result = self.save_with_store(store, profile);
         match &result {
             Ok(_) => info!("Session saved successfully to secure store"),
             Err(e) => error!("Failed to save session to secure store: {e}"),
@@ -101,35 +154,49 @@ impl SecureSession {
         result
     }
 
-    fn save_with_store(&self, store: &dyn KeyringStore) -> Result<()> {
+    fn save_with_store(&self, store: &dyn KeyringStore, profile: &str) -> Result<()> {
         // Save JWT
-        store.set_password(SERVICE_NAME, JWT_KEY, &self.jwt)?;
+        store.set_password(SERVICE_NAME, &profile_key(profile, JWT_KEY), &self.jwt)?;
 
         // Save user_id
-        store.set_password(SERVICE_NAME, USER_ID_KEY, &self.user_id)?;
+        store.set_password(
+            SERVICE_NAME,
+            &profile_key(profile, USER_ID_KEY),
+            &self.user_id,
+        )?;
 
         // Save email if present
         if let Some(email) = &self.email {
-            store.set_password(SERVICE_NAME, EMAIL_KEY, email)?;
+            store.set_password(SERVICE_NAME, &profile_key(profile, EMAIL_KEY), email)?;
+        }
+
+        // A session token replacing an OAuth login must not keep its refresh token
+        let refresh_key = profile_key(profile, REFRESH_TOKEN_KEY);
+        match &self.refresh_token {
+            Some(refresh_token) => store.set_password(SERVICE_NAME, &refresh_key, refresh_token)?,
+            None => store.delete_password(SERVICE_NAME, &refresh_key)?,
         }
 
         Ok(())
     }
 
-    pub fn delete() -> Result<()> {
+    pub fn delete(profile: &str) -> Result<()> {
         let store = keyring_store::default_store();
-        Self::delete_with_store(&store)
+        Self::delete_with_store(&store, profile)
     }
 
-    fn delete_with_store(store: &dyn KeyringStore) -> Result<()> {
+    pub(super) fn delete_with_store(store: &dyn KeyringStore, profile: &str) -> Result<()> {
         // Delete JWT
-        store.delete_password(SERVICE_NAME, JWT_KEY)?;
+        store.delete_password(SERVICE_NAME, &profile_key(profile, JWT_KEY))?;
 
         // Delete user_id
-        store.delete_password(SERVICE_NAME, USER_ID_KEY)?;
+        store.delete_password(SERVICE_NAME, &profile_key(profile, USER_ID_KEY))?;
 
         // Delete email
-        store.delete_password(SERVICE_NAME, EMAIL_KEY)?;
+        store.delete_password(SERVICE_NAME, &profile_key(profile, EMAIL_KEY))?;
+
+        // Delete refresh token
+        store.delete_password(SERVICE_NAME, &profile_key(profile, REFRESH_TOKEN_KEY))?;
 
         Ok(())
     }
@@ -139,18 +206,27 @@ impl SecureSession {
     }
 }
 
+/// A quarter of the lifetime of `jwt`, at most `max`
+fn quarter_lifetime(jwt: &JwtToken, max: Duration) -> Duration {
+    if jwt.claims.iat > 0 {
+        (Duration::seconds(jwt.claims.exp - jwt.claims.iat) / 4).min(max)
+    } else {
+        max
+    }
+}
+
 // Test helpers
 #[cfg(test)]
 impl SecureSession {
     pub fn load_with_mock(mock: &MockKeyring) -> Result<Option<Self>> {
-        Self::load_with_store(mock)
+        Self::load_with_store(mock, DEFAULT_PROFILE)
     }
 
     pub fn save_with_mock(&self, mock: &MockKeyring) -> Result<()> {
-        self.save_with_store(mock)
+        self.save_with_store(mock, DEFAULT_PROFILE)
     }
 
     pub fn delete_with_mock(mock: &MockKeyring) -> Result<()> {
-        Self::delete_with_store(mock)
+        Self::delete_with_store(mock, DEFAULT_PROFILE)
     }
 }
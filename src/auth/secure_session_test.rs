@@ -47,6 +47,7 @@ fn test_secure_session_save_and_load() {
         jwt: jwt.clone(),
         user_id: "test_user".to_string(),
         email: Some("test@example.com".to_string()),
+        refresh_token: None,
     };
 
     // Save session
@@ -98,6 +99,7 @@ fn test_secure_session_auto_cleanup_expired_token() {
         jwt: expired_jwt,
         user_id: "expired_user".to_string(),
         email: Some("expired@example.com".to_string()),
+        refresh_token: None,
     };
 
     // Save the expired session
@@ -133,6 +135,7 @@ fn test_secure_session_delete() {
         jwt,
         user_id: "delete_test_user".to_string(),
         email: None,
+        refresh_token: None,
     };
 
     assert!(session.save_with_mock(&mock).is_ok());
@@ -171,6 +174,7 @@ fn test_secure_session_jwt_token_method() {
         jwt: jwt.clone(),
         user_id: "method_test_user".to_string(),
         email: Some("method@test.com".to_string()),
+        refresh_token: None,
     };
 
     let jwt_token = session.jwt_token();
@@ -191,6 +195,7 @@ fn test_secure_session_without_email() {
         jwt: jwt.clone(),
         user_id: "no_email_user".to_string(),
         email: None,
+        refresh_token: None,
     };
 
     // Save and load
@@ -213,3 +218,106 @@ fn test_secure_session_with_integer_user_id() {
     let session = session.unwrap();
     assert_eq!(session.user_id, "12345");
 }
+
+#[test]
+fn test_secure_session_profiles_are_separate() {
+    let mock = MockKeyring::new();
+
+    let personal = SecureSession::new(create_test_jwt("1", Some("me@home.com"), 3600)).unwrap();
+    let work = SecureSession::new(create_test_jwt("2", Some("me@work.com"), 3600)).unwrap();
+    personal.save_with_mock(&mock).unwrap();
+    work.save_with_store(&mock, "work").unwrap();
+
+    let loaded = SecureSession::load_with_store(&mock, "work")
+        .unwrap()
+        .unwrap();
+    assert_eq!(loaded.email.as_deref(), Some("me@work.com"));
+    assert!(SecureSession::load_with_store(&mock, "other")
+        .unwrap()
+        .is_none());
+
+    // Logging out of one profile leaves the other signed in
+    SecureSession::delete_with_store(&mock, "work").unwrap();
+    assert!(SecureSession::load_with_store(&mock, "work")
+        .unwrap()
+        .is_none());
+    let loaded = SecureSession::load_with_mock(&mock).unwrap().unwrap();
+    assert_eq!(loaded.email.as_deref(), Some("me@home.com"));
+}
+
+#[test]
+fn test_secure_session_keeps_refresh_token() {
+    let mock = MockKeyring::new();
+
+    // An expired access token is kept while a refresh token can renew it
+    let session = SecureSession::new(create_test_jwt("oauth_user", None, -60))
+        .unwrap()
+        .with_refresh_token(Some("refresh-1".to_string()));
+    session.save_with_mock(&mock).unwrap();
+
+    let loaded = SecureSession::load_with_mock(&mock).unwrap().unwrap();
+    assert_eq!(loaded.refresh_token.as_deref(), Some("refresh-1"));
+    assert!(loaded.refresh_due(&loaded.jwt_token().unwrap()));
+
+    // A session token saved over it drops the refresh token
+    let session = SecureSession::new(create_test_jwt("oauth_user", None, 3600)).unwrap();
+    session.save_with_mock(&mock).unwrap();
+    let loaded = SecureSession::load_with_mock(&mock).unwrap().unwrap();
+    assert_eq!(loaded.refresh_token, None);
+
+    SecureSession::delete_with_mock(&mock).unwrap();
+    assert!(SecureSession::load_with_mock(&mock).unwrap().is_none());
+}
+
+#[test]
+fn test_access_tokens_are_renewed_late_in_their_lifetime() {
+    let refresh_token = Some("refresh-1".to_string());
+    let session = |expires_in| {
+        SecureSession::new(create_test_jwt("oauth_user", None, expires_in))
+            .unwrap()
+            .with_refresh_token(refresh_token.clone())
+    };
+
+    // An hour-long access token is renewed 5 minutes before it expires,
+    // a 10 minute one in its last 150 seconds
+    let fresh = session(60 * 60);
+    let jwt = fresh.jwt_token().unwrap();
+    assert!(!fresh.refresh_due(&jwt));
+    let refresh_in = fresh.refresh_in(&jwt).num_seconds();
+    assert!((3295..=3300).contains(&refresh_in), "{refresh_in}");
+
+    let short = session(10 * 60);
+    let refresh_in = short.refresh_in(&short.jwt_token().unwrap()).num_seconds();
+    assert!((445..=450).contains(&refresh_in), "{refresh_in}");
+
+    // Session tokens without a refresh token keep the hour of margin
+    let legacy = SecureSession::new(create_test_jwt("user", None, 30 * 60)).unwrap();
+    assert!(legacy.refresh_due(&legacy.jwt_token().unwrap()));
+}
+
+#[test]
+fn test_device_tokens_are_renewed_a_week_before_expiry() {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let now = chrono::Utc::now().timestamp();
+    let day = 24 * 3600;
+    let device_token = |lifetime: i64| {
+        let header = general_purpose::STANDARD_NO_PAD.encode(r#"{"alg":"ES256"}"#);
+        let claims = serde_json::json!({
+            "uid": 7, "kind": "device", "iat": now, "exp": now + lifetime
+        });
+        let payload = general_purpose::STANDARD_NO_PAD.encode(claims.to_string());
+        SecureSession::new(format!("{header}.{payload}.signature")).unwrap()
+    };
+
+    // A 90 day token waits 83 days, a 2 day one a day and a half
+    let session = device_token(90 * day);
+    let jwt = session.jwt_token().unwrap();
+    assert!(!session.refresh_due(&jwt));
+    assert!((session.refresh_in(&jwt).num_seconds() - 83 * day).abs() <= 5);
+
+    let session = device_token(2 * day);
+    let jwt = session.jwt_token().unwrap();
+    assert!(!session.refresh_due(&jwt));
+    assert!((session.refresh_in(&jwt).num_seconds() - 36 * 3600).abs() <= 5);
+}
@@ -0,0 +1,89 @@
+//! A local stand-in for the Cook.md server in tests
+
+use crate::api::CookApi;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub struct TestServer {
+    pub api: Arc<CookApi>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    /// Answers every request with the status line and JSON body `respond`
+    /// returns for it
+    pub async fn start(respond: impl Fn(&str) -> (&'static str, String) + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&requests);
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut socket).await;
+                let (status, body) = respond(&request);
+                received.lock().unwrap().push(request);
+
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let api = CookApi::new(format!("http://127.0.0.1:{port}/api")).unwrap();
+        Self {
+            api: Arc::new(api),
+            requests,
+        }
+    }
+
+    /// Answers each request with the next of `responses`, after checking
+    /// that it contains the expected text
+    pub async fn replay(responses: Vec<(&'static str, &'static str, &'static str)>) -> Self {
+        let responses = Mutex::new(VecDeque::from(responses));
+        Self::start(move |request| {
+            let (expected, status, body) = responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no response left");
+            assert!(request.contains(expected), "request without {expected}");
+            (status, body.to_string())
+        })
+        .await
+    }
+
+    /// Requests received so far, with their headers and bodies
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Read one request: the headers, then as much body as they announce
+async fn read_request(socket: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        let text = String::from_utf8_lossy(&request);
+        if let Some(end) = text.find("\r\n\r\n") {
+            let length = text[..end]
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .map_or(0, |(_, value)| value.trim().parse().unwrap());
+            if request.len() >= end + 4 + length {
+                return text.into_owned();
+            }
+        }
+
+        let n = socket.read(&mut buffer).await.unwrap();
+        assert!(n > 0, "connection closed mid-request");
+        request.extend_from_slice(&buffer[..n]);
+    }
+}
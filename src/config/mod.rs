@@ -1,5 +1,6 @@
 pub mod constants;
 pub mod paths;
+pub mod profiles;
 pub mod settings;
 
 use crate::error::Result;
@@ -8,8 +9,11 @@ use std::sync::{Arc, Mutex};
 pub use paths::AppPaths;
 pub use settings::Settings;
 
+use profiles::Profiles;
+
 pub struct Config {
-    paths: AppPaths,
+    /// Locations of the active profile; replaced when switching profiles
+    paths: Mutex<AppPaths>,
     settings: Arc<Mutex<Settings>>,
 }
 
@@ -19,26 +23,56 @@ impl Config {
         let settings = Settings::load(&paths.settings_file)?;
 
         Ok(Config {
-            paths,
+            paths: Mutex::new(paths),
             settings: Arc::new(Mutex::new(settings)),
         })
     }
 
     pub fn paths(&self) -> Arc<AppPaths> {
-        Arc::new(self.paths.clone())
+        Arc::new(self.paths.lock().unwrap().clone())
+    }
+
+    /// Name of the active profile
+    pub fn profile(&self) -> String {
+        self.paths.lock().unwrap().profile.clone()
+    }
+
+    pub fn profiles(&self) -> Result<Profiles> {
+        Profiles::load(&self.paths.lock().unwrap().profiles_file)
+    }
+
+    /// Make `name` the active profile and load its settings
+    pub fn switch_profile(&self, name: &str) -> Result<()> {
+        let mut profiles = self.profiles()?;
+        profiles.switch(name)?;
+
+        let paths = AppPaths::for_profile(name)?;
+        let settings = Settings::load(&paths.settings_file)?;
+        profiles.save(&paths.profiles_file)?;
+
+        *self.paths.lock().unwrap() = paths;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
     }
 
     pub fn settings(&self) -> Arc<Mutex<Settings>> {
         Arc::clone(&self.settings)
     }
 
+    /// Re-read settings from disk, picking up changes made by another process
+    pub fn reload_settings(&self) -> Result<()> {
+        let settings = Settings::load(&self.paths().settings_file)?;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
     pub fn update_settings<F>(&self, updater: F) -> Result<()>
     where
         F: FnOnce(&mut Settings),
     {
         let mut settings = self.settings.lock().unwrap();
         updater(&mut settings);
-        settings.save(&self.paths.settings_file)?;
+        settings.save(&self.paths().settings_file)?;
         Ok(())
     }
 }
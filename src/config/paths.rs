@@ -1,27 +1,53 @@
+use crate::config::profiles::{Profiles, DEFAULT_PROFILE};
+use crate::config::settings::SyncRoot;
 use crate::error::{Result, SyncError};
 use dirs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone)]
 pub struct AppPaths {
+    /// Profile the settings, session and databases belong to
+    pub profile: String,
     #[allow(dead_code)]
     pub config_dir: PathBuf,
     #[allow(dead_code)]
     pub data_dir: PathBuf,
     pub settings_file: PathBuf,
+    /// Known profiles and the active one, shared by all profiles
+    pub profiles_file: PathBuf,
     #[allow(dead_code)]
     pub session_file: PathBuf,
     #[allow(dead_code)]
     pub profile_file: PathBuf,
     pub database_file: PathBuf,
+    /// History of sync runs shown by `cook-sync history`
+    pub activity_file: PathBuf,
     #[allow(dead_code)]
     pub updates_file: PathBuf,
     pub log_file: PathBuf,
     pub pid_file: PathBuf,
+    /// Unix domain socket used by the CLI to talk to the running daemon, in a
+    /// folder only the current user can open
+    pub socket_file: PathBuf,
 }
 
 impl AppPaths {
+    /// Locations of the active profile
     pub fn new() -> Result<Self> {
+        let paths = Self::for_profile(DEFAULT_PROFILE)?;
+        let active = Profiles::load(&paths.profiles_file)?.active;
+
+        if active == DEFAULT_PROFILE {
+            Ok(paths)
+        } else {
+            Self::for_profile(&active)
+        }
+    }
+
+    /// Locations of `profile`. The default profile keeps the files at the top
+    /// of the config and data directories; the PID file, socket and logs are
+    /// shared because only one agent runs at a time.
+    pub fn for_profile(profile: &str) -> Result<Self> {
         let app_name = "cook-sync";
 
         let config_dir = dirs::config_dir()
@@ -47,19 +73,68 @@ impl AppPaths {
         std::fs::create_dir_all(&data_dir)?;
         std::fs::create_dir_all(&cache_dir)?;
 
+        let (profile_config_dir, profile_data_dir) = if profile == DEFAULT_PROFILE {
+            (config_dir.clone(), data_dir.clone())
+        } else {
+            let dirs = Self::profile_dirs(&config_dir, &data_dir, profile);
+            std::fs::create_dir_all(&dirs.0)?;
+            std::fs::create_dir_all(&dirs.1)?;
+            dirs
+        };
+
         Ok(AppPaths {
-            settings_file: config_dir.join("settings.json"),
+            profile: profile.to_string(),
+            settings_file: profile_config_dir.join("settings.json"),
+            profiles_file: config_dir.join("profiles.json"),
             session_file: config_dir.join("session.json"),
             profile_file: config_dir.join("profile.json"),
-            database_file: data_dir.join("sync.db"),
+            database_file: profile_data_dir.join("sync.db"),
+            activity_file: profile_data_dir.join("activity.db"),
             updates_file: data_dir.join("updates.json"),
             log_file: cache_dir.join("cook-sync.log"),
             pid_file: cache_dir.join("cook-sync.pid"),
+            socket_file: cache_dir.join("control").join("cook-sync.sock"),
             config_dir,
             data_dir,
         })
     }
 
+    /// Sync database of a synced folder; the main folder keeps the original file
+    pub fn root_database_file(&self, root_id: &str) -> PathBuf {
+        if root_id == SyncRoot::MAIN_ID {
+            self.database_file.clone()
+        } else {
+            self.database_file
+                .with_file_name("roots")
+                .join(format!("{root_id}.db"))
+        }
+    }
+
+    /// Config and data directories holding the files of a non-default profile
+    fn profile_dirs(config_dir: &Path, data_dir: &Path, profile: &str) -> (PathBuf, PathBuf) {
+        (
+            config_dir.join("profiles").join(profile),
+            data_dir.join("profiles").join(profile),
+        )
+    }
+
+    /// Delete the settings and databases of a non-default profile
+    pub fn remove_profile_data(&self, profile: &str) -> Result<()> {
+        if profile == DEFAULT_PROFILE {
+            return Err(SyncError::InvalidConfiguration(
+                "The default profile cannot be removed".to_string(),
+            ));
+        }
+
+        let (config_dir, data_dir) = Self::profile_dirs(&self.config_dir, &self.data_dir, profile);
+        for dir in [config_dir, data_dir] {
+            if dir.exists() {
+                std::fs::remove_dir_all(dir)?;
+            }
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn recipes_dir(&self) -> Option<PathBuf> {
         dirs::document_dir().map(|d| d.join("CookRecipes"))
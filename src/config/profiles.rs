@@ -0,0 +1,136 @@
+//! Named account profiles, e.g. a personal and a work cook.md account.
+//!
+//! Each profile has its own session in the keyring, its own settings (and so
+//! its own recipe folders) and its own sync databases. The default profile
+//! uses the locations from before profiles existed.
+
+use crate::error::{Result, SyncError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[cfg(test)]
+#[path = "profiles_test.rs"]
+mod profiles_test;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const MAX_NAME_LEN: usize = 32;
+
+/// The profiles that exist and which one is active, kept in `profiles.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profiles {
+    #[serde(default = "default_profile")]
+    pub active: String,
+    /// Profiles besides the default one
+    #[serde(default)]
+    pub extra: Vec<String>,
+}
+
+fn default_profile() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self {
+            active: default_profile(),
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl Profiles {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut profiles: Self = if path.exists() {
+            let content = std::fs::read_to_string(path)?;
+            serde_json::from_str(&content)?
+        } else {
+            Self::default()
+        };
+
+        // Fall back to the default profile if the active one was removed by hand
+        if !profiles.contains(&profiles.active) {
+            profiles.active = default_profile();
+        }
+
+        Ok(profiles)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// All profile names, the default profile first
+    pub fn names(&self) -> Vec<String> {
+        std::iter::once(default_profile())
+            .chain(self.extra.iter().cloned())
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        name == DEFAULT_PROFILE || self.extra.iter().any(|p| p == name)
+    }
+
+    pub fn add(&mut self, name: &str) -> Result<()> {
+        validate_name(name)?;
+        if self.contains(name) {
+            return Err(SyncError::InvalidConfiguration(format!(
+                "Profile '{name}' already exists"
+            )));
+        }
+
+        self.extra.push(name.to_string());
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        if name == DEFAULT_PROFILE {
+            return Err(SyncError::InvalidConfiguration(
+                "The default profile cannot be removed".to_string(),
+            ));
+        }
+        if name == self.active {
+            return Err(SyncError::InvalidConfiguration(format!(
+                "Profile '{name}' is active; switch to another profile first"
+            )));
+        }
+        if !self.contains(name) {
+            return Err(unknown_profile(name));
+        }
+
+        self.extra.retain(|p| p != name);
+        Ok(())
+    }
+
+    pub fn switch(&mut self, name: &str) -> Result<()> {
+        if !self.contains(name) {
+            return Err(unknown_profile(name));
+        }
+
+        self.active = name.to_string();
+        Ok(())
+    }
+}
+
+/// Profile names end up in file paths and keyring entries, so keep them simple
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(SyncError::InvalidConfiguration(format!(
+            "Invalid profile name '{name}': use up to {MAX_NAME_LEN} letters, digits, '-' or '_'"
+        )))
+    }
+}
+
+fn unknown_profile(name: &str) -> SyncError {
+    SyncError::InvalidConfiguration(format!("No profile named '{name}'"))
+}
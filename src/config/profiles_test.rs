@@ -0,0 +1,59 @@
+use super::{validate_name, Profiles, DEFAULT_PROFILE};
+use tempfile::TempDir;
+
+#[test]
+fn test_profiles_default_to_default_profile() {
+    let temp_dir = TempDir::new().unwrap();
+    let profiles = Profiles::load(&temp_dir.path().join("profiles.json")).unwrap();
+
+    assert_eq!(profiles.active, DEFAULT_PROFILE);
+    assert_eq!(profiles.names(), vec![DEFAULT_PROFILE.to_string()]);
+}
+
+#[test]
+fn test_profiles_add_switch_remove() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("profiles.json");
+
+    let mut profiles = Profiles::default();
+    profiles.add("work").unwrap();
+    assert!(profiles.add("work").is_err());
+    assert!(profiles.add(DEFAULT_PROFILE).is_err());
+
+    profiles.switch("work").unwrap();
+    assert!(profiles.switch("personal").is_err());
+    profiles.save(&path).unwrap();
+
+    let mut loaded = Profiles::load(&path).unwrap();
+    assert_eq!(loaded, profiles);
+    assert_eq!(loaded.names(), vec!["default", "work"]);
+
+    // Neither the active nor the default profile can be removed
+    assert!(loaded.remove("work").is_err());
+    assert!(loaded.remove(DEFAULT_PROFILE).is_err());
+    loaded.switch(DEFAULT_PROFILE).unwrap();
+    loaded.remove("work").unwrap();
+    assert!(loaded.remove("work").is_err());
+    assert_eq!(loaded.names(), vec!["default"]);
+}
+
+#[test]
+fn test_profiles_with_missing_active_profile_fall_back_to_default() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("profiles.json");
+    std::fs::write(&path, r#"{"active": "gone", "extra": ["work"]}"#).unwrap();
+
+    let profiles = Profiles::load(&path).unwrap();
+    assert_eq!(profiles.active, DEFAULT_PROFILE);
+    assert!(profiles.contains("work"));
+}
+
+#[test]
+fn test_validate_profile_name() {
+    assert!(validate_name("work").is_ok());
+    assert!(validate_name("Family_2").is_ok());
+    assert!(validate_name("").is_err());
+    assert!(validate_name("../work").is_err());
+    assert!(validate_name("my work").is_err());
+    assert!(validate_name(&"a".repeat(33)).is_err());
+}
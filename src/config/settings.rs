@@ -1,19 +1,167 @@
 use crate::config::constants;
-use crate::error::Result;
+use crate::error::{Result, SyncError};
+use log::warn;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
+    /// Main recipes folder, synced with the root of the account
     pub recipes_dir: Option<PathBuf>,
+    /// Further folders, each synced with its own folder on the server
+    #[serde(default)]
+    pub sync_roots: Vec<SyncRoot>,
+    /// Legacy polling interval, kept so older settings files still load.
+    /// Local changes are picked up by the file watcher instead.
     pub sync_interval_secs: u64,
+    /// How often to sync when neither local edits nor remote notifications arrive
+    #[serde(default = "default_fallback_interval_secs")]
+    pub fallback_interval_secs: u64,
     pub auto_start: bool,
     pub auto_update: bool,
     pub show_notifications: bool,
     #[serde(default)]
     pub welcome_shown: bool,
     #[serde(default)]
+    pub sync_direction: SyncDirection,
+    /// Gitignore-style patterns excluded in every synced folder, on top of
+    /// each folder's `.cooksyncignore`
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    #[serde(default)]
     pub update_settings: UpdateSettings,
+    /// How failed sync passes are retried
+    #[serde(default)]
+    pub retry: RetrySettings,
+    /// Transfer rate limits and what to do on metered connections
+    #[serde(default)]
+    pub bandwidth: BandwidthSettings,
+    /// Sync hours and timed pauses
+    #[serde(default)]
+    pub schedule: ScheduleSettings,
+    /// Commands run on sync events
+    #[serde(default)]
+    pub hooks: HookSettings,
+    /// Local HTTP endpoint for health checks and metrics
+    #[serde(default)]
+    pub status_server: StatusServerSettings,
+    #[serde(default)]
+    pub logging: LogSettings,
+}
+
+/// A recipes folder synced with a folder on the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRoot {
+    /// Stable identifier, names the folder's sync database
+    pub id: String,
+    pub path: PathBuf,
+    /// Folder on the server, e.g. `Shared`; empty for the account root
+    pub remote_folder: String,
+    #[serde(default)]
+    pub sync_direction: SyncDirection,
+}
+
+impl SyncRoot {
+    /// Id of the root built from `recipes_dir`
+    pub const MAIN_ID: &'static str = "main";
+
+    pub fn new(path: PathBuf, remote_folder: &str) -> Result<Self> {
+        Ok(Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            path,
+            remote_folder: normalize_remote_folder(remote_folder)?,
+            sync_direction: SyncDirection::default(),
+        })
+    }
+
+    pub fn is_main(&self) -> bool {
+        self.id == Self::MAIN_ID
+    }
+}
+
+/// Turn user input like `/Shared/` into the form used in server paths (`Shared`)
+pub fn normalize_remote_folder(folder: &str) -> Result<String> {
+    let normalized = folder.replace('\\', "/");
+    let parts: Vec<&str> = normalized.split('/').filter(|p| !p.is_empty()).collect();
+
+    if parts.is_empty() {
+        return Err(SyncError::InvalidConfiguration(
+            "Remote folder must not be empty".to_string(),
+        ));
+    }
+    if parts.iter().any(|p| *p == "." || *p == "..") {
+        return Err(SyncError::InvalidConfiguration(format!(
+            "Invalid remote folder: {folder}"
+        )));
+    }
+
+    Ok(parts.join("/"))
+}
+
+/// Whether one of the paths is inside the other (or they are the same)
+fn paths_overlap(a: &Path, b: &Path) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+/// Which way changes flow between the recipes directory and the server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    #[default]
+    Both,
+    /// Push local changes, never apply remote ones
+    UploadOnly,
+    /// Mirror the server, never push local changes
+    DownloadOnly,
+}
+
+impl SyncDirection {
+    pub const ALL: [SyncDirection; 3] = [Self::Both, Self::UploadOnly, Self::DownloadOnly];
+
+    pub fn uploads(self) -> bool {
+        self != Self::DownloadOnly
+    }
+
+    pub fn downloads(self) -> bool {
+        self != Self::UploadOnly
+    }
+
+    /// Value used in the settings file and on the command line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Both => "both",
+            Self::UploadOnly => "upload_only",
+            Self::DownloadOnly => "download_only",
+        }
+    }
+
+    /// Human readable name for menus
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Both => "Upload and download",
+            Self::UploadOnly => "Upload only",
+            Self::DownloadOnly => "Download only",
+        }
+    }
+}
+
+impl std::fmt::Display for SyncDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for SyncDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|d| d.as_str() == s.replace('-', "_"))
+            .ok_or_else(|| {
+                format!("Unknown sync direction '{s}', expected both, upload_only or download_only")
+            })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,16 +185,378 @@ impl Default for UpdateSettings {
     }
 }
 
+/// Backoff after a failed sync pass. Waits are randomised between the base
+/// delay and three times the previous wait, never longer than the maximum.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrySettings {
+    /// Attempts after the first failure before giving up until the next change
+    pub max_retries: usize,
+    pub base_delay_secs: u64,
+    pub max_delay_secs: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay_secs: 5,
+            max_delay_secs: 300,
+        }
+    }
+}
+
+impl RetrySettings {
+    /// Upper bound for `max_retries`; beyond it the offline wait is the better tool
+    pub const MAX_RETRIES: usize = 20;
+    /// Upper bound for `max_delay_secs`: one day
+    pub const MAX_DELAY_SECS: u64 = 24 * 60 * 60;
+
+    pub fn validate(&self) -> Result<()> {
+        if self.max_retries > Self::MAX_RETRIES {
+            return Err(SyncError::InvalidConfiguration(format!(
+                "At most {} retries are allowed",
+                Self::MAX_RETRIES
+            )));
+        }
+        if self.base_delay_secs == 0 {
+            return Err(SyncError::InvalidConfiguration(
+                "Retry delay must be at least 1 second".to_string(),
+            ));
+        }
+        if self.max_delay_secs < self.base_delay_secs {
+            return Err(SyncError::InvalidConfiguration(
+                "Maximum retry delay must not be shorter than the base delay".to_string(),
+            ));
+        }
+        if self.max_delay_secs > Self::MAX_DELAY_SECS {
+            return Err(SyncError::InvalidConfiguration(
+                "Maximum retry delay must not exceed one day".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What the agent does while the network connection is metered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeteredPolicy {
+    /// Stop syncing until the connection is no longer metered
+    Pause,
+    /// Keep syncing at `metered_kb_per_sec`
+    Throttle,
+    /// Treat it like any other connection; the platform's guess that a
+    /// connection is metered should not stop syncing unasked
+    #[default]
+    Ignore,
+}
+
+impl MeteredPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pause => "pause",
+            Self::Throttle => "throttle",
+            Self::Ignore => "ignore",
+        }
+    }
+}
+
+impl std::fmt::Display for MeteredPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for MeteredPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        [Self::Pause, Self::Throttle, Self::Ignore]
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| {
+                format!("Unknown metered policy '{s}', expected pause, throttle or ignore")
+            })
+    }
+}
+
+/// Transfer rates in KB/s (1 KB = 1024 bytes); `None` means unlimited
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandwidthSettings {
+    pub max_upload_kb_per_sec: Option<u64>,
+    pub max_download_kb_per_sec: Option<u64>,
+    pub on_metered: MeteredPolicy,
+    /// Limit in both directions on a metered connection with `Throttle`
+    pub metered_kb_per_sec: u64,
+}
+
+impl Default for BandwidthSettings {
+    fn default() -> Self {
+        Self {
+            max_upload_kb_per_sec: None,
+            max_download_kb_per_sec: None,
+            on_metered: MeteredPolicy::default(),
+            metered_kb_per_sec: 64,
+        }
+    }
+}
+
+impl BandwidthSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_upload_kb_per_sec == Some(0)
+            || self.max_download_kb_per_sec == Some(0)
+            || self.metered_kb_per_sec == 0
+        {
+            return Err(SyncError::InvalidConfiguration(
+                "Transfer limits must be at least 1 KB/s".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Shell commands run on sync events; each gets the event as environment
+/// variables and as JSON on stdin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    /// After a pass that transferred files
+    pub on_sync_complete: Option<String>,
+    /// For every file written from the server
+    pub on_file_downloaded: Option<String>,
+    /// For every conflict copy kept
+    pub on_conflict: Option<String>,
+    /// When a pass fails and is not retried
+    pub on_error: Option<String>,
+    /// Hooks still running after this long are killed
+    pub timeout_secs: u64,
+    /// Hooks running at the same time; further events wait for a free slot
+    pub max_concurrent: usize,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        Self {
+            on_sync_complete: None,
+            on_file_downloaded: None,
+            on_conflict: None,
+            on_error: None,
+            timeout_secs: 60,
+            max_concurrent: 4,
+        }
+    }
+}
+
+impl HookSettings {
+    pub const MAX_CONCURRENT: usize = 32;
+
+    pub fn validate(&self) -> Result<()> {
+        if self.timeout_secs == 0 {
+            return Err(SyncError::InvalidConfiguration(
+                "Hook timeout must be at least 1 second".to_string(),
+            ));
+        }
+        if !(1..=Self::MAX_CONCURRENT).contains(&self.max_concurrent) {
+            return Err(SyncError::InvalidConfiguration(format!(
+                "Hooks may run 1 to {} at a time",
+                Self::MAX_CONCURRENT
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// HTTP server on 127.0.0.1 answering `/healthz`, `/status` and `/metrics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusServerSettings {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for StatusServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 9581,
+        }
+    }
+}
+
+impl StatusServerSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.enabled && self.port == 0 {
+            return Err(SyncError::InvalidConfiguration(
+                "The status server needs a port".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// How the agent logs; a running daemon applies changes on reload
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// Filter such as `debug` or `warn,cook_sync=trace`; `None` uses
+    /// `RUST_LOG` or the default
+    pub level: Option<String>,
+    pub format: LogFormat,
+}
+
+impl LogSettings {
+    pub fn validate(&self) -> Result<()> {
+        let Some(level) = &self.level else {
+            return Ok(());
+        };
+        // Directives are `level` or `module=level`, as in RUST_LOG
+        let valid = level.split(',').all(|directive| {
+            let level = directive.rsplit('=').next().unwrap_or_default();
+            level.trim().parse::<log::LevelFilter>().is_ok()
+        });
+        if !valid {
+            return Err(SyncError::InvalidConfiguration(format!(
+                "Invalid log level '{level}', expected e.g. info or warn,cook_sync=debug"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Layout of log lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// `[timestamp] LEVEL message`
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl LogFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl std::fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        [Self::Text, Self::Json]
+            .into_iter()
+            .find(|f| f.as_str() == s)
+            .ok_or_else(|| format!("Unknown log format '{s}', expected text or json"))
+    }
+}
+
+/// When the agent may sync
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleSettings {
+    /// Times of day syncing is allowed, in local time; empty for any time
+    pub sync_windows: Vec<SyncWindow>,
+    /// Syncing is paused until then
+    pub paused_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Paused by the user until resumed, kept across restarts
+    pub paused: bool,
+    /// Ids of the folders paused on their own
+    pub paused_folders: Vec<String>,
+}
+
+/// A daily time range such as `07:00-23:00`; `22:00-06:00` spans midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SyncWindow {
+    pub start: chrono::NaiveTime,
+    pub end: chrono::NaiveTime,
+}
+
+impl SyncWindow {
+    /// Whether `time` falls inside the window; a window that starts and ends
+    /// at the same time covers the whole day
+    pub fn contains(&self, time: chrono::NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl std::fmt::Display for SyncWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+impl std::str::FromStr for SyncWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let parse = |time: &str| chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M");
+        s.split_once('-')
+            .and_then(|(start, end)| Some((parse(start).ok()?, parse(end).ok()?)))
+            .map(|(start, end)| SyncWindow { start, end })
+            .ok_or_else(|| format!("'{s}' is not a time range like 07:00-23:00"))
+    }
+}
+
+impl TryFrom<String> for SyncWindow {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<SyncWindow> for String {
+    fn from(window: SyncWindow) -> Self {
+        window.to_string()
+    }
+}
+
+fn default_fallback_interval_secs() -> u64 {
+    900
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
             recipes_dir: None,
+            sync_roots: Vec::new(),
             sync_interval_secs: 12,
+            fallback_interval_secs: default_fallback_interval_secs(),
             auto_start: true,
             auto_update: true,
             show_notifications: true,
             welcome_shown: false,
+            sync_direction: SyncDirection::default(),
+            ignore_patterns: Vec::new(),
             update_settings: UpdateSettings::default(),
+            retry: RetrySettings::default(),
+            bandwidth: BandwidthSettings::default(),
+            schedule: ScheduleSettings::default(),
+            hooks: HookSettings::default(),
+            status_server: StatusServerSettings::default(),
+            logging: LogSettings::default(),
         }
     }
 }
@@ -60,7 +570,100 @@ impl Settings {
             Self::default()
         };
 
-        Ok(settings)
+        Ok(settings.without_invalid_sections())
+    }
+
+    /// Hand-edited settings are checked only here: sections with invalid
+    /// values are logged and fall back to their defaults. Folder problems
+    /// are only logged; syncing reports them per folder.
+    fn without_invalid_sections(mut self) -> Self {
+        fn reset<T: Default>(section: &mut T, name: &str, validate: fn(&T) -> Result<()>) {
+            if let Err(e) = validate(section) {
+                warn!("Using the default {name} settings: {e}");
+                *section = T::default();
+            }
+        }
+
+        let defaults = Self::default();
+        if self.sync_interval_secs < 5 {
+            warn!(
+                "Sync interval too short, using {}s",
+                defaults.sync_interval_secs
+            );
+            self.sync_interval_secs = defaults.sync_interval_secs;
+        }
+        if self.fallback_interval_secs < 60 {
+            warn!(
+                "Fallback sync interval too short, using {}s",
+                defaults.fallback_interval_secs
+            );
+            self.fallback_interval_secs = defaults.fallback_interval_secs;
+        }
+        reset(&mut self.retry, "retry", RetrySettings::validate);
+        reset(
+            &mut self.bandwidth,
+            "bandwidth",
+            BandwidthSettings::validate,
+        );
+        reset(&mut self.hooks, "hook", HookSettings::validate);
+        reset(
+            &mut self.status_server,
+            "status server",
+            StatusServerSettings::validate,
+        );
+        reset(&mut self.logging, "logging", LogSettings::validate);
+
+        if let Err(e) = self.validate() {
+            warn!("Problem in the settings: {e}");
+        }
+        self
+    }
+
+    /// Every synced folder, the main recipes folder first
+    pub fn roots(&self) -> Vec<SyncRoot> {
+        let main = self.recipes_dir.as_ref().map(|dir| SyncRoot {
+            id: SyncRoot::MAIN_ID.to_string(),
+            path: dir.clone(),
+            remote_folder: String::new(),
+            sync_direction: self.sync_direction,
+        });
+
+        main.into_iter().chain(self.sync_roots.clone()).collect()
+    }
+
+    /// Check that `root` can be synced next to the folders already configured
+    pub fn check_new_root(&self, root: &SyncRoot) -> Result<()> {
+        if !root.path.is_dir() {
+            return Err(SyncError::InvalidConfiguration(format!(
+                "Directory does not exist: {}",
+                root.path.display()
+            )));
+        }
+
+        for existing in self.roots() {
+            if paths_overlap(&existing.path, &root.path) {
+                return Err(SyncError::InvalidConfiguration(format!(
+                    "{} overlaps with synced folder {}",
+                    root.path.display(),
+                    existing.path.display()
+                )));
+            }
+            if !existing.is_main()
+                && paths_overlap(
+                    Path::new(&existing.remote_folder),
+                    Path::new(&root.remote_folder),
+                )
+            {
+                return Err(SyncError::InvalidConfiguration(format!(
+                    "Remote folder '{}' overlaps with '{}' used by {}",
+                    root.remote_folder,
+                    existing.remote_folder,
+                    existing.path.display()
+                )));
+            }
+        }
+
+        Ok(())
     }
 
     /// Get the API endpoint, with support for environment variable override
@@ -108,7 +711,6 @@ impl Settings {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn validate(&self) -> Result<()> {
         if let Some(ref dir) = self.recipes_dir {
             if !dir.exists() {
@@ -119,13 +721,59 @@ impl Settings {
             }
         }
 
+        let roots = self.roots();
+        for (i, root) in roots.iter().enumerate() {
+            if root.is_main() {
+                continue;
+            }
+            if root.remote_folder.is_empty() {
+                return Err(SyncError::InvalidConfiguration(format!(
+                    "No remote folder set for {}",
+                    root.path.display()
+                )));
+            }
+            // The main folder comes first, so `other` is never the main folder
+            for other in &roots[i + 1..] {
+                if paths_overlap(&root.path, &other.path)
+                    || paths_overlap(
+                        Path::new(&root.remote_folder),
+                        Path::new(&other.remote_folder),
+                    )
+                {
+                    return Err(SyncError::InvalidConfiguration(format!(
+                        "Synced folders {} and {} overlap",
+                        root.path.display(),
+                        other.path.display()
+                    )));
+                }
+            }
+            if let Some(ref dir) = self.recipes_dir {
+                if paths_overlap(dir, &root.path) {
+                    return Err(SyncError::InvalidConfiguration(format!(
+                        "{} overlaps with the recipes directory",
+                        root.path.display()
+                    )));
+                }
+            }
+        }
+
         if self.sync_interval_secs < 5 {
             return Err(crate::error::SyncError::InvalidConfiguration(
                 "Sync interval must be at least 5 seconds".to_string(),
             ));
         }
 
-        Ok(())
+        if self.fallback_interval_secs < 60 {
+            return Err(crate::error::SyncError::InvalidConfiguration(
+                "Fallback sync interval must be at least 60 seconds".to_string(),
+            ));
+        }
+
+        self.retry.validate()?;
+        self.bandwidth.validate()?;
+        self.hooks.validate()?;
+        self.status_server.validate()?;
+        self.logging.validate()
     }
 }
 
@@ -1,4 +1,7 @@
-use super::Settings;
+use super::{
+    HookSettings, LogFormat, LogSettings, MeteredPolicy, RetrySettings, ScheduleSettings, Settings,
+    StatusServerSettings, SyncDirection, SyncRoot,
+};
 use std::path::PathBuf;
 use tempfile::TempDir;
 
@@ -13,6 +16,13 @@ fn test_settings_default_has_welcome_shown_false() {
     );
 }
 
+#[test]
+fn test_metered_connections_are_not_paused_by_default() {
+    // Upgrading must not stop syncing on connections the platform guesses are metered
+    let settings = Settings::default();
+    assert_eq!(settings.bandwidth.on_metered, MeteredPolicy::Ignore);
+}
+
 #[test]
 fn test_settings_serialization_includes_welcome_shown() {
     // Test that welcome_shown field is serialized correctly
@@ -130,6 +140,25 @@ fn test_settings_load_nonexistent_file_defaults_to_false() {
     );
 }
 
+#[test]
+fn test_load_replaces_invalid_sections_with_defaults() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let settings_path = temp_dir.path().join("settings.json");
+    let mut settings = Settings {
+        welcome_shown: true,
+        ..Default::default()
+    };
+    settings.retry.base_delay_secs = 0;
+    settings.hooks.max_concurrent = 4;
+    settings.save(&settings_path).unwrap();
+
+    let loaded = Settings::load(&settings_path).unwrap();
+
+    assert_eq!(loaded.retry, RetrySettings::default());
+    assert_eq!(loaded.hooks, settings.hooks);
+    assert!(loaded.welcome_shown);
+}
+
 #[test]
 fn test_settings_welcome_shown_can_be_set_to_true() {
     // Test that welcome_shown can be updated from false to true
@@ -199,3 +228,279 @@ fn test_settings_multiple_updates_to_welcome_shown() {
     settings.welcome_shown = true;
     assert!(settings.welcome_shown);
 }
+
+#[test]
+fn test_settings_without_fallback_interval_use_default() {
+    // Settings written before the file watcher existed have no fallback interval
+    let json = r#"{
+        "recipes_dir": null,
+        "sync_interval_secs": 12,
+        "auto_start": true,
+        "auto_update": true,
+        "show_notifications": true
+    }"#;
+
+    let settings: Settings =
+        serde_json::from_str(json).expect("Failed to deserialize old settings format");
+
+    assert_eq!(
+        settings.fallback_interval_secs,
+        Settings::default().fallback_interval_secs
+    );
+    assert!(
+        settings.fallback_interval_secs >= 60,
+        "Idle machines should not be woken up frequently"
+    );
+}
+
+#[test]
+fn test_settings_validate_rejects_short_fallback_interval() {
+    let settings = Settings {
+        fallback_interval_secs: 10,
+        ..Default::default()
+    };
+
+    assert!(settings.validate().is_err());
+}
+
+#[test]
+fn test_settings_without_sync_direction_sync_both_ways() {
+    let json = r#"{
+        "recipes_dir": null,
+        "sync_interval_secs": 12,
+        "auto_start": true,
+        "auto_update": true,
+        "show_notifications": true
+    }"#;
+
+    let settings: Settings =
+        serde_json::from_str(json).expect("Failed to deserialize old settings format");
+
+    assert_eq!(settings.sync_direction, SyncDirection::Both);
+}
+
+#[test]
+fn test_sync_direction_serialization() {
+    let settings = Settings {
+        sync_direction: SyncDirection::DownloadOnly,
+        ..Default::default()
+    };
+
+    let json = serde_json::to_value(&settings).expect("Failed to serialize to JSON value");
+    assert_eq!(json["sync_direction"], "download_only");
+
+    let loaded: Settings = serde_json::from_value(json).expect("Failed to deserialize settings");
+    assert_eq!(loaded.sync_direction, SyncDirection::DownloadOnly);
+}
+
+#[test]
+fn test_sync_direction_from_str() {
+    for direction in SyncDirection::ALL {
+        assert_eq!(direction.to_string().parse(), Ok(direction));
+    }
+    assert_eq!("upload-only".parse(), Ok(SyncDirection::UploadOnly));
+    assert!("sideways".parse::<SyncDirection>().is_err());
+
+    assert!(SyncDirection::UploadOnly.uploads());
+    assert!(!SyncDirection::UploadOnly.downloads());
+    assert!(!SyncDirection::DownloadOnly.uploads());
+    assert!(SyncDirection::DownloadOnly.downloads());
+}
+
+#[test]
+fn test_roots_start_with_recipes_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let shared = temp_dir.path().join("shared");
+    std::fs::create_dir(&shared).unwrap();
+
+    let mut settings = Settings {
+        recipes_dir: Some(temp_dir.path().join("recipes")),
+        sync_direction: SyncDirection::UploadOnly,
+        ..Default::default()
+    };
+    settings
+        .sync_roots
+        .push(SyncRoot::new(shared.clone(), "/Shared/Team/").unwrap());
+
+    let roots = settings.roots();
+    assert_eq!(roots.len(), 2);
+    assert!(roots[0].is_main());
+    assert_eq!(roots[0].remote_folder, "");
+    assert_eq!(roots[0].sync_direction, SyncDirection::UploadOnly);
+    assert_eq!(roots[1].path, shared);
+    assert_eq!(roots[1].remote_folder, "Shared/Team");
+    assert_eq!(roots[1].sync_direction, SyncDirection::Both);
+}
+
+#[test]
+fn test_check_new_root_rejects_overlaps() {
+    let temp_dir = TempDir::new().unwrap();
+    let recipes = temp_dir.path().join("recipes");
+    let nested = recipes.join("nested");
+    let shared = temp_dir.path().join("shared");
+    let other = temp_dir.path().join("other");
+    for dir in [&nested, &shared, &other] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+
+    let mut settings = Settings {
+        recipes_dir: Some(recipes),
+        ..Default::default()
+    };
+    settings
+        .sync_roots
+        .push(SyncRoot::new(shared, "Shared").unwrap());
+    assert!(settings.validate().is_ok());
+
+    assert!(settings
+        .check_new_root(&SyncRoot::new(nested, "Nested").unwrap())
+        .is_err());
+    assert!(settings
+        .check_new_root(&SyncRoot::new(other.clone(), "Shared/Team").unwrap())
+        .is_err());
+    assert!(settings
+        .check_new_root(&SyncRoot::new(temp_dir.path().join("missing"), "Missing").unwrap())
+        .is_err());
+    assert!(settings
+        .check_new_root(&SyncRoot::new(other, "Other").unwrap())
+        .is_ok());
+
+    assert!(SyncRoot::new(PathBuf::from("/tmp"), "/").is_err());
+    assert!(SyncRoot::new(PathBuf::from("/tmp"), "Shared/../Other").is_err());
+}
+
+#[test]
+fn test_retry_settings_default_and_validation() {
+    // Settings files from before the retry options load with the defaults
+    let settings: Settings = serde_json::from_str(
+        r#"{"recipes_dir":null,"sync_interval_secs":12,"auto_start":true,
+            "auto_update":true,"show_notifications":true}"#,
+    )
+    .unwrap();
+    assert_eq!(settings.retry, RetrySettings::default());
+    assert!(settings.validate().is_ok());
+
+    let invalid = [
+        RetrySettings {
+            base_delay_secs: 0,
+            ..Default::default()
+        },
+        RetrySettings {
+            base_delay_secs: 60,
+            max_delay_secs: 30,
+            ..Default::default()
+        },
+        RetrySettings {
+            max_retries: RetrySettings::MAX_RETRIES + 1,
+            ..Default::default()
+        },
+    ];
+    for retry in invalid {
+        let settings = Settings {
+            retry,
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
+
+#[test]
+fn test_pause_state_survives_save_and_load() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let settings_path = temp_dir.path().join("settings.json");
+
+    let until = "2026-03-10T13:00:00Z".parse().unwrap();
+    let settings = Settings {
+        schedule: ScheduleSettings {
+            sync_windows: vec!["07:00-23:00".parse().unwrap()],
+            paused_until: Some(until),
+            paused: true,
+            paused_folders: vec!["family".to_string()],
+        },
+        ..Default::default()
+    };
+    settings.save(&settings_path).unwrap();
+
+    let loaded = Settings::load(&settings_path).unwrap();
+    assert_eq!(loaded.schedule, settings.schedule);
+
+    // Settings files from before pausing was saved load as not paused
+    let settings: Settings = serde_json::from_str(
+        r#"{"recipes_dir":null,"sync_interval_secs":12,"auto_start":true,
+            "auto_update":true,"show_notifications":true,
+            "schedule":{"sync_windows":["22:00-06:00"]}}"#,
+    )
+    .unwrap();
+    assert!(!settings.schedule.paused);
+    assert!(settings.schedule.paused_folders.is_empty());
+    assert_eq!(settings.schedule.sync_windows[0].to_string(), "22:00-06:00");
+}
+
+#[test]
+fn test_hook_settings_validation() {
+    assert!(HookSettings::default().validate().is_ok());
+    for hooks in [
+        HookSettings {
+            timeout_secs: 0,
+            ..Default::default()
+        },
+        HookSettings {
+            max_concurrent: 0,
+            ..Default::default()
+        },
+        HookSettings {
+            max_concurrent: HookSettings::MAX_CONCURRENT + 1,
+            ..Default::default()
+        },
+    ] {
+        let settings = Settings {
+            hooks,
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
+
+#[test]
+fn test_status_server_is_off_unless_enabled() {
+    let json = r#"{
+        "recipes_dir": null,
+        "sync_interval_secs": 12,
+        "auto_start": true,
+        "auto_update": true,
+        "show_notifications": true,
+        "status_server": {"enabled": true}
+    }"#;
+    let settings: Settings = serde_json::from_str(json).unwrap();
+    assert!(settings.status_server.enabled);
+    assert_eq!(settings.status_server.port, 9581);
+    assert!(!Settings::default().status_server.enabled);
+
+    let settings = Settings {
+        status_server: StatusServerSettings {
+            enabled: true,
+            port: 0,
+        },
+        ..Default::default()
+    };
+    assert!(settings.validate().is_err());
+}
+
+#[test]
+fn test_log_settings() {
+    for level in ["debug", "warn,cook_sync=trace", "off"] {
+        let logging = LogSettings {
+            level: Some(level.to_string()),
+            format: LogFormat::Json,
+        };
+        assert!(logging.validate().is_ok(), "{level}");
+    }
+    for level in ["", "loud", "cook_sync=verbose"] {
+        let logging = LogSettings {
+            level: Some(level.to_string()),
+            ..Default::default()
+        };
+        assert!(logging.validate().is_err(), "{level}");
+    }
+    assert_eq!("json".parse(), Ok(LogFormat::Json));
+}
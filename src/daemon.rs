@@ -1,19 +1,27 @@
 use crate::api::CookApi;
 use crate::auth::AuthManager;
+use crate::config::settings::SyncRoot;
 use crate::config::Config;
-use crate::error::Result;
+use crate::error::{Result, SyncError};
+use crate::ipc::protocol::{Request, Response};
+use crate::ipc::server::RequestHandler;
+use crate::status_server::{StatusServer, StatusSource};
+use crate::sync::report::StatusReport;
+use crate::sync::status::SyncState;
 use crate::sync::SyncManager;
 use crate::tray::SystemTray;
 // Update manager is available for manual checks via UpdateManager::new()
 // Auto-update checking can be re-implemented if needed
 use log::info;
 use std::fs;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 pub struct Daemon {
     config: Arc<Config>,
     auth_manager: Arc<AuthManager>,
     sync_manager: Arc<SyncManager>,
+    status_server: Arc<StatusServer<DaemonStatus>>,
 }
 
 impl Daemon {
@@ -33,26 +41,63 @@ impl Daemon {
             Arc::clone(&config),
         ));
 
+        let status_server = Arc::new(StatusServer::new(Arc::new(DaemonStatus {
+            config: Arc::clone(&config),
+            auth_manager: Arc::clone(&auth_manager),
+            sync_manager: Arc::clone(&sync_manager),
+        })));
+
         Ok(Daemon {
             config,
             auth_manager,
             sync_manager,
+            status_server,
         })
     }
 
-    pub async fn run(&self) -> Result<()> {
+    /// Run until shutdown. Without `headless` the system tray drives the
+    /// lifetime; headless (or when the tray can't be created) a signal does.
+    pub async fn run(&self, headless: bool) -> Result<()> {
         info!("Starting Cook Sync daemon");
 
         // Write PID file
         self.write_pid_file()?;
 
-        // Start token refresh if authenticated
-        if self.auth_manager.is_authenticated() {
-            self.auth_manager.start_token_refresh().await;
+        // Listen for control requests from the CLI
+        self.start_control_server();
+        #[cfg(unix)]
+        self.reload_on_hangup();
+        let status_server = self.config.settings().lock().unwrap().status_server;
+        self.status_server.apply(status_server);
+
+        // Pause or throttle on metered connections, where the platform tells us
+        if let Some(probe) = crate::sync::metered::system_probe() {
+            let sync_manager = Arc::clone(&self.sync_manager);
+            tokio::spawn(crate::sync::metered::watch(
+                probe,
+                crate::sync::metered::CHECK_INTERVAL,
+                move |metered| sync_manager.set_metered(metered),
+            ));
+        }
+
+        // Only headless machines are provisioned through the environment
+        if headless {
+            if let Err(e) = self.auth_manager.login_from_env().await {
+                log::warn!("Could not log in with {}: {e}", crate::auth::TOKEN_ENV);
+            }
+        }
+
+        // Keep the session fresh; this also covers profiles switched to later
+        self.auth_manager.start_token_refresh().await;
+        if !self.auth_manager.is_authenticated() && headless {
+            log::warn!(
+                "Not authenticated. Run 'cook-sync login' or set {} to start syncing.",
+                crate::auth::TOKEN_ENV
+            );
         }
 
         // Start sync manager if configured and authenticated
-        if self.config.settings().lock().unwrap().recipes_dir.is_some()
+        if !self.config.settings().lock().unwrap().roots().is_empty()
             && self.auth_manager.is_authenticated()
         {
             self.sync_manager.start().await?;
@@ -93,6 +138,14 @@ impl Daemon {
             }
         });
 
+        if headless {
+            // Login-session auto-start entries are meaningless without a desktop;
+            // headless installs are started by systemd or similar
+            return self.run_headless().await;
+        }
+
+        self.sync_auto_start();
+
         // Setup signal handler for graceful shutdown
         let _sync_manager_clone = Arc::clone(&self.sync_manager);
         tokio::spawn(async move {
@@ -106,34 +159,6 @@ impl Daemon {
             }
         });
 
-        // Sync auto-start state with system (non-fatal — daemon must not crash for this)
-        let platform = crate::platform::get_platform();
-        let config_auto_start = self.config.settings().lock().unwrap().auto_start;
-        let system_auto_start = platform.is_auto_start_enabled("cook-sync").unwrap_or(false);
-
-        if config_auto_start && !system_auto_start {
-            // Config says enabled but system doesn't have it - install it
-            match std::env::current_exe() {
-                Ok(app_path) => {
-                    if let Err(e) =
-                        platform.enable_auto_start("cook-sync", &app_path.to_string_lossy())
-                    {
-                        log::warn!("Failed to register auto-start with system: {e}");
-                    } else {
-                        info!("Auto-start enabled: registered with system");
-                    }
-                }
-                Err(e) => log::warn!("Failed to get executable path for auto-start: {e}"),
-            }
-        } else if !config_auto_start && system_auto_start {
-            // Config says disabled but system has it - unregister it
-            if let Err(e) = platform.disable_auto_start("cook-sync") {
-                log::warn!("Failed to unregister auto-start from system: {e}");
-            } else {
-                info!("Auto-start disabled: unregistered from system");
-            }
-        }
-
         // Create and run system tray
         info!("Initializing system tray...");
 
@@ -241,7 +266,9 @@ impl Daemon {
                     );
                 }
 
-                return Err(e);
+                // Syncing matters more than the icon; keep going without it
+                log::warn!("Continuing without system tray");
+                return self.run_headless().await;
             }
         };
 
@@ -291,6 +318,90 @@ impl Daemon {
         Ok(())
     }
 
+    /// Sync auto-start state with system (non-fatal — daemon must not crash for this)
+    fn sync_auto_start(&self) {
+        let platform = crate::platform::get_platform();
+        let config_auto_start = self.config.settings().lock().unwrap().auto_start;
+        let system_auto_start = platform.is_auto_start_enabled("cook-sync").unwrap_or(false);
+
+        if config_auto_start && !system_auto_start {
+            // Config says enabled but system doesn't have it - install it
+            match std::env::current_exe() {
+                Ok(app_path) => {
+                    if let Err(e) =
+                        platform.enable_auto_start("cook-sync", &app_path.to_string_lossy())
+                    {
+                        log::warn!("Failed to register auto-start with system: {e}");
+                    } else {
+                        info!("Auto-start enabled: registered with system");
+                    }
+                }
+                Err(e) => log::warn!("Failed to get executable path for auto-start: {e}"),
+            }
+        } else if !config_auto_start && system_auto_start {
+            // Config says disabled but system has it - unregister it
+            if let Err(e) = platform.disable_auto_start("cook-sync") {
+                log::warn!("Failed to unregister auto-start from system: {e}");
+            } else {
+                info!("Auto-start disabled: unregistered from system");
+            }
+        }
+    }
+
+    /// Keep syncing without a tray until SIGTERM/SIGINT (Ctrl+C on Windows)
+    async fn run_headless(&self) -> Result<()> {
+        info!("Running headless without system tray");
+
+        wait_for_shutdown_signal().await;
+        info!("Shutdown signal received, stopping sync manager");
+
+        self.sync_manager.stop().await?;
+        self.cleanup()
+    }
+
+    fn control(&self) -> Arc<DaemonControl> {
+        Arc::new(DaemonControl {
+            config: Arc::clone(&self.config),
+            auth_manager: Arc::clone(&self.auth_manager),
+            sync_manager: Arc::clone(&self.sync_manager),
+            status_server: Arc::clone(&self.status_server),
+        })
+    }
+
+    fn start_control_server(&self) {
+        let endpoint = crate::ipc::endpoint(&self.config.paths());
+        let control = self.control();
+
+        tokio::spawn(async move {
+            if let Err(e) = crate::ipc::server::serve(&endpoint, control).await {
+                log::error!("Control server stopped: {}", e);
+            }
+        });
+    }
+
+    /// Reload the settings on SIGHUP, like `cook-sync reload`
+    #[cfg(unix)]
+    fn reload_on_hangup(&self) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let control = self.control();
+        tokio::spawn(async move {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    log::error!("Failed to install SIGHUP handler: {e}");
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                info!("Received SIGHUP, reloading settings");
+                if let Err(e) = control.reload_settings().await {
+                    log::error!("Failed to reload settings: {e}");
+                }
+            }
+        });
+    }
+
     fn write_pid_file(&self) -> Result<()> {
         let pid = std::process::id();
         let pid_file = &self.config.paths().pid_file;
@@ -304,8 +415,222 @@ impl Daemon {
         if pid_file.exists() {
             fs::remove_file(pid_file)?;
         }
+
+        // Remove control socket
+        crate::ipc::cleanup(&self.config.paths());
+        Ok(())
+    }
+}
+
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::interrupt()),
+        ) {
+            (Ok(mut sigterm), Ok(mut sigint)) => {
+                tokio::select! {
+                    _ = sigterm.recv() => info!("Received SIGTERM"),
+                    _ = sigint.recv() => info!("Received SIGINT"),
+                }
+            }
+            _ => {
+                log::error!("Failed to install signal handlers, falling back to Ctrl+C");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Answers control requests from the CLI using the daemon's live state
+struct DaemonControl {
+    config: Arc<Config>,
+    auth_manager: Arc<AuthManager>,
+    sync_manager: Arc<SyncManager>,
+    status_server: Arc<StatusServer<DaemonStatus>>,
+}
+
+impl DaemonControl {
+    fn status(&self) -> Response {
+        let session = self.auth_manager.get_session();
+
+        Response::Status {
+            state: Box::new(self.sync_manager.state()),
+            authenticated: session.is_some(),
+            user: session.map(|s| s.email.unwrap_or(s.user_id)),
+            folders: self.sync_manager.folder_states(),
+        }
+    }
+
+    async fn sync_now(&self) -> Result<()> {
+        if !self.auth_manager.is_authenticated() {
+            return Err(crate::error::SyncError::AuthenticationRequired);
+        }
+        self.sync_manager.sync_now().await
+    }
+
+    async fn reload_settings(&self) -> Result<()> {
+        let previous = self.config.settings().lock().unwrap().roots();
+        self.config.reload_settings()?;
+        let current = self.config.settings().lock().unwrap().roots();
+        info!("Settings reloaded from disk");
+
+        // Restart syncing only when the set of folders actually changed
+        let folders = |roots: &[SyncRoot]| -> Vec<(String, PathBuf, String)> {
+            roots
+                .iter()
+                .map(|r| (r.id.clone(), r.path.clone(), r.remote_folder.clone()))
+                .collect()
+        };
+        if folders(&previous) != folders(&current) {
+            info!("Synced folders changed, restarting sync");
+            self.sync_manager.stop().await?;
+            if !current.is_empty() && self.auth_manager.is_authenticated() {
+                self.sync_manager.start().await?;
+            }
+        } else if previous != current {
+            // The next pass picks up the new direction by itself
+            info!("Sync direction changed");
+            self.sync_manager.wake();
+        }
+        // The metered policy and the sync hours may have changed
+        self.sync_manager.apply_metered_policy();
+        self.sync_manager.apply_schedule();
+        self.apply_settings();
+
+        Ok(())
+    }
+
+    async fn switch_profile(&self, name: &str) -> Result<()> {
+        info!("Profile switch to '{name}' requested via control channel");
+        switch_profile(&self.config, &self.auth_manager, &self.sync_manager, name).await?;
+        self.apply_settings();
         Ok(())
     }
+
+    /// Apply the settings the daemon itself reads: logging and the status server
+    fn apply_settings(&self) {
+        let settings = self.config.settings();
+        let settings = settings.lock().unwrap();
+        crate::logging::apply(&settings.logging);
+        self.status_server.apply(settings.status_server);
+    }
+
+    async fn logout(&self) -> Result<()> {
+        info!("Logout requested via control channel");
+        self.auth_manager.logout()?;
+        self.sync_manager.stop().await
+    }
+}
+
+/// Feeds the status server from the daemon's live state
+struct DaemonStatus {
+    config: Arc<Config>,
+    auth_manager: Arc<AuthManager>,
+    sync_manager: Arc<SyncManager>,
+}
+
+impl StatusSource for DaemonStatus {
+    fn report(&self) -> StatusReport {
+        let session = self.auth_manager.get_session();
+        let user = session.clone().map(|s| s.email.unwrap_or(s.user_id));
+
+        let mut report = StatusReport::not_running(session.is_some(), user);
+        report.running = true;
+        report.pid = Some(std::process::id());
+        report.recipes_dir = self.config.settings().lock().unwrap().recipes_dir.clone();
+
+        let folders = self.sync_manager.folder_states();
+        let now = chrono::Utc::now();
+        report
+            .with_state(&SyncState::combine(&folders), now)
+            .with_folders(&folders, now)
+    }
+
+    fn token_expires_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let jwt = self.auth_manager.get_session()?.jwt_token().ok()?;
+        chrono::DateTime::from_timestamp(jwt.claims.exp, 0)
+    }
+}
+
+impl RequestHandler for DaemonControl {
+    async fn handle(&self, request: Request) -> Response {
+        let result = match request {
+            Request::Ping => {
+                return Response::Pong {
+                    agent_version: env!("CARGO_PKG_VERSION").to_string(),
+                }
+            }
+            Request::Status => return self.status(),
+            Request::Pause {
+                folder: None,
+                until: None,
+            } => {
+                self.sync_manager.pause();
+                Ok(())
+            }
+            Request::Pause {
+                folder: None,
+                until: Some(until),
+            } => self.sync_manager.pause_until(until),
+            Request::Pause {
+                folder: Some(folder),
+                until: None,
+            } => self.sync_manager.pause_folder(&folder),
+            Request::Pause {
+                folder: Some(_),
+                until: Some(_),
+            } => Err(SyncError::Other(
+                "Timed pauses apply to all folders".to_string(),
+            )),
+            Request::Resume { folder: None } => {
+                self.sync_manager.resume();
+                Ok(())
+            }
+            Request::Resume {
+                folder: Some(folder),
+            } => self.sync_manager.resume_folder(&folder),
+            Request::SyncNow => self.sync_now().await,
+            Request::ReloadSettings => self.reload_settings().await,
+            Request::SwitchProfile { name } => self.switch_profile(&name).await,
+            Request::Logout => self.logout().await,
+        };
+
+        match result {
+            Ok(()) => Response::Ok,
+            Err(e) => Response::Error {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Stop syncing the active profile, then load and start syncing profile `name`
+pub async fn switch_profile(
+    config: &Config,
+    auth_manager: &AuthManager,
+    sync_manager: &SyncManager,
+    name: &str,
+) -> Result<()> {
+    // Nothing may sync with the old folders and the new session in between
+    sync_manager.stop().await?;
+    config.switch_profile(name)?;
+    auth_manager.switch_profile(name)?;
+    info!("Switched to profile '{name}'");
+
+    if !config.settings().lock().unwrap().roots().is_empty() && auth_manager.is_authenticated() {
+        sync_manager.start().await
+    } else {
+        // Drops the previous profile's folders from the status
+        sync_manager.stop().await
+    }
 }
 
 pub fn is_already_running(config: &Config) -> bool {
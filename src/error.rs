@@ -14,11 +14,19 @@ pub enum SyncError {
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
+    #[error("Offline: {0}")]
+    Offline(String),
+
+    #[error("Server error: {0}")]
+    Server(String),
+
+    #[error("Request rejected by server: {0}")]
+    Rejected(String),
+
     #[error("JWT error: {0}")]
     Jwt(#[from] jsonwebtoken::errors::Error),
 
     #[error("Sync client error: {0}")]
-    #[allow(dead_code)]
     SyncClient(String),
 
     #[error("Authentication required")]
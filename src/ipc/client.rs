@@ -0,0 +1,58 @@
+use super::protocol::{Envelope, Request, Response, PROTOCOL_VERSION};
+use crate::error::{Result, SyncError};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::time::{timeout, Duration};
+
+/// How long to wait for the daemon to answer a single request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Send a request over the daemon's Unix domain socket
+#[cfg(unix)]
+pub async fn send(socket_path: &std::path::Path, request: Request) -> Result<Response> {
+    let stream = tokio::net::UnixStream::connect(socket_path)
+        .await
+        .map_err(|e| SyncError::Other(format!("Could not reach the running daemon: {e}")))?;
+    exchange(stream, request).await
+}
+
+/// Send a request over the daemon's named pipe
+#[cfg(windows)]
+pub async fn send(pipe_name: &str, request: Request) -> Result<Response> {
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(pipe_name)
+        .map_err(|e| SyncError::Other(format!("Could not reach the running daemon: {e}")))?;
+    exchange(stream, request).await
+}
+
+pub(crate) async fn exchange<S>(stream: S, request: Request) -> Result<Response>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+
+    let mut payload = serde_json::to_string(&Envelope::new(request))?;
+    payload.push('\n');
+    writer.write_all(payload.as_bytes()).await?;
+    writer.flush().await?;
+
+    let mut line = String::new();
+    let read = timeout(REQUEST_TIMEOUT, BufReader::new(reader).read_line(&mut line))
+        .await
+        .map_err(|_| SyncError::Other("Timed out waiting for the daemon".to_string()))??;
+
+    if read == 0 {
+        return Err(SyncError::Other(
+            "Daemon closed the connection without replying".to_string(),
+        ));
+    }
+
+    let envelope: Envelope<Response> = serde_json::from_str(&line)?;
+    if envelope.version != PROTOCOL_VERSION {
+        return Err(SyncError::Other(format!(
+            "Daemon speaks protocol version {} but this CLI expects {}; restart the agent",
+            envelope.version, PROTOCOL_VERSION
+        )));
+    }
+
+    Ok(envelope.body)
+}
@@ -0,0 +1,176 @@
+use super::client::exchange;
+use super::protocol::{Envelope, Request, Response, PROTOCOL_VERSION};
+use super::server::{handle_connection, RequestHandler};
+use crate::sync::status::SyncState;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// Handler that records requests and answers with canned responses
+#[derive(Default)]
+struct RecordingHandler {
+    received: Mutex<Vec<Request>>,
+}
+
+impl RequestHandler for RecordingHandler {
+    async fn handle(&self, request: Request) -> Response {
+        self.received.lock().unwrap().push(request.clone());
+        match request {
+            Request::Ping => Response::Pong {
+                agent_version: "test".to_string(),
+            },
+            Request::Status => Response::Status {
+                state: Box::new(SyncState::default()),
+                authenticated: true,
+                user: Some("test@example.com".to_string()),
+                folders: Vec::new(),
+            },
+            _ => Response::Ok,
+        }
+    }
+}
+
+#[test]
+fn test_request_wire_format() {
+    let json = serde_json::to_string(&Envelope::new(Request::SyncNow)).unwrap();
+    assert_eq!(
+        json,
+        format!(r#"{{"version":{PROTOCOL_VERSION},"command":"sync_now"}}"#)
+    );
+
+    let parsed: Envelope<Request> =
+        serde_json::from_str(r#"{"version":1,"command":"reload_settings"}"#).unwrap();
+    assert_eq!(parsed.version, 1);
+    assert_eq!(parsed.body, Request::ReloadSettings);
+}
+
+#[test]
+fn test_response_round_trip() {
+    let response = Envelope::new(Response::Error {
+        message: "boom".to_string(),
+    });
+    let json = serde_json::to_string(&response).unwrap();
+    assert!(json.contains(r#""type":"error""#));
+
+    let parsed: Envelope<Response> = serde_json::from_str(&json).unwrap();
+    assert!(matches!(parsed.body, Response::Error { message } if message == "boom"));
+}
+
+#[tokio::test]
+async fn test_exchange_over_stream() {
+    let handler = Arc::new(RecordingHandler::default());
+    let (client, server) = tokio::io::duplex(4096);
+
+    let server_task = tokio::spawn(handle_connection(server, Arc::clone(&handler)));
+
+    let response = exchange(client, Request::Status).await.unwrap();
+    match response {
+        Response::Status {
+            authenticated,
+            user,
+            ..
+        } => {
+            assert!(authenticated);
+            assert_eq!(user.as_deref(), Some("test@example.com"));
+        }
+        other => panic!("Unexpected response: {other:?}"),
+    }
+
+    server_task.await.unwrap().unwrap();
+    assert_eq!(*handler.received.lock().unwrap(), vec![Request::Status]);
+}
+
+#[tokio::test]
+async fn test_version_mismatch_is_rejected() {
+    let handler = Arc::new(RecordingHandler::default());
+    let (client, server) = tokio::io::duplex(4096);
+    tokio::spawn(handle_connection(server, Arc::clone(&handler)));
+
+    let (reader, mut writer) = tokio::io::split(client);
+    writer
+        .write_all(b"{\"version\":999,\"command\":\"pause\"}\n")
+        .await
+        .unwrap();
+
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await.unwrap();
+    let envelope: Envelope<Response> = serde_json::from_str(&line).unwrap();
+
+    assert!(matches!(envelope.body, Response::Error { .. }));
+    assert!(
+        handler.received.lock().unwrap().is_empty(),
+        "Handler must not see requests with an unknown version"
+    );
+}
+
+#[tokio::test]
+async fn test_malformed_request_gets_error_and_connection_stays_open() {
+    let handler = Arc::new(RecordingHandler::default());
+    let (client, server) = tokio::io::duplex(4096);
+    tokio::spawn(handle_connection(server, Arc::clone(&handler)));
+
+    let (reader, mut writer) = tokio::io::split(client);
+    let mut reader = BufReader::new(reader);
+
+    writer.write_all(b"not json\n").await.unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    let envelope: Envelope<Response> = serde_json::from_str(&line).unwrap();
+    assert!(matches!(envelope.body, Response::Error { .. }));
+
+    writer
+        .write_all(b"{\"version\":1,\"command\":\"ping\"}\n")
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    let envelope: Envelope<Response> = serde_json::from_str(&line).unwrap();
+    assert!(matches!(envelope.body, Response::Pong { .. }));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_socket_round_trip() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let socket_dir = temp_dir.path().join("control");
+    let socket_path = socket_dir.join("control.sock");
+
+    // A stale socket file from a previous run must not prevent binding
+    std::fs::create_dir(&socket_dir).unwrap();
+    std::fs::set_permissions(&socket_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::write(&socket_path, b"").unwrap();
+
+    let handler = Arc::new(RecordingHandler::default());
+    let server_path = socket_path.clone();
+    let server_handler = Arc::clone(&handler);
+    let server =
+        tokio::spawn(async move { super::server::serve(&server_path, server_handler).await });
+
+    // Wait for the listener to come up
+    let mut response = None;
+    for _ in 0..50 {
+        match super::client::send(&socket_path, Request::Ping).await {
+            Ok(r) => {
+                response = Some(r);
+                break;
+            }
+            Err(_) => tokio::time::sleep(std::time::Duration::from_millis(20)).await,
+        }
+    }
+
+    assert!(matches!(response, Some(Response::Pong { .. })));
+    let mode = std::fs::metadata(&socket_path)
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600, "Socket should be private to the user");
+    let mode = std::fs::metadata(&socket_dir).unwrap().permissions().mode();
+    assert_eq!(
+        mode & 0o777,
+        0o700,
+        "Socket folder should be private to the user"
+    );
+
+    server.abort();
+}
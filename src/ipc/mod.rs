@@ -0,0 +1,60 @@
+//! Local control channel between the CLI and the running daemon.
+//!
+//! The daemon listens on a Unix domain socket in a private folder of the cache
+//! directory (a named pipe on Windows). CLI subcommands connect, send one JSON request per line and
+//! read one JSON response per line, so they can act on the live daemon instead
+//! of restarting it.
+
+pub mod client;
+pub mod protocol;
+pub mod server;
+
+use crate::config::AppPaths;
+use crate::error::{Result, SyncError};
+use protocol::{Request, Response};
+
+#[cfg(test)]
+#[path = "ipc_test.rs"]
+mod ipc_test;
+
+/// Unix domain socket path used by the daemon
+#[cfg(unix)]
+pub fn endpoint(paths: &AppPaths) -> std::path::PathBuf {
+    paths.socket_file.clone()
+}
+
+/// Named pipe used by the daemon, scoped to the current user
+#[cfg(windows)]
+pub fn endpoint(_paths: &AppPaths) -> String {
+    let user = std::env::var("USERNAME").unwrap_or_else(|_| "default".to_string());
+    format!(r"\\.\pipe\cook-sync-{user}")
+}
+
+/// Send a request to the running daemon and return its response
+pub async fn request(paths: &AppPaths, request: Request) -> Result<Response> {
+    client::send(&endpoint(paths), request).await
+}
+
+/// Send a request that is expected to answer with `Response::Ok`
+pub async fn command(paths: &AppPaths, request: Request) -> Result<()> {
+    match self::request(paths, request).await? {
+        Response::Ok => Ok(()),
+        Response::Error { message } => Err(SyncError::Other(message)),
+        other => Err(SyncError::Other(format!(
+            "Unexpected response from daemon: {other:?}"
+        ))),
+    }
+}
+
+/// Remove the socket file left by the daemon (no-op for named pipes)
+pub fn cleanup(paths: &AppPaths) {
+    #[cfg(unix)]
+    {
+        let socket = endpoint(paths);
+        if socket.exists() {
+            let _ = std::fs::remove_file(socket);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = paths;
+}
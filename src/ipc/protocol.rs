@@ -0,0 +1,81 @@
+//! Wire format for the local control channel.
+//!
+//! Every message is a single line of JSON. Requests and responses carry the
+//! protocol version so that a CLI from a newer release can detect an older
+//! daemon (e.g. right after an auto-update) instead of misinterpreting replies.
+
+use crate::sync::status::{FolderState, SyncState};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Current version of the control protocol
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Commands understood by the daemon
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Check that the daemon is alive
+    Ping,
+    /// Query the live sync state
+    Status,
+    /// Pause syncing, of one folder or all of them, optionally until a given
+    /// time
+    Pause {
+        #[serde(default)]
+        folder: Option<PathBuf>,
+        #[serde(default)]
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    },
+    /// Resume syncing, of one folder or all of them
+    Resume {
+        #[serde(default)]
+        folder: Option<PathBuf>,
+    },
+    /// Start a sync pass right away
+    SyncNow,
+    /// Re-read settings from disk and apply them
+    ReloadSettings,
+    /// Make another profile the active one and sync it instead
+    SwitchProfile { name: String },
+    /// Clear the session and stop syncing
+    Logout,
+}
+
+/// Replies sent back by the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Pong {
+        agent_version: String,
+    },
+    Status {
+        state: Box<SyncState>,
+        authenticated: bool,
+        user: Option<String>,
+        /// Per-folder states; empty from daemons that predate multiple folders
+        #[serde(default)]
+        folders: Vec<FolderState>,
+    },
+    Ok,
+    Error {
+        message: String,
+    },
+}
+
+/// A request or response tagged with the protocol version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub version: u32,
+    #[serde(flatten)]
+    pub body: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(body: T) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            body,
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use super::protocol::{Envelope, Request, Response, PROTOCOL_VERSION};
+use crate::error::Result;
+use log::{debug, info, warn};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Implemented by whatever owns the live agent state (the daemon in production)
+pub trait RequestHandler: Send + Sync + 'static {
+    fn handle(&self, request: Request) -> impl Future<Output = Response> + Send;
+}
+
+/// Accept control connections on a Unix domain socket until the task is dropped
+#[cfg(unix)]
+pub async fn serve<H: RequestHandler>(
+    socket_path: &std::path::Path,
+    handler: Arc<H>,
+) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use tokio::net::UnixListener;
+
+    // Only the current user may control the agent. The socket is created
+    // inside a private folder, so nobody else can reach it even before its
+    // own permissions are tightened.
+    if let Some(dir) = socket_path.parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+
+    // A socket left behind by a crashed daemon would make bind() fail
+    if socket_path.exists() {
+        std::fs::remove_file(socket_path)?;
+    }
+
+    let listener = UnixListener::bind(socket_path)?;
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))?;
+
+    info!("Control socket listening at {}", socket_path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let handler = Arc::clone(&handler);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, handler).await {
+                debug!("Control connection closed with error: {e}");
+            }
+        });
+    }
+}
+
+/// Accept control connections on a named pipe until the task is dropped
+#[cfg(windows)]
+pub async fn serve<H: RequestHandler>(pipe_name: &str, handler: Arc<H>) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(pipe_name)?;
+
+    info!("Control pipe listening at {pipe_name}");
+
+    loop {
+        server.connect().await?;
+        let connected = server;
+        // Create the next instance before serving so clients never see the pipe missing
+        server = ServerOptions::new().create(pipe_name)?;
+
+        let handler = Arc::clone(&handler);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(connected, handler).await {
+                debug!("Control connection closed with error: {e}");
+            }
+        });
+    }
+}
+
+/// Serve newline-delimited requests on a single connection until the client hangs up
+pub(crate) async fn handle_connection<S, H>(stream: S, handler: Arc<H>) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    H: RequestHandler,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Envelope<Request>>(&line) {
+            Ok(envelope) if envelope.version != PROTOCOL_VERSION => {
+                warn!(
+                    "Rejecting control request with protocol version {} (expected {})",
+                    envelope.version, PROTOCOL_VERSION
+                );
+                Response::Error {
+                    message: format!(
+                        "Unsupported protocol version {} (daemon speaks {})",
+                        envelope.version, PROTOCOL_VERSION
+                    ),
+                }
+            }
+            Ok(envelope) => {
+                debug!("Control request: {:?}", envelope.body);
+                handler.handle(envelope.body).await
+            }
+            Err(e) => Response::Error {
+                message: format!("Malformed request: {e}"),
+            },
+        };
+
+        let mut payload = serde_json::to_string(&Envelope::new(response))?;
+        payload.push('\n');
+        writer.write_all(payload.as_bytes()).await?;
+        writer.flush().await?;
+    }
+
+    Ok(())
+}
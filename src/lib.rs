@@ -10,10 +10,12 @@ pub mod auth;
 pub mod config;
 pub mod daemon;
 pub mod error;
+pub mod ipc;
 pub mod logging;
 pub mod notifications;
 pub mod platform;
 pub mod sentry_integration;
+pub mod status_server;
 pub mod sync;
 pub mod tray;
 pub mod updater;
@@ -1,13 +1,26 @@
-use crate::error::Result;
-use env_logger::{Builder, Env, Target};
-use log::LevelFilter;
+use crate::config::settings::{LogFormat, LogSettings};
+use crate::error::{Result, SyncError};
+use env_logger::{Builder, Target};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
 use std::fs::{self, OpenOptions};
+use std::future::Future;
 use std::io::Write;
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
 
 static LOG_FILE: Mutex<Option<std::fs::File>> = Mutex::new(None);
 
+/// The installed logger; `apply` swaps what it wraps
+static LOGGER: ReloadableLogger = ReloadableLogger {
+    inner: RwLock::new(None),
+};
+
+tokio::task_local! {
+    /// Id of the sync run the current task works on
+    static SYNC_RUN: String;
+}
+
 // Maximum log file size before rotation (10 MB)
 const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
 // Maximum number of rotated log files to keep
@@ -50,7 +63,7 @@ fn rotate_logs_if_needed(log_file_path: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn init_logging(log_file_path: &Path) -> Result<()> {
+pub fn init_logging(log_file_path: &Path, settings: &LogSettings) -> Result<()> {
     // Rotate logs if needed before opening
     rotate_logs_if_needed(log_file_path)?;
 
@@ -63,19 +76,44 @@ pub fn init_logging(log_file_path: &Path) -> Result<()> {
     // Store the file handle for use by the logger
     *LOG_FILE.lock().unwrap() = Some(log_file);
 
-    // Initialize env_logger with custom output
-    let mut builder = Builder::from_env(Env::default().default_filter_or("warn,cook_sync=info"));
+    apply(settings);
+    log::set_logger(&LOGGER).map_err(|e| SyncError::Other(e.to_string()))
+}
+
+/// Use the level and format of `settings` from now on
+pub fn apply(settings: &LogSettings) {
+    let logger = build(settings);
+    log::set_max_level(logger.filter());
+    *LOGGER.inner.write().unwrap() = Some(logger);
+}
+
+/// Run `future` as one sync run; its log lines carry a fresh `sync_run` id
+pub async fn sync_run<F: Future>(future: F) -> F::Output {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    SYNC_RUN.scope(id[..8].to_string(), future).await
+}
+
+/// Wrap `f` so it runs in the current sync run, if any; task-locals do not
+/// follow work handed to `spawn_blocking`
+pub fn in_sync_run<R>(f: impl FnOnce() -> R) -> impl FnOnce() -> R {
+    let run = SYNC_RUN.try_with(Clone::clone).ok();
+    move || match run {
+        Some(run) => SYNC_RUN.sync_scope(run, f),
+        None => f(),
+    }
+}
+
+fn build(settings: &LogSettings) -> env_logger::Logger {
+    let mut builder = Builder::new();
 
     // Configure to write to both stderr and our log file
     builder.target(Target::Stderr);
+    let format = settings.format;
     builder.format(move |buf, record| {
-        // Format the log message
-        let formatted = format!(
-            "[{}] {} {}",
-            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
-            record.level(),
-            record.args()
-        );
+        let formatted = match format {
+            LogFormat::Text => text_line(record),
+            LogFormat::Json => json_line(record),
+        };
 
         // Write to stderr (console)
         writeln!(buf, "{}", &formatted)?;
@@ -91,15 +129,126 @@ pub fn init_logging(log_file_path: &Path) -> Result<()> {
         Ok(())
     });
 
-    // Set the log level from environment or default
-    if let Ok(rust_log) = std::env::var("RUST_LOG") {
-        builder.parse_filters(&rust_log);
-    } else {
-        builder.filter_level(LevelFilter::Warn);
-        builder.filter_module("cook_sync", LevelFilter::Info);
+    // The setting wins over RUST_LOG so a running daemon can be turned up
+    match settings.level.as_deref() {
+        Some(level) => {
+            builder.parse_filters(level);
+        }
+        None => match std::env::var("RUST_LOG") {
+            Ok(rust_log) => {
+                builder.parse_filters(&rust_log);
+            }
+            Err(_) => {
+                builder.filter_level(LevelFilter::Warn);
+                builder.filter_module("cook_sync", LevelFilter::Info);
+            }
+        },
     }
 
-    builder.init();
+    builder.build()
+}
 
-    Ok(())
+fn text_line(record: &Record) -> String {
+    format!(
+        "[{}] {} {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        record.level(),
+        record.args()
+    )
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: String,
+    level: &'a str,
+    target: &'a str,
+    module: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync_run: Option<String>,
+    message: String,
+}
+
+fn json_line(record: &Record) -> String {
+    let line = JsonLine {
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        level: record.level().as_str(),
+        target: record.target(),
+        module: record.module_path(),
+        sync_run: SYNC_RUN.try_with(Clone::clone).ok(),
+        message: record.args().to_string(),
+    };
+    serde_json::to_string(&line).unwrap_or_else(|_| text_line(record))
+}
+
+/// Forwards to an `env_logger` that can be replaced while running
+struct ReloadableLogger {
+    inner: RwLock<Option<env_logger::Logger>>,
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let inner = self.inner.read().unwrap();
+        inner
+            .as_ref()
+            .is_some_and(|logger| logger.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(logger) = self.inner.read().unwrap().as_ref() {
+            logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(logger) = self.inner.read().unwrap().as_ref() {
+            logger.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_json(record: &Record) -> serde_json::Value {
+        serde_json::from_str(&json_line(record)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_json_lines() {
+        let args = format_args!("Sync failed: \"offline\"");
+        let record = Record::builder()
+            .args(args)
+            .level(log::Level::Warn)
+            .target("cook_sync::sync::manager")
+            .module_path(Some("cook_sync::sync::manager"))
+            .build();
+
+        let json = to_json(&record);
+        assert_eq!(json["level"], "WARN");
+        assert_eq!(json["target"], "cook_sync::sync::manager");
+        assert_eq!(json["module"], "cook_sync::sync::manager");
+        assert_eq!(json["message"], "Sync failed: \"offline\"");
+        assert!(json.get("sync_run").is_none());
+
+        // Lines logged during the same run share its id
+        let (first, second) = sync_run(async { (to_json(&record), to_json(&record)) }).await;
+        let run = first["sync_run"].as_str().unwrap();
+        assert_eq!(run.len(), 8);
+        assert_eq!(second["sync_run"], run);
+        let other = sync_run(async { to_json(&record) }).await;
+        assert_ne!(other["sync_run"], run);
+
+        // Blocking work started during a run logs under its id
+        let (run, blocking) = sync_run(async {
+            let run = to_json(&record)["sync_run"].clone();
+            let blocking = tokio::task::spawn_blocking(in_sync_run(|| {
+                let args = format_args!("Indexed");
+                to_json(&Record::builder().args(args).build())
+            }));
+            (run, blocking.await.unwrap())
+        })
+        .await;
+        assert_eq!(blocking["sync_run"], run);
+    }
 }
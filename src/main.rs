@@ -5,10 +5,12 @@ mod auth;
 mod config;
 mod daemon;
 mod error;
+mod ipc;
 mod logging;
 mod notifications;
 mod platform;
 mod sentry_integration;
+mod status_server;
 mod sync;
 mod tray;
 mod updater;
@@ -24,7 +26,12 @@ use std::sync::Arc;
 #[command(name = "cook-sync")]
 #[command(about = "Cook.md Sync Agent for syncing recipes with Cook.md")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
+    /// Run a single sync pass in the foreground and exit (same as `sync-now`)
+    #[arg(long)]
+    once: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -32,23 +39,107 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Start the sync agent daemon
-    Start,
+    Start {
+        /// Run without system tray or welcome screen (auto-detected when there is no display)
+        #[arg(long)]
+        headless: bool,
+    },
 
     /// Start in daemon mode (used by auto-start)
-    Daemon,
+    Daemon {
+        /// Run without system tray; stop with SIGTERM or Ctrl+C
+        #[arg(long)]
+        headless: bool,
+    },
 
     /// Stop the running sync agent
     Stop,
 
     /// Show sync status
-    Status,
+    Status {
+        /// Print machine-readable JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Open browser for login
-    Login,
+    Login {
+        /// Log in by entering a code on another device, for machines without a browser
+        #[arg(long, conflicts_with_all = ["token_stdin", "token_env"])]
+        device: bool,
+
+        /// Log in with a token read from standard input, e.g. a device token
+        #[arg(long, conflicts_with = "token_env")]
+        token_stdin: bool,
+
+        /// Log in with the token in COOK_SYNC_TOKEN
+        #[arg(long)]
+        token_env: bool,
+    },
 
     /// Logout and clear session
     Logout,
 
+    /// Pause syncing in the running sync agent
+    Pause {
+        /// Only pause this synced folder
+        #[arg(long, conflicts_with = "until")]
+        folder: Option<std::path::PathBuf>,
+
+        /// Resume by itself after a while (30m, 2h, 1d), at a time (14:00)
+        /// or tomorrow morning (tomorrow)
+        #[arg(long = "for", visible_alias = "until", value_name = "WHEN", value_parser = sync::schedule::parse_pause_end)]
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    },
+
+    /// Resume syncing in the running sync agent
+    Resume {
+        /// Only resume this synced folder
+        #[arg(long)]
+        folder: Option<std::path::PathBuf>,
+    },
+
+    /// Ask the running sync agent to sync right away
+    Trigger,
+
+    /// Make the running sync agent re-read its settings
+    Reload,
+
+    /// Run a single sync pass in the foreground and exit
+    SyncNow,
+
+    /// List files and folders that are not synced because of ignore rules
+    Ignored {
+        /// Print machine-readable JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show what recent sync runs transferred
+    History {
+        /// Only runs since an age (30m, 12h, 7d, 2w), a date or a timestamp
+        #[arg(long, value_parser = sync::activity::parse_since)]
+        since: Option<chrono::DateTime<chrono::Utc>>,
+
+        /// Include runs that found nothing to do
+        #[arg(long)]
+        all: bool,
+
+        /// Print machine-readable JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List sync conflicts, or compare and resolve one
+    Conflicts {
+        #[command(subcommand)]
+        command: Option<ConflictCommand>,
+
+        /// Print machine-readable JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Configure sync settings
     Config {
         /// Set recipes directory
@@ -63,11 +154,42 @@ enum Commands {
         #[arg(long)]
         auto_update: Option<bool>,
 
+        /// Which way to sync: both, upload_only or download_only.
+        /// Applies to the folder given with --add-folder, if any
+        #[arg(long)]
+        sync_direction: Option<config::settings::SyncDirection>,
+
+        #[command(flatten)]
+        folders: FolderOptions,
+
+        #[command(flatten)]
+        ignore: IgnoreOptions,
+
+        #[command(flatten)]
+        retry: RetryOptions,
+
+        #[command(flatten)]
+        bandwidth: Box<BandwidthOptions>,
+
+        /// Only sync during these hours, e.g. 07:00-23:00 or
+        /// 07:00-12:00,14:00-23:00; an empty value syncs at any time
+        #[arg(long, value_name = "HOURS")]
+        sync_hours: Option<String>,
+
+        #[command(flatten)]
+        logging: Box<LogOptions>,
+
         /// Show current configuration
         #[arg(long, short)]
         show: bool,
     },
 
+    /// Manage account profiles, e.g. for a personal and a work account
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+
     /// Check for updates
     Update,
 
@@ -85,6 +207,114 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// List profiles, marking the active one
+    List,
+
+    /// Create a profile with its own login and recipe folders
+    Add { name: String },
+
+    /// Make a profile the active one
+    Switch { name: String },
+
+    /// Delete a profile, its login and its sync data (recipe files are kept)
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+enum ConflictCommand {
+    /// Show how a conflict copy differs from the recipe it belongs to
+    Diff { copy: std::path::PathBuf },
+
+    /// Settle a conflict by keeping one of the two versions
+    Resolve {
+        copy: std::path::PathBuf,
+
+        /// current keeps the recipe as it is now and deletes the copy;
+        /// copy replaces the recipe with the copy
+        #[arg(long)]
+        keep: sync::conflicts::Resolution,
+    },
+}
+
+/// Options of `config` for syncing further folders
+#[derive(clap::Args)]
+struct FolderOptions {
+    /// Sync another folder, next to the recipes directory
+    #[arg(long, value_name = "DIR")]
+    add_folder: Option<std::path::PathBuf>,
+
+    /// Folder on the server for --add-folder (default: the folder's name)
+    #[arg(long, requires = "add_folder")]
+    remote_folder: Option<String>,
+
+    /// Stop syncing a folder added with --add-folder (files are kept)
+    #[arg(long, value_name = "DIR")]
+    remove_folder: Option<std::path::PathBuf>,
+}
+
+/// Options of `config` for global ignore patterns
+#[derive(clap::Args)]
+struct IgnoreOptions {
+    /// Exclude files matching a gitignore-style pattern in every synced folder
+    #[arg(long, value_name = "PATTERN")]
+    ignore: Vec<String>,
+
+    /// Remove a pattern added with --ignore
+    #[arg(long, value_name = "PATTERN")]
+    unignore: Vec<String>,
+}
+
+/// Options of `config` for retrying failed syncs
+#[derive(clap::Args)]
+struct RetryOptions {
+    /// How often a failed sync is retried before waiting for the next change
+    #[arg(long, value_name = "COUNT")]
+    max_retries: Option<usize>,
+
+    /// Shortest wait before a retry, in seconds
+    #[arg(long, value_name = "SECS")]
+    retry_base_delay: Option<u64>,
+
+    /// Longest wait before a retry, in seconds
+    #[arg(long, value_name = "SECS")]
+    retry_max_delay: Option<u64>,
+}
+
+/// Options of `config` for logging
+#[derive(clap::Args)]
+struct LogOptions {
+    /// Log filter such as debug or warn,cook_sync=trace, applied by the running
+    /// agent on reload; an empty value goes back to RUST_LOG or the default
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<String>,
+
+    /// Layout of log lines: text or json
+    #[arg(long, value_name = "FORMAT")]
+    log_format: Option<config::settings::LogFormat>,
+}
+
+/// Options of `config` for transfer rates
+#[derive(clap::Args)]
+struct BandwidthOptions {
+    /// Upload limit in KB/s, 0 for unlimited
+    #[arg(long, value_name = "KB")]
+    max_upload_rate: Option<u64>,
+
+    /// Download limit in KB/s, 0 for unlimited
+    #[arg(long, value_name = "KB")]
+    max_download_rate: Option<u64>,
+
+    /// On metered connections: pause, throttle or ignore
+    #[arg(long, value_name = "POLICY")]
+    on_metered: Option<config::settings::MeteredPolicy>,
+
+    /// Limit in KB/s on metered connections with --on-metered throttle
+    #[arg(long, value_name = "KB")]
+    metered_rate: Option<u64>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // On Windows release builds, the binary uses windows_subsystem = "windows" to
@@ -99,7 +329,8 @@ async fn main() -> Result<()> {
     let log_file_path = config.paths().log_file.clone();
 
     // Initialize logger with file output
-    logging::init_logging(&log_file_path)?;
+    let log_settings = config.settings().lock().unwrap().logging.clone();
+    logging::init_logging(&log_file_path, &log_settings)?;
 
     // Initialize Sentry for error tracking
     sentry_integration::init_sentry();
@@ -114,31 +345,85 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
 
+    if cli.once {
+        return sync_once().await;
+    }
+
     match cli.command {
-        Some(Commands::Start) => start_daemon().await,
-        Some(Commands::Daemon) => run_daemon().await,
+        Some(Commands::Start { headless }) => start_daemon(headless).await,
+        Some(Commands::Daemon { headless }) => run_daemon(headless).await,
         Some(Commands::Stop) => stop_daemon(),
-        Some(Commands::Status) => show_status().await,
-        Some(Commands::Login) => login().await,
+        Some(Commands::Status { json }) => show_status(json).await,
+        Some(Commands::Login {
+            device,
+            token_stdin,
+            token_env,
+        }) => login(device, token_stdin, token_env).await,
         Some(Commands::Logout) => logout().await,
+        Some(Commands::Pause { folder, until }) => {
+            let folder = folder.map(std::path::absolute).transpose()?;
+            let message = match until {
+                Some(until) => format!(
+                    "Sync paused until {}",
+                    until.with_timezone(&chrono::Local).format("%a %H:%M")
+                ),
+                None => "Sync paused".to_string(),
+            };
+            control(ipc::protocol::Request::Pause { folder, until }, &message).await
+        }
+        Some(Commands::Resume { folder }) => {
+            let folder = folder.map(std::path::absolute).transpose()?;
+            control(ipc::protocol::Request::Resume { folder }, "Sync resumed").await
+        }
+        Some(Commands::Trigger) => control(ipc::protocol::Request::SyncNow, "Sync started").await,
+        Some(Commands::Reload) => {
+            control(ipc::protocol::Request::ReloadSettings, "Settings reloaded").await
+        }
+        Some(Commands::SyncNow) => sync_once().await,
+        Some(Commands::Ignored { json }) => list_ignored(json),
+        Some(Commands::History { since, all, json }) => history(since, all, json),
+        Some(Commands::Conflicts { command, json }) => conflicts(command, json),
         Some(Commands::Config {
             recipes_dir,
             auto_start,
             auto_update,
+            sync_direction,
+            folders,
+            ignore,
+            retry,
+            bandwidth,
+            sync_hours,
+            logging,
             show,
-        }) => configure(recipes_dir, auto_start, auto_update, show).await,
+        }) => {
+            configure(
+                recipes_dir,
+                auto_start,
+                auto_update,
+                sync_direction,
+                folders,
+                ignore,
+                retry,
+                bandwidth,
+                sync_hours,
+                logging,
+                show,
+            )
+            .await
+        }
+        Some(Commands::Profile { command }) => profile(command).await,
         Some(Commands::Update) => check_update().await,
         Some(Commands::Install) => install_integration(),
         Some(Commands::Uninstall) => uninstall_integration(),
         Some(Commands::Reset { yes }) => reset_all_data(yes).await,
         None => {
             // If no command specified, start the daemon
-            start_daemon().await
+            start_daemon(false).await
         }
     }
 }
 
-async fn start_daemon() -> Result<()> {
+async fn start_daemon(headless: bool) -> Result<()> {
     let config = config::Config::new()?;
 
     if daemon::is_already_running(&config) {
@@ -146,6 +431,8 @@ async fn start_daemon() -> Result<()> {
         return Ok(());
     }
 
+    let headless = headless || !platform::display_available();
+
     // Check if this is first run and show welcome screen BEFORE spawning daemon
     // This avoids event loop conflicts between egui and system tray
     let is_first_run = {
@@ -154,7 +441,12 @@ async fn start_daemon() -> Result<()> {
         !settings.welcome_shown
     };
 
-    if is_first_run {
+    if is_first_run && headless {
+        // No GUI to show; configure through the CLI instead
+        println!("Running headless. Configure with:");
+        println!("  cook-sync config --recipes-dir <DIR>");
+        println!("  cook-sync login");
+    } else if is_first_run {
         info!("First run detected, showing welcome screen");
 
         // Show welcome screen (blocks until closed)
@@ -168,6 +460,7 @@ async fn start_daemon() -> Result<()> {
             if let Some(dir) = welcome_result.recipes_dir {
                 s.recipes_dir = Some(dir);
             }
+            s.sync_direction = welcome_result.sync_direction;
         })?;
 
         // If user clicked login, perform login BEFORE starting daemon
@@ -178,7 +471,7 @@ async fn start_daemon() -> Result<()> {
             println!("Please complete login in your browser...");
 
             // Perform login synchronously before starting daemon
-            match login().await {
+            match login(false, false, false).await {
                 Ok(()) => {
                     println!("✓ Login successful!");
                 }
@@ -218,6 +511,9 @@ async fn start_daemon() -> Result<()> {
         // Use launchctl to start the daemon properly
         let mut cmd = Command::new(exe);
         cmd.arg("daemon");
+        if headless {
+            cmd.arg("--headless");
+        }
 
         // Set environment to ensure GUI access
         cmd.env("COOK_SYNC_DAEMON", "1");
@@ -251,6 +547,9 @@ async fn start_daemon() -> Result<()> {
         let exe = std::env::current_exe()?;
         let mut cmd = Command::new(exe);
         cmd.arg("daemon");
+        if headless {
+            cmd.arg("--headless");
+        }
 
         // Detach from parent, but keep display server access for tray icon
         // Only redirect stdin to avoid blocking on input
@@ -258,6 +557,11 @@ async fn start_daemon() -> Result<()> {
 
         // Start the process detached
         match cmd.spawn() {
+            Ok(_) if headless => {
+                println!("Cook Sync started successfully (headless)");
+                println!("Run 'cook-sync status' to check on it.");
+                Ok(())
+            }
             Ok(_) => {
                 println!("Cook Sync started successfully");
                 println!("The system tray icon should appear in your status bar.");
@@ -285,6 +589,9 @@ async fn start_daemon() -> Result<()> {
         let exe = std::env::current_exe()?;
         let mut cmd = Command::new(exe);
         cmd.arg("daemon");
+        if headless {
+            cmd.arg("--headless");
+        }
 
         // Don't hide stderr to capture potential errors during startup
         cmd.stdin(std::process::Stdio::null())
@@ -318,9 +625,18 @@ async fn start_daemon() -> Result<()> {
     }
 }
 
-async fn run_daemon() -> Result<()> {
+async fn run_daemon(headless: bool) -> Result<()> {
     let config = config::Config::new()?;
 
+    let headless = if headless {
+        true
+    } else if !platform::display_available() {
+        info!("No display detected, running headless");
+        true
+    } else {
+        false
+    };
+
     // On Linux and macOS, we avoid traditional fork-based daemonization
     // because system tray apps need to maintain access to the display server
     // (X11/Wayland on Linux, WindowServer on macOS)
@@ -339,7 +655,7 @@ async fn run_daemon() -> Result<()> {
 
     // Now run the actual daemon
     let daemon = daemon::Daemon::new().await?;
-    daemon.run().await
+    daemon.run(headless).await
 }
 
 fn stop_daemon() -> Result<()> {
@@ -389,21 +705,139 @@ fn stop_daemon() -> Result<()> {
     Ok(())
 }
 
-async fn show_status() -> Result<()> {
+async fn show_status(json: bool) -> Result<()> {
     let config = config::Config::new()?;
 
-    if !daemon::is_already_running(&config) {
+    let api = api::CookApi::new(config::settings::Settings::get_api_endpoint())?;
+    let auth = auth::AuthManager::new(config.paths(), Arc::new(api))?;
+    let user = auth.get_session().map(|s| s.email.unwrap_or(s.user_id));
+
+    let mut report = sync::report::StatusReport::not_running(auth.is_authenticated(), user);
+    report.recipes_dir = config.settings().lock().unwrap().recipes_dir.clone();
+
+    if daemon::is_already_running(&config) {
+        report.running = true;
+        report.pid = std::fs::read_to_string(&config.paths().pid_file)
+            .ok()
+            .and_then(|pid| pid.trim().parse().ok());
+
+        match ipc::request(&config.paths(), ipc::protocol::Request::Status).await {
+            Ok(ipc::protocol::Response::Status {
+                state,
+                authenticated,
+                user,
+                folders,
+            }) => {
+                // The daemon's view of the session wins over what is on disk
+                report.authenticated = authenticated;
+                report.user = user;
+                let now = chrono::Utc::now();
+                report = report.with_state(&state, now).with_folders(&folders, now);
+            }
+            Ok(other) => log::warn!("Unexpected status response from daemon: {other:?}"),
+            Err(e) => log::warn!("Could not query running daemon: {e}"),
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_status(&report, &config);
+    }
+
+    Ok(())
+}
+
+fn print_status(report: &sync::report::StatusReport, config: &config::Config) {
+    if !report.running {
         println!("Cook Sync is not running");
-        return Ok(());
+        return;
+    }
+
+    match report.pid {
+        Some(pid) => println!("Cook Sync is running (PID {pid})"),
+        None => println!("Cook Sync is running"),
     }
 
-    println!("Cook Sync is running");
+    if report.live {
+        if let Some(status) = report.status {
+            match report.paused_by {
+                Some(reason) => println!("  Status: {status} ({reason})"),
+                None => println!("  Status: {status}"),
+            }
+        }
+        match (report.last_sync, report.last_sync_age_secs) {
+            (Some(at), Some(age)) => println!(
+                "  Last successful sync: {} ({} ago)",
+                at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+                format_age(age)
+            ),
+            _ => println!("  Last successful sync: never"),
+        }
+        if let Some(progress) = &report.progress {
+            println!(
+                "  Progress: {progress}, {} transferred",
+                format_bytes(progress.bytes_transferred)
+            );
+        }
+        if report.items_pending > 0 {
+            println!("  Waiting to upload: {} files", report.items_pending);
+        }
+        if report.retry_attempt > 0 {
+            match (report.next_retry_at, report.next_retry_in_secs) {
+                (Some(at), Some(secs)) => println!(
+                    "  Retry attempt: {}, next at {} (in {})",
+                    report.retry_attempt,
+                    at.with_timezone(&chrono::Local).format("%H:%M:%S"),
+                    format_age(secs)
+                ),
+                _ => println!("  Retry attempt: {}", report.retry_attempt),
+            }
+        }
+        if let Some(message) = &report.error_message {
+            println!("  Error: {message}");
+        }
+        if let Some(last_error) = &report.last_error {
+            if report.error_message.as_ref() != Some(last_error) {
+                println!("  Last error: {last_error}");
+            }
+        }
+        if !report.conflicts.is_empty() {
+            println!(
+                "  Conflicts: {} (see 'cook-sync conflicts')",
+                report.conflicts.len()
+            );
+        }
+        if report.folders.len() > 1 {
+            println!("  Folders:");
+            for folder in &report.folders {
+                let last_sync = folder
+                    .last_sync_age_secs
+                    .map(|age| format!("synced {} ago", format_age(age)))
+                    .unwrap_or_else(|| "never synced".to_string());
+                let status = match (&folder.progress, folder.paused_by) {
+                    (Some(progress), _) => progress.to_string(),
+                    (None, Some(reason)) => format!("{} ({reason})", folder.status),
+                    (None, None) => folder.status.to_string(),
+                };
+                println!("    {}: {status} ({last_sync})", folder.path.display());
+                if let Some(message) = &folder.error_message {
+                    println!("      Error: {message}");
+                }
+            }
+        }
+    } else {
+        println!("  Live status unavailable (daemon did not respond)");
+    }
 
     // Load and display current settings
     let settings = config.settings();
     let settings = settings.lock().unwrap();
 
     println!("Configuration:");
+    if config.profiles().is_ok_and(|p| !p.extra.is_empty()) {
+        println!("  Profile: {}", config.profile());
+    }
     println!(
         "  Recipes directory: {}",
         settings
@@ -412,30 +846,151 @@ async fn show_status() -> Result<()> {
             .map(|p| p.display().to_string())
             .unwrap_or_else(|| "Not configured".to_string())
     );
+    for root in &settings.sync_roots {
+        println!(
+            "  Also syncing: {} <-> {}",
+            root.path.display(),
+            root.remote_folder
+        );
+    }
     println!("  Auto-start: {}", settings.auto_start);
     println!("  Auto-update: {}", settings.auto_update);
-    println!("  Sync interval: {} seconds", settings.sync_interval_secs);
-
-    // Check authentication status
-    let api = api::CookApi::new(config::settings::Settings::get_api_endpoint())?;
-    let auth = auth::AuthManager::new(config.paths(), Arc::new(api))?;
+    println!(
+        "  Fallback sync interval: {} seconds",
+        settings.fallback_interval_secs
+    );
 
-    if auth.is_authenticated() {
-        if let Some(session) = auth.get_session() {
-            println!(
-                "\nAuthenticated as: {}",
-                session.email.unwrap_or(session.user_id)
-            );
+    if report.authenticated {
+        if let Some(user) = &report.user {
+            println!("\nAuthenticated as: {user}");
         }
     } else {
         println!("\nNot authenticated. Run 'cook-sync login' to authenticate.");
     }
+}
 
-    Ok(())
+/// Human-friendly rendering of an age in seconds, e.g. "5m 3s"
+fn format_age(secs: i64) -> String {
+    match secs {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m {}s", s / 60, s % 60),
+        s if s < 86400 => format!("{}h {}m", s / 3600, (s % 3600) / 60),
+        s => format!("{}d {}h", s / 86400, (s % 86400) / 3600),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{b} B"),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b if b < 1024 * 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b => format!("{:.1} GB", b as f64 / (1024.0 * 1024.0 * 1024.0)),
+    }
+}
+
+/// Exit codes of `sync-now` / `--once`, relied upon by scripts
+mod exit_code {
+    pub const SYNC_FAILED: i32 = 1;
+    pub const NOT_AUTHENTICATED: i32 = 2;
+    pub const NOT_CONFIGURED: i32 = 3;
+    pub const AGENT_RUNNING: i32 = 4;
+}
+
+async fn sync_once() -> Result<()> {
+    let config = Arc::new(config::Config::new()?);
+
+    // Two writers on the same database and directory would step on each other
+    if daemon::is_already_running(&config) {
+        eprintln!(
+            "Cook Sync is already running in the background. Use 'cook-sync trigger' to sync right away."
+        );
+        std::process::exit(exit_code::AGENT_RUNNING);
+    }
+
+    let api = api::CookApi::new(config::settings::Settings::get_api_endpoint())?;
+    let auth = Arc::new(auth::AuthManager::new(config.paths(), Arc::new(api))?);
+
+    if let Err(e) = auth.login_from_env().await {
+        log::warn!("Could not log in with {}: {e}", auth::TOKEN_ENV);
+    }
+    if let Err(e) = auth.refresh_if_needed().await {
+        log::warn!("Could not refresh session before sync: {e}");
+    }
+
+    let sync_manager = sync::SyncManager::new(Arc::clone(&auth), Arc::clone(&config));
+    let started = std::time::Instant::now();
+
+    match sync_manager.run_once().await {
+        Ok(outcomes) => {
+            let mut pending_uploads = 0;
+            for (root, outcome) in &outcomes {
+                if outcome.is_empty() {
+                    println!("{} is up to date", root.path.display());
+                } else {
+                    println!("Synced {}", root.path.display());
+                }
+                println!("  Uploaded: {}", outcome.uploaded.len());
+                println!("  Downloaded: {}", outcome.downloaded.len());
+                println!("  Removed: {}", outcome.removed.len());
+                if outcome.pending_uploads > 0 {
+                    println!("  Still waiting to upload: {}", outcome.pending_uploads);
+                }
+                for conflict in &outcome.conflicts {
+                    println!(
+                        "  Conflict: {} (this computer's version kept as {})",
+                        conflict.path.display(),
+                        conflict.copy.display()
+                    );
+                }
+                pending_uploads += outcome.pending_uploads;
+            }
+            println!("Took {:.1}s", started.elapsed().as_secs_f64());
+
+            if pending_uploads > 0 {
+                std::process::exit(exit_code::SYNC_FAILED);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Sync failed: {e}");
+            let code = match e {
+                error::SyncError::AuthenticationRequired => {
+                    eprintln!("Run 'cook-sync login' to authenticate.");
+                    exit_code::NOT_AUTHENTICATED
+                }
+                error::SyncError::InvalidConfiguration(_) => {
+                    eprintln!("Set it with 'cook-sync config --recipes-dir <DIR>'.");
+                    exit_code::NOT_CONFIGURED
+                }
+                _ => exit_code::SYNC_FAILED,
+            };
+            std::process::exit(code);
+        }
+    }
 }
 
-async fn login() -> Result<()> {
-    println!("Opening browser for login...");
+async fn login(device: bool, token_stdin: bool, token_env: bool) -> Result<()> {
+    // A token given on standard input or in the environment needs no browser
+    let token = if token_stdin {
+        println!("Using the token from standard input");
+        let token = std::io::read_to_string(std::io::stdin())?;
+        if token.trim().is_empty() {
+            return Err(error::SyncError::Other(
+                "No token on standard input".to_string(),
+            ));
+        }
+        Some(token)
+    } else if token_env {
+        println!("Using the token from {}", auth::TOKEN_ENV);
+        let token = auth::token_from_env()
+            .ok_or_else(|| error::SyncError::Other(format!("{} is not set", auth::TOKEN_ENV)))?;
+        Some(token)
+    } else {
+        None
+    };
+    if token.is_none() && !device {
+        println!("Opening browser for login...");
+    }
 
     let config = config::Config::new()?;
     let api_endpoint = config::settings::Settings::get_api_endpoint();
@@ -443,8 +998,25 @@ async fn login() -> Result<()> {
     let api = api::CookApi::new(api_endpoint)?;
     let auth = auth::AuthManager::new(config.paths(), Arc::new(api))?;
 
-    // Perform browser-based login
-    match auth.browser_login().await {
+    let result = if let Some(token) = token {
+        auth.token_login(&token).await
+    } else if device {
+        auth.device_login(|authorization| {
+            println!();
+            println!("To log in, open {}", authorization.verification_uri);
+            println!("and enter the code: {}", authorization.user_code);
+            if let Some(uri) = &authorization.verification_uri_complete {
+                println!("Or open {uri}");
+            }
+            println!();
+            println!("Waiting for the login to be approved...");
+        })
+        .await
+    } else {
+        auth.browser_login().await
+    };
+
+    match result {
         Ok(()) => {
             println!("Successfully authenticated!");
             if let Some(session) = auth.get_session() {
@@ -468,28 +1040,41 @@ async fn logout() -> Result<()> {
     // Clear the auth session first
     auth.logout()?;
 
-    // If daemon is running, we need to inform it to stop syncing
-    // For now, the simplest approach is to restart the daemon
-    // In the future, we should implement IPC to notify the running daemon
+    // If daemon is running, tell it to drop its session and stop syncing
     if daemon::is_already_running(&config) {
-        println!("Stopping sync agent to apply logout...");
-        stop_daemon()?;
+        match ipc::command(&config.paths(), ipc::protocol::Request::Logout).await {
+            Ok(()) => info!("Running sync agent logged out"),
+            Err(e) => {
+                // Older daemons have no control channel, fall back to a restart
+                log::warn!("Could not notify running daemon of logout: {e}");
+                println!("Stopping sync agent to apply logout...");
+                stop_daemon()?;
 
-        // Give it a moment to properly shutdown
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                // Give it a moment to properly shutdown
+                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
 
-        println!("Restarting sync agent without authentication...");
-        start_daemon().await?;
+                println!("Restarting sync agent without authentication...");
+                start_daemon(false).await?;
+            }
+        }
     }
 
     println!("Logged out successfully");
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn configure(
     recipes_dir: Option<String>,
     auto_start: Option<bool>,
     auto_update: Option<bool>,
+    sync_direction: Option<config::settings::SyncDirection>,
+    folders: FolderOptions,
+    ignores: IgnoreOptions,
+    retry: RetryOptions,
+    bandwidth: Box<BandwidthOptions>,
+    sync_hours: Option<String>,
+    logging: Box<LogOptions>,
     show: bool,
 ) -> Result<()> {
     let config = config::Config::new()?;
@@ -508,6 +1093,83 @@ async fn configure(
         );
         println!("  Auto-start: {}", settings.auto_start);
         println!("  Auto-update: {}", settings.auto_update);
+        println!(
+            "  Sync direction: {} ({})",
+            settings.sync_direction,
+            settings.sync_direction.label()
+        );
+        for root in &settings.sync_roots {
+            println!(
+                "  Also syncing: {} <-> {} ({})",
+                root.path.display(),
+                root.remote_folder,
+                root.sync_direction.label()
+            );
+        }
+        if !settings.ignore_patterns.is_empty() {
+            println!("  Ignore patterns: {}", settings.ignore_patterns.join(", "));
+        }
+        println!(
+            "  Retries: up to {}, waiting {}s to {}s",
+            settings.retry.max_retries,
+            settings.retry.base_delay_secs,
+            settings.retry.max_delay_secs
+        );
+        let rate =
+            |limit: Option<u64>| limit.map_or("unlimited".to_string(), |kb| format!("{kb} KB/s"));
+        println!(
+            "  Upload limit: {}, download limit: {}",
+            rate(settings.bandwidth.max_upload_kb_per_sec),
+            rate(settings.bandwidth.max_download_kb_per_sec)
+        );
+        match settings.bandwidth.on_metered {
+            config::settings::MeteredPolicy::Throttle => println!(
+                "  On metered connections: throttle to {} KB/s",
+                settings.bandwidth.metered_kb_per_sec
+            ),
+            policy => println!("  On metered connections: {policy}"),
+        }
+        if !settings.schedule.sync_windows.is_empty() {
+            let windows: Vec<String> = settings
+                .schedule
+                .sync_windows
+                .iter()
+                .map(|w| w.to_string())
+                .collect();
+            println!("  Sync hours: {}", windows.join(", "));
+        }
+        let hooks = &settings.hooks;
+        for (event, command) in [
+            ("on_sync_complete", &hooks.on_sync_complete),
+            ("on_file_downloaded", &hooks.on_file_downloaded),
+            ("on_conflict", &hooks.on_conflict),
+            ("on_error", &hooks.on_error),
+        ] {
+            if let Some(command) = command {
+                println!("  Hook {event}: {command}");
+            }
+        }
+        println!(
+            "  Log level: {}, format: {}",
+            settings.logging.level.as_deref().unwrap_or("default"),
+            settings.logging.format
+        );
+        if settings.status_server.enabled {
+            println!(
+                "  Status server: http://127.0.0.1:{}",
+                settings.status_server.port
+            );
+        }
+        if let Some(until) = settings
+            .schedule
+            .paused_until
+            .filter(|until| *until > chrono::Utc::now())
+        {
+            println!(
+                "  Paused until: {}",
+                until.with_timezone(&chrono::Local).format("%a %H:%M")
+            );
+        }
         println!(
             "  API endpoint: {}",
             config::settings::Settings::get_api_endpoint()
@@ -528,6 +1190,9 @@ async fn configure(
                 "Directory does not exist: {dir}"
             )));
         }
+        let mut candidate = config.settings().lock().unwrap().clone();
+        candidate.recipes_dir = Some(path.clone());
+        candidate.validate()?;
         config.update_settings(|s| {
             s.recipes_dir = Some(path);
         })?;
@@ -535,6 +1200,61 @@ async fn configure(
         changed = true;
     }
 
+    if let Some(dir) = folders.add_folder {
+        let path = std::path::absolute(&dir)?;
+        let remote_folder = match folders.remote_folder {
+            Some(folder) => folder,
+            None => path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        let mut root = config::settings::SyncRoot::new(path, &remote_folder)?;
+        if let Some(direction) = sync_direction {
+            root.sync_direction = direction;
+        }
+        config.settings().lock().unwrap().check_new_root(&root)?;
+
+        println!(
+            "Syncing {} with '{}' on the server",
+            root.path.display(),
+            root.remote_folder
+        );
+        config.update_settings(|s| s.sync_roots.push(root))?;
+        changed = true;
+    } else if let Some(direction) = sync_direction {
+        config.update_settings(|s| {
+            s.sync_direction = direction;
+        })?;
+        println!("Sync direction set to: {}", direction.label());
+        changed = true;
+    }
+
+    if let Some(dir) = folders.remove_folder {
+        let path = std::path::absolute(&dir)?;
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        let root = config
+            .settings()
+            .lock()
+            .unwrap()
+            .sync_roots
+            .iter()
+            .find(|r| r.path == path || r.path.canonicalize().is_ok_and(|p| p == canonical))
+            .cloned()
+            .ok_or_else(|| {
+                error::SyncError::InvalidConfiguration(format!(
+                    "Not a folder added with --add-folder: {}",
+                    dir.display()
+                ))
+            })?;
+
+        config.update_settings(|s| s.sync_roots.retain(|r| r.id != root.id))?;
+        // The folder's sync state is useless without it
+        let _ = std::fs::remove_file(config.paths().root_database_file(&root.id));
+        println!("Stopped syncing {}", root.path.display());
+        changed = true;
+    }
+
     if let Some(enabled) = auto_start {
         config.update_settings(|s| {
             s.auto_start = enabled;
@@ -589,10 +1309,401 @@ async fn configure(
         changed = true;
     }
 
+    for pattern in ignores.ignore {
+        // Reject patterns the sync loop would have to skip
+        ignore::gitignore::GitignoreBuilder::new("")
+            .add_line(None, &pattern)
+            .map_err(|e| {
+                error::SyncError::InvalidConfiguration(format!(
+                    "Invalid ignore pattern '{pattern}': {e}"
+                ))
+            })?;
+        config.update_settings(|s| {
+            if !s.ignore_patterns.contains(&pattern) {
+                s.ignore_patterns.push(pattern.clone());
+            }
+        })?;
+        println!("Ignoring: {pattern}");
+        changed = true;
+    }
+
+    for pattern in ignores.unignore {
+        let mut found = false;
+        config.update_settings(|s| {
+            found = s.ignore_patterns.contains(&pattern);
+            s.ignore_patterns.retain(|p| *p != pattern);
+        })?;
+        if !found {
+            return Err(error::SyncError::InvalidConfiguration(format!(
+                "Not an ignore pattern: {pattern}"
+            )));
+        }
+        println!("No longer ignoring: {pattern}");
+        changed = true;
+    }
+
+    if retry.max_retries.is_some()
+        || retry.retry_base_delay.is_some()
+        || retry.retry_max_delay.is_some()
+    {
+        let mut candidate = config.settings().lock().unwrap().retry.clone();
+        if let Some(count) = retry.max_retries {
+            candidate.max_retries = count;
+        }
+        if let Some(secs) = retry.retry_base_delay {
+            candidate.base_delay_secs = secs;
+        }
+        if let Some(secs) = retry.retry_max_delay {
+            candidate.max_delay_secs = secs;
+        }
+        candidate.validate()?;
+        println!(
+            "Failed syncs are retried up to {} times, waiting {}s to {}s",
+            candidate.max_retries, candidate.base_delay_secs, candidate.max_delay_secs
+        );
+        config.update_settings(|s| s.retry = candidate)?;
+        changed = true;
+    }
+
+    if bandwidth.max_upload_rate.is_some()
+        || bandwidth.max_download_rate.is_some()
+        || bandwidth.on_metered.is_some()
+        || bandwidth.metered_rate.is_some()
+    {
+        let mut candidate = config.settings().lock().unwrap().bandwidth.clone();
+        let limit = |kb: u64| (kb > 0).then_some(kb);
+        if let Some(kb) = bandwidth.max_upload_rate {
+            candidate.max_upload_kb_per_sec = limit(kb);
+        }
+        if let Some(kb) = bandwidth.max_download_rate {
+            candidate.max_download_kb_per_sec = limit(kb);
+        }
+        if let Some(policy) = bandwidth.on_metered {
+            candidate.on_metered = policy;
+        }
+        if let Some(kb) = bandwidth.metered_rate {
+            candidate.metered_kb_per_sec = kb;
+        }
+        candidate.validate()?;
+        println!("Transfer limits updated");
+        config.update_settings(|s| s.bandwidth = candidate)?;
+        changed = true;
+    }
+
+    if let Some(hours) = sync_hours {
+        let windows =
+            sync::schedule::parse_sync_windows(&hours).map_err(error::SyncError::Other)?;
+        if windows.is_empty() {
+            println!("Syncing at any time of day");
+        } else {
+            let list: Vec<String> = windows.iter().map(|w| w.to_string()).collect();
+            println!("Syncing only during {}", list.join(", "));
+        }
+        config.update_settings(|s| s.schedule.sync_windows = windows)?;
+        changed = true;
+    }
+
+    if logging.log_level.is_some() || logging.log_format.is_some() {
+        let mut candidate = config.settings().lock().unwrap().logging.clone();
+        if let Some(level) = logging.log_level {
+            candidate.level = Some(level.trim().to_string()).filter(|l| !l.is_empty());
+        }
+        if let Some(format) = logging.log_format {
+            candidate.format = format;
+        }
+        candidate.validate()?;
+        println!(
+            "Logging {} lines at level {}",
+            candidate.format,
+            candidate.level.as_deref().unwrap_or("default")
+        );
+        config.update_settings(|s| s.logging = candidate)?;
+        changed = true;
+    }
+
     if !changed {
         println!("No changes made. Use --show to see current configuration.");
+    } else if daemon::is_already_running(&config) {
+        // Apply the new settings without restarting the agent
+        if let Err(e) = ipc::command(&config.paths(), ipc::protocol::Request::ReloadSettings).await
+        {
+            log::warn!("Could not notify running daemon of new settings: {e}");
+            println!("Restart Cook Sync to apply the new settings.");
+        }
+    }
+
+    Ok(())
+}
+
+async fn profile(command: ProfileCommand) -> Result<()> {
+    let config = config::Config::new()?;
+    let paths = config.paths();
+    let mut profiles = config.profiles()?;
+
+    match command {
+        ProfileCommand::List => {
+            for name in profiles.names() {
+                let marker = if name == profiles.active { "*" } else { " " };
+                let user = auth::secure_session::SecureSession::load(&name)
+                    .ok()
+                    .flatten()
+                    .map(|s| s.email.unwrap_or(s.user_id))
+                    .unwrap_or_else(|| "not logged in".to_string());
+                let settings =
+                    config::Settings::load(&config::AppPaths::for_profile(&name)?.settings_file)?;
+                let folder = settings
+                    .recipes_dir
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "no recipes directory".to_string());
+                println!("{marker} {name} ({user}, {folder})");
+            }
+        }
+        ProfileCommand::Add { name } => {
+            profiles.add(&name)?;
+
+            // Start from the current preferences, but without folders
+            let settings = {
+                let current = config.settings();
+                let current = current.lock().unwrap();
+                config::Settings {
+                    recipes_dir: None,
+                    sync_roots: Vec::new(),
+                    sync_direction: Default::default(),
+                    ..current.clone()
+                }
+            };
+            settings.save(&config::AppPaths::for_profile(&name)?.settings_file)?;
+            profiles.save(&paths.profiles_file)?;
+
+            println!("Profile '{name}' created");
+            println!("Switch to it with 'cook-sync profile switch {name}', then log in and set its recipes directory");
+        }
+        ProfileCommand::Switch { name } => {
+            if daemon::is_already_running(&config) {
+                // The agent stops syncing the old profile before switching
+                ipc::command(
+                    &paths,
+                    ipc::protocol::Request::SwitchProfile { name: name.clone() },
+                )
+                .await?;
+            } else {
+                config.switch_profile(&name)?;
+            }
+            println!("Switched to profile '{name}'");
+        }
+        ProfileCommand::Remove { name } => {
+            profiles.remove(&name)?;
+            auth::secure_session::SecureSession::delete(&name)?;
+            paths.remove_profile_data(&name)?;
+            profiles.save(&paths.profiles_file)?;
+            println!("Profile '{name}' removed");
+        }
+    }
+
+    Ok(())
+}
+
+/// List what the ignore rules exclude in each synced folder
+fn list_ignored(json: bool) -> Result<()> {
+    let config = config::Config::new()?;
+    let (roots, patterns) = {
+        let settings = config.settings();
+        let settings = settings.lock().unwrap();
+        (settings.roots(), settings.ignore_patterns.clone())
+    };
+
+    let folders: Vec<_> = roots
+        .iter()
+        .map(|root| {
+            let rules = sync::ignore_rules::IgnoreRules::load(&root.path, &patterns);
+            (root, rules.list_ignored(&root.path))
+        })
+        .collect();
+
+    if json {
+        let folders: Vec<_> = folders
+            .iter()
+            .map(|(root, ignored)| serde_json::json!({ "folder": root.path, "ignored": ignored }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&folders)?);
+        return Ok(());
+    }
+
+    if folders.is_empty() {
+        println!("No recipes directory configured");
+    }
+    for (root, ignored) in folders {
+        println!("{}", root.path.display());
+        if ignored.is_empty() {
+            println!("  Nothing ignored");
+        }
+        for item in ignored {
+            let slash = if item.is_dir { "/" } else { "" };
+            println!("  {}{slash}  ({})", item.path, item.reason);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print sync runs from the activity journal, newest first
+fn history(since: Option<chrono::DateTime<chrono::Utc>>, all: bool, json: bool) -> Result<()> {
+    use sync::activity::{ActivityJournal, FileAction};
+
+    // Without a time range, show the last few runs that did something
+    const DEFAULT_RUNS: usize = 20;
+
+    let config = config::Config::new()?;
+    let journal = ActivityJournal::open(&config.paths().activity_file)?;
+    let runs: Vec<_> = match (since, all) {
+        (None, false) => journal.recent(DEFAULT_RUNS)?,
+        (since, all) => journal
+            .runs(since)?
+            .into_iter()
+            .filter(|run| all || run.is_notable())
+            .collect(),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&runs)?);
+        return Ok(());
+    }
+
+    if runs.is_empty() {
+        println!("No sync activity recorded");
+    }
+    for run in &runs {
+        let took = (run.finished_at - run.started_at).num_milliseconds() as f64 / 1000.0;
+        let summary = if let Some(error) = &run.error {
+            format!("failed: {error}")
+        } else if run.files.is_empty() {
+            "nothing to do".to_string()
+        } else {
+            [
+                (FileAction::Uploaded, "uploaded"),
+                (FileAction::Downloaded, "downloaded"),
+                (FileAction::Removed, "removed"),
+                (FileAction::Conflict, "conflicts"),
+            ]
+            .into_iter()
+            .filter_map(|(action, label)| {
+                let count = run.count(action);
+                (count > 0).then(|| format!("{count} {label}"))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+        };
+
+        println!(
+            "{}  {} ({}, {took:.1}s): {summary}",
+            run.started_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            run.folder.display(),
+            run.direction
+        );
+        for file in &run.files {
+            println!("    {:<10} {}", file.action, file.path);
+        }
+    }
+
+    Ok(())
+}
+
+/// List the conflict copies in all synced folders, or diff/resolve one
+fn conflicts(command: Option<ConflictCommand>, json: bool) -> Result<()> {
+    let config = config::Config::new()?;
+    let (roots, ignore_patterns) = {
+        let settings = config.settings();
+        let settings = settings.lock().unwrap();
+        (settings.roots(), settings.ignore_patterns.clone())
+    };
+
+    match command {
+        None => {
+            let conflicts: Vec<_> = roots
+                .iter()
+                .flat_map(|root| {
+                    let rules = sync::ignore_rules::IgnoreRules::load(&root.path, &ignore_patterns);
+                    sync::conflicts::find(&root.path, &rules)
+                })
+                .collect();
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&conflicts)?);
+            } else if conflicts.is_empty() {
+                println!("No conflicts");
+            } else {
+                for conflict in &conflicts {
+                    println!("{}", conflict.path_abs().display());
+                    println!(
+                        "  {} (from {}, {})",
+                        conflict.copy_abs().display(),
+                        conflict.host,
+                        conflict
+                            .created_at
+                            .with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M")
+                    );
+                }
+                println!();
+                println!("Compare with 'cook-sync conflicts diff <COPY>', settle with 'cook-sync conflicts resolve <COPY> --keep current|copy'.");
+            }
+        }
+        Some(ConflictCommand::Diff { copy }) => {
+            let conflict = find_conflict(&roots, &copy)?;
+            print!("{}", sync::conflicts::diff(&conflict)?);
+        }
+        Some(ConflictCommand::Resolve { copy, keep }) => {
+            let conflict = find_conflict(&roots, &copy)?;
+            sync::conflicts::resolve(&conflict, keep)?;
+            match keep {
+                sync::conflicts::Resolution::Current => {
+                    println!("Kept {}", conflict.path_abs().display())
+                }
+                sync::conflicts::Resolution::Copy => println!(
+                    "Replaced {} with the conflict copy",
+                    conflict.path_abs().display()
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The conflict a copy given on the command line belongs to
+fn find_conflict(
+    roots: &[config::settings::SyncRoot],
+    copy: &std::path::Path,
+) -> Result<sync::conflicts::Conflict> {
+    let copy = std::path::absolute(copy)?;
+
+    roots
+        .iter()
+        .find_map(|root| {
+            let relative = copy.strip_prefix(&root.path).ok()?;
+            sync::conflicts::parse(&root.path, relative)
+        })
+        .ok_or_else(|| {
+            error::SyncError::Other(format!(
+                "{} is not a conflict copy in a synced folder",
+                copy.display()
+            ))
+        })
+}
+
+/// Send a control request to the running daemon and report the outcome
+async fn control(request: ipc::protocol::Request, success_message: &str) -> Result<()> {
+    let config = config::Config::new()?;
+
+    if !daemon::is_already_running(&config) {
+        println!("Cook Sync is not running");
+        return Ok(());
     }
 
+    ipc::command(&config.paths(), request).await?;
+    println!("{success_message}");
     Ok(())
 }
 
@@ -798,13 +1909,32 @@ async fn reset_all_data(skip_confirmation: bool) -> Result<()> {
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
     }
 
-    let paths = config.paths();
+    // The default profile keeps its files at the top of the config and data
+    // directories, whichever profile is active
+    let paths = config::AppPaths::for_profile(config::profiles::DEFAULT_PROFILE)?;
     let mut errors = Vec::new();
 
-    // Clear session from keyring
+    // Clear the sessions of all profiles from keyring
     println!("  🔑 Clearing authentication session...");
-    if let Err(e) = auth::secure_session::SecureSession::delete() {
-        errors.push(format!("Failed to clear keyring session: {}", e));
+    let profiles = config.profiles().unwrap_or_default();
+    for profile in profiles.names() {
+        if let Err(e) = auth::secure_session::SecureSession::delete(&profile) {
+            errors.push(format!("Failed to clear keyring session: {}", e));
+        }
+    }
+
+    // Delete the settings and databases of extra profiles
+    for profile in &profiles.extra {
+        if let Err(e) = paths.remove_profile_data(profile) {
+            errors.push(format!("Failed to delete profile '{}': {}", profile, e));
+        }
+    }
+    let _ = std::fs::remove_dir(paths.config_dir.join("profiles"));
+    let _ = std::fs::remove_dir(paths.data_dir.join("profiles"));
+    if paths.profiles_file.exists() {
+        if let Err(e) = std::fs::remove_file(&paths.profiles_file) {
+            errors.push(format!("Failed to delete profiles: {}", e));
+        }
     }
 
     // Delete settings file
@@ -837,6 +1967,21 @@ async fn reset_all_data(skip_confirmation: bool) -> Result<()> {
         }
     }
 
+    // Delete the databases of extra synced folders
+    let roots_dir = paths.database_file.with_file_name("roots");
+    if roots_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&roots_dir) {
+            errors.push(format!("Failed to delete folder databases: {}", e));
+        }
+    }
+
+    // Delete activity journal
+    if paths.activity_file.exists() {
+        if let Err(e) = std::fs::remove_file(&paths.activity_file) {
+            errors.push(format!("Failed to delete activity journal: {}", e));
+        }
+    }
+
     // Delete updates file
     if paths.updates_file.exists() {
         if let Err(e) = std::fs::remove_file(&paths.updates_file) {
@@ -866,8 +2011,11 @@ async fn reset_all_data(skip_confirmation: bool) -> Result<()> {
     }
 
     // Try to remove directories if empty
-    let _ = std::fs::remove_dir(&paths.config_dir);
-    let _ = std::fs::remove_dir(&paths.data_dir);
+    for dir in [&paths.config_dir, &paths.data_dir] {
+        if std::fs::remove_dir(dir).is_err() && dir.exists() {
+            errors.push(format!("Some files were left in {}", dir.display()));
+        }
+    }
 
     println!();
 
@@ -71,3 +71,19 @@ pub fn get_platform() -> Box<dyn PlatformIntegration> {
 pub fn get_platform() -> Box<dyn PlatformIntegration> {
     Box::new(windows::WindowsIntegration)
 }
+
+/// Whether a graphical session is available for the tray icon and welcome screen.
+/// Servers and containers without X11/Wayland run the agent headless.
+#[cfg(target_os = "linux")]
+pub fn display_available() -> bool {
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()))
+}
+
+/// Whether a graphical session is available for the tray icon and welcome screen.
+/// macOS and Windows always have one for interactive users.
+#[cfg(not(target_os = "linux"))]
+pub fn display_available() -> bool {
+    true
+}
@@ -0,0 +1,411 @@
+//! Local HTTP endpoint for dashboards and health checks.
+//!
+//! When enabled in the settings, the daemon answers `GET /healthz`,
+//! `/status` (the same report as `cook-sync status --json`) and `/metrics`
+//! (Prometheus text format) on 127.0.0.1. It never listens on other
+//! interfaces, and requests naming another host are refused so web pages
+//! cannot read the status through DNS rebinding.
+
+use crate::config::settings::StatusServerSettings;
+use crate::error::Result;
+use crate::sync::report::StatusReport;
+use chrono::{DateTime, Utc};
+use log::{debug, error, info};
+use std::fmt::Write as _;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Longest request head read before answering
+const MAX_REQUEST_HEAD: usize = 8192;
+/// Connections that send no complete request by then are dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+const TEXT: &str = "text/plain; charset=utf-8";
+const JSON: &str = "application/json";
+const PROMETHEUS: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Implemented by whatever owns the live agent state (the daemon in production)
+pub trait StatusSource: Send + Sync + 'static {
+    fn report(&self) -> StatusReport;
+    /// When the session token runs out; `None` when signed out
+    fn token_expires_at(&self) -> Option<DateTime<Utc>>;
+}
+
+/// Runs the server while the settings ask for it
+pub struct StatusServer<S> {
+    source: Arc<S>,
+    running: Mutex<Option<(StatusServerSettings, JoinHandle<()>)>>,
+}
+
+impl<S: StatusSource> StatusServer<S> {
+    pub fn new(source: Arc<S>) -> Self {
+        Self {
+            source,
+            running: Mutex::new(None),
+        }
+    }
+
+    /// Start, stop or move the server to match `settings`. A server that
+    /// could not listen is tried again.
+    pub fn apply(&self, settings: StatusServerSettings) {
+        let mut running = self.running.lock().unwrap();
+        let wanted = settings.enabled.then_some(settings);
+        let current = running
+            .as_ref()
+            .filter(|(_, task)| !task.is_finished())
+            .map(|(settings, _)| *settings);
+        if current == wanted {
+            return;
+        }
+
+        if let Some((_, task)) = running.take() {
+            task.abort();
+            info!("Status server stopped");
+        }
+        let Some(settings) = wanted else {
+            return;
+        };
+
+        let source = Arc::clone(&self.source);
+        let task = tokio::spawn(async move {
+            let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, settings.port)).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    error!("Status server cannot listen on port {}: {e}", settings.port);
+                    return;
+                }
+            };
+            info!(
+                "Status server listening at http://127.0.0.1:{}",
+                settings.port
+            );
+            if let Err(e) = serve(listener, source).await {
+                error!("Status server stopped: {e}");
+            }
+        });
+        *running = Some((settings, task));
+    }
+}
+
+/// Answer requests on `listener` until the task is dropped
+pub async fn serve<S: StatusSource>(listener: TcpListener, source: Arc<S>) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let source = Arc::clone(&source);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, source.as_ref()).await {
+                debug!("Status connection closed with error: {e}");
+            }
+        });
+    }
+}
+
+/// Answer one request, then close the connection
+async fn handle_connection<S: StatusSource>(mut stream: TcpStream, source: &S) -> Result<()> {
+    let Ok(head) = tokio::time::timeout(READ_TIMEOUT, read_head(&mut stream)).await else {
+        return Ok(());
+    };
+    let response = respond(&head?, source);
+    stream.write_all(&response).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Request line and headers, up to the blank line that ends them
+async fn read_head(stream: &mut TcpStream) -> Result<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD {
+        let n = stream.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..n]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn respond<S: StatusSource>(head: &str, source: &S) -> Vec<u8> {
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return response("400 Bad Request", TEXT, "Bad request\n", true);
+    };
+
+    let host = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("host"))
+        .map(|(_, value)| value.trim());
+    if host.is_some_and(|host| !is_loopback_host(host)) {
+        return response("403 Forbidden", TEXT, "Forbidden\n", true);
+    }
+
+    let with_body = match method {
+        "GET" => true,
+        "HEAD" => false,
+        _ => return response("405 Method Not Allowed", TEXT, "Method not allowed\n", true),
+    };
+
+    let path = target.split('?').next().unwrap_or(target);
+    match path {
+        "/healthz" => response("200 OK", TEXT, "ok\n", with_body),
+        "/status" => match serde_json::to_string_pretty(&source.report()) {
+            Ok(json) => response("200 OK", JSON, &json, with_body),
+            Err(e) => response("500 Internal Server Error", TEXT, &e.to_string(), with_body),
+        },
+        "/metrics" => {
+            let metrics = metrics(&source.report(), source.token_expires_at(), Utc::now());
+            response("200 OK", PROMETHEUS, &metrics, with_body)
+        }
+        _ => response("404 Not Found", TEXT, "Not found\n", with_body),
+    }
+}
+
+/// `Host` header values that name this machine, with or without a port
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(name, "127.0.0.1" | "localhost" | "::1")
+}
+
+fn response(status: &str, content_type: &str, body: &str, with_body: bool) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    if with_body {
+        response.extend_from_slice(body.as_bytes());
+    }
+    response
+}
+
+/// The report in Prometheus text format; per-folder series carry a
+/// `folder` label
+fn metrics(
+    report: &StatusReport,
+    token_expires_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> String {
+    let mut out = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, i64)>| {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+        for (labels, value) in samples {
+            let _ = writeln!(out, "{name}{labels} {value}");
+        }
+    };
+    let per_folder = |value: &dyn Fn(&crate::sync::report::FolderReport) -> Option<i64>| {
+        report
+            .folders
+            .iter()
+            .filter_map(|f| {
+                let folder = escape_label(&f.path.display().to_string());
+                Some((format!("{{folder=\"{folder}\"}}"), value(f)?))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    family(
+        "cook_sync_info",
+        "gauge",
+        "Version of the running agent.",
+        vec![(
+            format!("{{version=\"{}\"}}", escape_label(&report.cli_version)),
+            1,
+        )],
+    );
+    family(
+        "cook_sync_authenticated",
+        "gauge",
+        "Whether the agent is signed in.",
+        vec![(String::new(), report.authenticated.into())],
+    );
+    family(
+        "cook_sync_token_expiry_seconds",
+        "gauge",
+        "Seconds until the session token expires; negative once expired.",
+        token_expires_at
+            .map(|at| (String::new(), (at - now).num_seconds()))
+            .into_iter()
+            .collect(),
+    );
+    family(
+        "cook_sync_runs_total",
+        "counter",
+        "Sync passes run since the agent started.",
+        per_folder(&|f| i64::try_from(f.passes.runs).ok()),
+    );
+    family(
+        "cook_sync_failures_total",
+        "counter",
+        "Sync passes that failed since the agent started.",
+        per_folder(&|f| i64::try_from(f.passes.failures).ok()),
+    );
+    family(
+        "cook_sync_retries_total",
+        "counter",
+        "Failed sync passes tried again since the agent started.",
+        per_folder(&|f| i64::try_from(f.passes.retries).ok()),
+    );
+    family(
+        "cook_sync_last_success_age_seconds",
+        "gauge",
+        "Seconds since the last successful sync pass.",
+        per_folder(&|f| f.last_sync_age_secs),
+    );
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::status::{FolderState, PassCounts, SyncState, SyncStatus};
+    use std::path::PathBuf;
+
+    struct FakeSource {
+        folders: Vec<FolderState>,
+    }
+
+    impl StatusSource for FakeSource {
+        fn report(&self) -> StatusReport {
+            let now = Utc::now();
+            StatusReport::not_running(true, Some("cook@example.com".to_string()))
+                .with_state(&SyncState::combine(&self.folders), now)
+                .with_folders(&self.folders, now)
+        }
+
+        fn token_expires_at(&self) -> Option<DateTime<Utc>> {
+            Some(Utc::now() + chrono::Duration::hours(2))
+        }
+    }
+
+    async fn start() -> u16 {
+        let source = FakeSource {
+            folders: vec![FolderState {
+                path: PathBuf::from("/home/me/\"Recipes\""),
+                remote_folder: String::new(),
+                state: SyncState {
+                    status: SyncStatus::Idle,
+                    last_sync: Some(Utc::now() - chrono::Duration::seconds(30)),
+                    ..Default::default()
+                },
+                passes: PassCounts {
+                    runs: 7,
+                    failures: 2,
+                    retries: 1,
+                },
+            }],
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener, Arc::new(source)));
+        port
+    }
+
+    /// Send `request` and return the status line and the body
+    async fn send(port: u16, request: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.lines().next().unwrap().to_string();
+        (status, body.to_string())
+    }
+
+    fn get(path: &str) -> String {
+        format!("GET {path} HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n")
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        let port = start().await;
+
+        assert_eq!(
+            send(port, &get("/healthz")).await,
+            ("HTTP/1.1 200 OK".to_string(), "ok\n".to_string())
+        );
+        assert_eq!(
+            send(port, &get("/favicon.ico")).await.0,
+            "HTTP/1.1 404 Not Found"
+        );
+        assert_eq!(
+            send(port, "POST /healthz HTTP/1.1\r\n\r\n").await.0,
+            "HTTP/1.1 405 Method Not Allowed"
+        );
+        // A page on another site reaching us through DNS rebinding
+        assert_eq!(
+            send(
+                port,
+                "GET /status HTTP/1.1\r\nHost: evil.example:9581\r\n\r\n"
+            )
+            .await
+            .0,
+            "HTTP/1.1 403 Forbidden"
+        );
+        assert_eq!(
+            send(
+                port,
+                "HEAD /healthz HTTP/1.1\r\nHost: localhost:9581\r\n\r\n"
+            )
+            .await,
+            ("HTTP/1.1 200 OK".to_string(), String::new())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_is_the_status_report() {
+        let port = start().await;
+
+        let (status, body) = send(port, &get("/status?pretty")).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["user"], "cook@example.com");
+        assert_eq!(json["status"], "idle");
+        assert_eq!(json["cli_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["folders"][0]["passes"]["runs"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let port = start().await;
+
+        let (status, body) = send(port, &get("/metrics")).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let folder = r#"{folder="/home/me/\"Recipes\""}"#;
+        for line in [
+            "# TYPE cook_sync_runs_total counter".to_string(),
+            format!("cook_sync_runs_total{folder} 7"),
+            format!("cook_sync_failures_total{folder} 2"),
+            format!("cook_sync_retries_total{folder} 1"),
+            format!("cook_sync_last_success_age_seconds{folder} 30"),
+            "cook_sync_authenticated 1".to_string(),
+        ] {
+            assert!(body.lines().any(|l| l == line), "{line} missing in\n{body}");
+        }
+        let expiry = body
+            .lines()
+            .find_map(|l| l.strip_prefix("cook_sync_token_expiry_seconds "))
+            .unwrap();
+        assert!((7100..=7200).contains(&expiry.parse::<i64>().unwrap()));
+    }
+}
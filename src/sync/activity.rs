@@ -0,0 +1,404 @@
+//! History of sync runs, kept in its own SQLite file.
+//!
+//! Every pass of every folder is recorded with what it transferred or why it
+//! failed, so `cook-sync history` and the tray can answer "did my change go
+//! through?" after the fact. Old runs are pruned as new ones come in.
+
+use crate::config::settings::SyncDirection;
+use crate::error::Result;
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, TimeZone, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Runs older than this are dropped
+const MAX_AGE_DAYS: i64 = 90;
+
+/// Upper bound on stored runs, for agents that sync very often
+const MAX_RUNS: i64 = 20_000;
+
+/// Entries in the tray's "Recent activity" menu
+const MENU_LINES: usize = 10;
+
+/// What happened to a file in a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    Uploaded,
+    Downloaded,
+    /// Removed locally because it was deleted on the server
+    Removed,
+    /// Local version kept as a conflict copy
+    Conflict,
+}
+
+impl FileAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            FileAction::Uploaded => "uploaded",
+            FileAction::Downloaded => "downloaded",
+            FileAction::Removed => "removed",
+            FileAction::Conflict => "conflict",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "uploaded" => Some(FileAction::Uploaded),
+            "downloaded" => Some(FileAction::Downloaded),
+            "removed" => Some(FileAction::Removed),
+            "conflict" => Some(FileAction::Conflict),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // pad() so callers can align the column
+        f.pad(match self {
+            FileAction::Uploaded => "Uploaded",
+            FileAction::Downloaded => "Downloaded",
+            FileAction::Removed => "Removed",
+            FileAction::Conflict => "Conflict",
+        })
+    }
+}
+
+/// A file touched by a run, relative to the run's folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileActivity {
+    pub path: String,
+    pub action: FileAction,
+}
+
+/// One sync pass of one folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyncRun {
+    pub folder: PathBuf,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub direction: SyncDirection,
+    pub files: Vec<FileActivity>,
+    /// Why the run failed; `None` for successful runs
+    pub error: Option<String>,
+}
+
+impl SyncRun {
+    /// Whether the run moved files or failed, as opposed to finding nothing to do
+    pub fn is_notable(&self) -> bool {
+        !self.files.is_empty() || self.error.is_some()
+    }
+
+    pub fn count(&self, action: FileAction) -> usize {
+        self.files.iter().filter(|f| f.action == action).count()
+    }
+}
+
+pub struct ActivityJournal {
+    conn: Connection,
+}
+
+impl ActivityJournal {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        // The daemon writes while the CLI or tray reads
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                folder TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                finished_at INTEGER NOT NULL,
+                direction TEXT NOT NULL,
+                error TEXT
+            );
+            CREATE INDEX IF NOT EXISTS runs_started_at ON runs (started_at);
+            CREATE TABLE IF NOT EXISTS run_files (
+                run_id INTEGER NOT NULL REFERENCES runs (id) ON DELETE CASCADE,
+                path TEXT NOT NULL,
+                action TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS run_files_run_id ON run_files (run_id);
+            PRAGMA foreign_keys = ON;",
+        )?;
+
+        Ok(Self { conn })
+    }
+
+    /// Store a run and prune old ones
+    pub fn record(&mut self, run: &SyncRun) -> Result<()> {
+        let tx = self.conn.transaction()?;
+
+        tx.execute(
+            "INSERT INTO runs (folder, started_at, finished_at, direction, error)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                run.folder.to_string_lossy(),
+                run.started_at.timestamp_millis(),
+                run.finished_at.timestamp_millis(),
+                run.direction.as_str(),
+                run.error,
+            ],
+        )?;
+        let run_id = tx.last_insert_rowid();
+
+        {
+            let mut insert =
+                tx.prepare("INSERT INTO run_files (run_id, path, action) VALUES (?1, ?2, ?3)")?;
+            for file in &run.files {
+                insert.execute(params![run_id, file.path, file.action.as_str()])?;
+            }
+        }
+
+        let cutoff = Utc::now() - ChronoDuration::days(MAX_AGE_DAYS);
+        tx.execute(
+            "DELETE FROM runs WHERE started_at < ?1 OR id <= ?2 - ?3",
+            params![cutoff.timestamp_millis(), run_id, MAX_RUNS],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Runs started at or after `since` (all runs if `None`), newest first
+    pub fn runs(&self, since: Option<DateTime<Utc>>) -> Result<Vec<SyncRun>> {
+        let since = since.map_or(i64::MIN, |t| t.timestamp_millis());
+        self.load(
+            "SELECT id, folder, started_at, finished_at, direction, error FROM runs
+             WHERE started_at >= ?1 ORDER BY started_at DESC, id DESC",
+            params![since],
+        )
+    }
+
+    /// The latest `limit` runs that moved files or failed, newest first
+    pub fn recent(&self, limit: usize) -> Result<Vec<SyncRun>> {
+        // Quiet runs vastly outnumber the others, so skip them in the query
+        self.load(
+            "SELECT id, folder, started_at, finished_at, direction, error FROM runs
+             WHERE error IS NOT NULL OR EXISTS (SELECT 1 FROM run_files WHERE run_id = runs.id)
+             ORDER BY started_at DESC, id DESC LIMIT ?1",
+            params![limit as i64],
+        )
+    }
+
+    fn load(&self, query: &str, params: impl rusqlite::Params) -> Result<Vec<SyncRun>> {
+        let mut stmt = self.conn.prepare(query)?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    SyncRun {
+                        folder: PathBuf::from(row.get::<_, String>(1)?),
+                        started_at: from_millis(row.get(2)?),
+                        finished_at: from_millis(row.get(3)?),
+                        direction: row.get::<_, String>(4)?.parse().unwrap_or_default(),
+                        files: Vec::new(),
+                        error: row.get(5)?,
+                    },
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let Some(oldest) = rows.iter().map(|(_, run)| run.started_at).min() else {
+            return Ok(Vec::new());
+        };
+        let mut files = self.files_since(oldest.timestamp_millis())?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, mut run)| {
+                run.files = files.remove(&id).unwrap_or_default();
+                run
+            })
+            .collect())
+    }
+
+    /// Files of the runs started at or after `since`, by run id
+    fn files_since(&self, since: i64) -> Result<HashMap<i64, Vec<FileActivity>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_files.run_id, run_files.path, run_files.action
+             FROM run_files JOIN runs ON runs.id = run_files.run_id
+             WHERE runs.started_at >= ?1 ORDER BY run_files.rowid",
+        )?;
+        let rows = stmt.query_map(params![since], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut files: HashMap<i64, Vec<FileActivity>> = HashMap::new();
+        for row in rows {
+            let (run_id, path, action) = row?;
+            if let Some(action) = FileAction::parse(&action) {
+                files
+                    .entry(run_id)
+                    .or_default()
+                    .push(FileActivity { path, action });
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Lines for the tray's "Recent activity" menu from the journal at `path`
+pub fn recent_menu_lines(path: &Path) -> Vec<String> {
+    match ActivityJournal::open(path).and_then(|journal| journal.recent(MENU_LINES)) {
+        Ok(runs) => menu_lines(&runs, MENU_LINES),
+        Err(e) => {
+            log::debug!("Could not read sync activity: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Short lines for the tray's "Recent activity" menu, newest first: one per
+/// file, or one per failed run
+fn menu_lines(runs: &[SyncRun], limit: usize) -> Vec<String> {
+    let today = chrono::Local::now().date_naive();
+
+    runs.iter()
+        .flat_map(|run| {
+            let at = run.finished_at.with_timezone(&chrono::Local);
+            let when = if at.date_naive() == today {
+                at.format("%H:%M").to_string()
+            } else {
+                at.format("%b %d").to_string()
+            };
+
+            let lines: Vec<String> = match &run.error {
+                Some(error) => vec![format!("Sync failed: {error} ({when})")],
+                None => run
+                    .files
+                    .iter()
+                    .map(|file| format!("{} {} ({when})", file.action, file.path))
+                    .collect(),
+            };
+            lines
+        })
+        .take(limit)
+        .collect()
+}
+
+fn from_millis(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .unwrap_or_default()
+}
+
+/// Parse the `--since` argument: an age such as `30m`, `12h`, `7d` or `2w`,
+/// a date (`2024-01-31`) or an RFC 3339 timestamp
+pub fn parse_since(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+    let value = value.trim();
+
+    if let Some(unit) = value.chars().last().filter(char::is_ascii_alphabetic) {
+        if let Ok(amount) = value[..value.len() - 1].parse::<i64>() {
+            let age = match unit {
+                'm' => ChronoDuration::try_minutes(amount),
+                'h' => ChronoDuration::try_hours(amount),
+                'd' => ChronoDuration::try_days(amount),
+                'w' => ChronoDuration::try_weeks(amount),
+                _ => None,
+            };
+            if let Some(age) = age {
+                return Ok(Utc::now() - age);
+            }
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        if let Some(start) = date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+        {
+            return Ok(start.with_timezone(&Utc));
+        }
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| {
+            format!(
+                "'{value}' is not an age (30m, 12h, 7d, 2w), a date (2024-01-31) or a timestamp"
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn run(minutes_ago: i64, files: &[(&str, FileAction)], error: Option<&str>) -> SyncRun {
+        let started_at =
+            from_millis((Utc::now() - ChronoDuration::minutes(minutes_ago)).timestamp_millis());
+        SyncRun {
+            folder: PathBuf::from("/recipes"),
+            started_at,
+            finished_at: started_at + ChronoDuration::seconds(2),
+            direction: SyncDirection::Both,
+            files: files
+                .iter()
+                .map(|(path, action)| FileActivity {
+                    path: path.to_string(),
+                    action: *action,
+                })
+                .collect(),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_record_and_query_runs() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut journal = ActivityJournal::open(&temp_dir.path().join("activity.db")).unwrap();
+
+        let old = run(120, &[("Soup.cook", FileAction::Uploaded)], None);
+        let quiet = run(60, &[], None);
+        let failed = run(30, &[], Some("Connection error"));
+        let busy = run(
+            10,
+            &[
+                ("Pasta.cook", FileAction::Downloaded),
+                ("Old.cook", FileAction::Removed),
+            ],
+            None,
+        );
+        for r in [&old, &quiet, &failed, &busy] {
+            journal.record(r).unwrap();
+        }
+
+        assert_eq!(
+            journal.runs(None).unwrap(),
+            vec![busy.clone(), failed.clone(), quiet, old.clone()]
+        );
+        assert_eq!(
+            journal
+                .runs(Some(Utc::now() - ChronoDuration::minutes(45)))
+                .unwrap(),
+            vec![busy.clone(), failed.clone()]
+        );
+        assert_eq!(journal.recent(2).unwrap(), vec![busy.clone(), failed]);
+        assert_eq!(busy.count(FileAction::Downloaded), 1);
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = Utc::now();
+        let age = now - parse_since("2h").unwrap();
+        assert!((age - ChronoDuration::hours(2)).num_seconds().abs() < 5);
+
+        assert_eq!(
+            parse_since("2024-01-31T10:00:00Z").unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 31, 10, 0, 0).unwrap()
+        );
+        assert!(parse_since("2024-01-31").is_ok());
+        assert!(parse_since("yesterday").is_err());
+        assert!(parse_since("5y").is_err());
+    }
+}
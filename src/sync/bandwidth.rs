@@ -0,0 +1,215 @@
+//! Transfer rate limits.
+//!
+//! Uploads and downloads of all folders draw from one token bucket per
+//! direction, so a limit holds for the agent as a whole. Chunk batches are
+//! sent whole; the wait before a batch is what keeps the average rate under the
+//! limit. On a metered connection with the `throttle` policy the lower of the
+//! configured and the metered limit applies.
+
+use crate::config::settings::{BandwidthSettings, MeteredPolicy};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token bucket for one direction
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Bytes per second; `None` for unlimited
+    rate: Option<u64>,
+    /// Bytes that may be sent right away; negative while paying off a batch
+    available: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Take `bytes` from the bucket and return how long to wait before sending
+    fn reserve(&mut self, bytes: usize, now: Instant) -> Duration {
+        let Some(rate) = self.rate else {
+            return Duration::ZERO;
+        };
+        let rate = rate as f64;
+
+        // At most one second of unused allowance carries over
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * rate).min(rate);
+        self.updated = now;
+        self.available -= bytes as f64;
+
+        if self.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available / rate)
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(rate: Option<u64>) -> Self {
+        let rate = at_least_one(rate);
+        Self {
+            bucket: Mutex::new(Bucket {
+                rate,
+                available: rate.unwrap_or(0) as f64,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn set_rate(&self, rate: Option<u64>) {
+        let rate = at_least_one(rate);
+        let mut bucket = self.bucket.lock().unwrap();
+        if bucket.rate != rate {
+            bucket.rate = rate;
+            bucket.available = bucket.available.min(rate.unwrap_or(0) as f64);
+        }
+    }
+
+    /// Wait until `bytes` may be transferred
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = self.bucket.lock().unwrap().reserve(bytes, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// A rate of zero would never let anything through, and cannot be waited for
+fn at_least_one(rate: Option<u64>) -> Option<u64> {
+    rate.map(|rate| rate.max(1))
+}
+
+/// Limits shared by all sync passes
+pub struct Bandwidth {
+    upload: RateLimiter,
+    download: RateLimiter,
+    metered: AtomicBool,
+}
+
+impl Default for Bandwidth {
+    fn default() -> Self {
+        Self {
+            upload: RateLimiter::new(None),
+            download: RateLimiter::new(None),
+            metered: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Bandwidth {
+    /// Apply the limits from the settings, taking the connection into account
+    pub fn configure(&self, settings: &BandwidthSettings) {
+        let metered_limit = (self.is_metered() && settings.on_metered == MeteredPolicy::Throttle)
+            .then_some(settings.metered_kb_per_sec);
+        let limit = |configured: Option<u64>| {
+            let kb = match (configured, metered_limit) {
+                (Some(configured), Some(metered)) => Some(configured.min(metered)),
+                (configured, metered) => configured.or(metered),
+            };
+            // Settings edited by hand may hold a zero limit; use the lowest instead
+            kb.map(|kb| kb.max(1) * 1024)
+        };
+
+        self.upload.set_rate(limit(settings.max_upload_kb_per_sec));
+        self.download
+            .set_rate(limit(settings.max_download_kb_per_sec));
+    }
+
+    pub fn is_metered(&self) -> bool {
+        self.metered.load(Ordering::SeqCst)
+    }
+
+    /// Record whether the connection is metered; returns whether that changed
+    pub fn set_metered(&self, metered: bool) -> bool {
+        self.metered.swap(metered, Ordering::SeqCst) != metered
+    }
+
+    pub async fn upload(&self, bytes: usize) {
+        self.upload.acquire(bytes).await;
+    }
+
+    pub async fn download(&self, bytes: usize) {
+        self.download.acquire(bytes).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(limiter: &RateLimiter) -> Option<u64> {
+        limiter.bucket.lock().unwrap().rate
+    }
+
+    #[test]
+    fn test_bucket_spreads_batches_over_time() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            rate: Some(1000),
+            available: 1000.0,
+            updated: start,
+        };
+
+        // One second's worth goes at once, the rest has to wait
+        assert_eq!(bucket.reserve(1000, start), Duration::ZERO);
+        assert_eq!(bucket.reserve(500, start), Duration::from_millis(500));
+        // Half a second later the debt is paid off
+        assert_eq!(
+            bucket.reserve(1000, start + Duration::from_millis(500)),
+            Duration::from_secs(1)
+        );
+
+        bucket.rate = None;
+        assert_eq!(bucket.reserve(1_000_000, start), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_metered_throttle_lowers_limits() {
+        let bandwidth = Bandwidth::default();
+        let settings = BandwidthSettings {
+            max_upload_kb_per_sec: Some(512),
+            on_metered: MeteredPolicy::Throttle,
+            metered_kb_per_sec: 64,
+            ..Default::default()
+        };
+
+        bandwidth.configure(&settings);
+        assert_eq!(rate(&bandwidth.upload), Some(512 * 1024));
+        assert_eq!(rate(&bandwidth.download), None);
+
+        assert!(bandwidth.set_metered(true));
+        assert!(!bandwidth.set_metered(true));
+        bandwidth.configure(&settings);
+        assert_eq!(rate(&bandwidth.upload), Some(64 * 1024));
+        assert_eq!(rate(&bandwidth.download), Some(64 * 1024));
+
+        // Pausing is handled by the manager; the limits stay as configured
+        bandwidth.configure(&BandwidthSettings {
+            on_metered: MeteredPolicy::Pause,
+            ..settings
+        });
+        assert_eq!(rate(&bandwidth.upload), Some(512 * 1024));
+    }
+
+    #[test]
+    fn test_zero_rate_is_lowest_limit() {
+        let limiter = RateLimiter::new(Some(0));
+        assert_eq!(rate(&limiter), Some(1));
+        limiter.set_rate(Some(0));
+        let wait = limiter.bucket.lock().unwrap().reserve(3, Instant::now());
+        assert!(wait >= Duration::from_secs(1) && wait <= Duration::from_secs(3));
+
+        let bandwidth = Bandwidth::default();
+        bandwidth.set_metered(true);
+        bandwidth.configure(&BandwidthSettings {
+            max_upload_kb_per_sec: Some(0),
+            on_metered: MeteredPolicy::Throttle,
+            metered_kb_per_sec: 0,
+            ..Default::default()
+        });
+        assert_eq!(rate(&bandwidth.upload), Some(1024));
+        assert_eq!(rate(&bandwidth.download), Some(1024));
+    }
+}
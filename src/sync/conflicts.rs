@@ -0,0 +1,304 @@
+//! Recipes edited on two machines before either change was synced.
+//!
+//! The server keeps whichever version is committed last, so before uploading
+//! a local change the engine checks whether the same file also changed on the
+//! server. If it did, the server version wins and the local version is kept
+//! next to it as `Soup.conflict-<host>-<timestamp>.cook`. Those copies sync
+//! like any other file, and the list of conflicts is simply the copies found
+//! in a folder, so every machine sees the same conflicts until they are
+//! resolved.
+
+use super::ignore_rules::IgnoreRules;
+use crate::error::{Result, SyncError};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Separates the original name from the host and time in a conflict copy
+const MARKER: &str = ".conflict-";
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// A local version that lost against a concurrent change on the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conflict {
+    /// The synced folder containing both files
+    pub folder: PathBuf,
+    /// The recipe, relative to the folder; it holds the server version
+    pub path: PathBuf,
+    /// The preserved local version, relative to the folder
+    pub copy: PathBuf,
+    /// Machine the preserved version was edited on
+    pub host: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Conflict {
+    pub fn path_abs(&self) -> PathBuf {
+        self.folder.join(&self.path)
+    }
+
+    pub fn copy_abs(&self) -> PathBuf {
+        self.folder.join(&self.copy)
+    }
+}
+
+/// Which version of a conflicted recipe to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// The version now in the recipe file, which came from the server
+    Current,
+    /// The preserved conflict copy
+    Copy,
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "current" => Ok(Resolution::Current),
+            "copy" => Ok(Resolution::Copy),
+            _ => Err(format!("'{s}' is not one of: current, copy")),
+        }
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resolution::Current => write!(f, "current"),
+            Resolution::Copy => write!(f, "copy"),
+        }
+    }
+}
+
+/// This machine's name as used in conflict copies
+pub fn local_host() -> String {
+    sys_info::hostname()
+        .map(|name| sanitize_host(&name))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Keep the first label of the host name and only characters that are safe
+/// in file names on every platform
+fn sanitize_host(name: &str) -> String {
+    let label = name.split('.').next().unwrap_or_default();
+    let host: String = label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    if host.is_empty() {
+        "unknown".to_string()
+    } else {
+        host
+    }
+}
+
+/// Name of the conflict copy for `path`: `Soup.cook` becomes
+/// `Soup.conflict-laptop-20240102T030405Z.cook`
+pub fn copy_name(path: &Path, host: &str, at: DateTime<Utc>) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let suffix = format!("{MARKER}{host}-{}", at.format(TIMESTAMP_FORMAT));
+
+    let name = match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem}{suffix}.{ext}"),
+        _ => format!("{file_name}{suffix}"),
+    };
+
+    path.with_file_name(name)
+}
+
+/// The conflict a copy at `copy` (relative to `folder`) stands for, if the
+/// name is one of ours
+pub fn parse(folder: &Path, copy: &Path) -> Option<Conflict> {
+    let file_name = copy.file_name()?.to_str()?;
+    let (stem, rest) = file_name.rsplit_once(MARKER)?;
+    let (details, ext) = match rest.split_once('.') {
+        Some((details, ext)) => (details, Some(ext)),
+        None => (rest, None),
+    };
+    let (host, timestamp) = details.rsplit_once('-')?;
+    let created_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()?
+        .and_utc();
+
+    if stem.is_empty() || host.is_empty() {
+        return None;
+    }
+
+    let original = match ext {
+        Some(ext) => format!("{stem}.{ext}"),
+        None => stem.to_string(),
+    };
+
+    Some(Conflict {
+        folder: folder.to_path_buf(),
+        path: copy.with_file_name(original),
+        copy: copy.to_path_buf(),
+        host: host.to_string(),
+        created_at,
+    })
+}
+
+/// Unresolved conflicts in `folder`, oldest first. Directories excluded by
+/// `rules` are not walked; nothing in them is synced.
+pub fn find(folder: &Path, rules: &IgnoreRules) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    collect(folder, folder, rules, &mut conflicts);
+    sort(&mut conflicts);
+    conflicts
+}
+
+/// Order conflicts oldest first and drop repeated copies
+pub fn sort(conflicts: &mut Vec<Conflict>) {
+    conflicts.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.copy.cmp(&b.copy)));
+    conflicts.dedup_by(|a, b| a.copy == b.copy);
+}
+
+fn collect(folder: &Path, dir: &Path, rules: &IgnoreRules, conflicts: &mut Vec<Conflict>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_symlink() {
+            continue;
+        }
+        if path.is_dir() {
+            let ignored = path
+                .strip_prefix(folder)
+                .is_ok_and(|relative| rules.is_ignored(relative, true));
+            if !ignored {
+                collect(folder, &path, rules, conflicts);
+            }
+        } else if let Some(conflict) = path
+            .strip_prefix(folder)
+            .ok()
+            .and_then(|relative| parse(folder, relative))
+        {
+            conflicts.push(conflict);
+        }
+    }
+}
+
+/// Copy the local version of `path` (relative to `folder`) aside before the
+/// server version replaces it
+pub fn preserve(folder: &Path, path: &Path, host: &str, at: DateTime<Utc>) -> Result<Conflict> {
+    let copy = copy_name(path, host, at);
+    std::fs::copy(folder.join(path), folder.join(&copy))?;
+
+    Ok(Conflict {
+        folder: folder.to_path_buf(),
+        path: path.to_path_buf(),
+        copy,
+        host: host.to_string(),
+        created_at: at,
+    })
+}
+
+/// Unified diff from the current recipe to the preserved copy
+pub fn diff(conflict: &Conflict) -> Result<String> {
+    // The recipe may have been deleted on the server since
+    let current = std::fs::read_to_string(conflict.path_abs()).unwrap_or_default();
+    let copy = std::fs::read_to_string(conflict.copy_abs())?;
+
+    Ok(TextDiff::from_lines(&current, &copy)
+        .unified_diff()
+        .header(
+            &conflict.path.to_string_lossy(),
+            &conflict.copy.to_string_lossy(),
+        )
+        .to_string())
+}
+
+/// Settle a conflict. Keeping the copy moves it over the recipe, which then
+/// syncs as a normal edit; keeping the current version removes the copy.
+pub fn resolve(conflict: &Conflict, keep: Resolution) -> Result<()> {
+    let copy = conflict.copy_abs();
+    if !copy.is_file() {
+        return Err(SyncError::Other(format!(
+            "Conflict copy {} no longer exists",
+            copy.display()
+        )));
+    }
+
+    match keep {
+        Resolution::Current => std::fs::remove_file(copy)?,
+        Resolution::Copy => std::fs::rename(copy, conflict.path_abs())?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+    }
+
+    #[test]
+    fn test_copy_name_round_trips() {
+        let copy = copy_name(Path::new("Soups/Tomato.soup.cook"), "laptop-2", at());
+        assert_eq!(
+            copy,
+            Path::new("Soups/Tomato.soup.conflict-laptop-2-20240102T030405Z.cook")
+        );
+
+        let conflict = parse(Path::new("/recipes"), &copy).unwrap();
+        assert_eq!(conflict.path, Path::new("Soups/Tomato.soup.cook"));
+        assert_eq!(conflict.host, "laptop-2");
+        assert_eq!(conflict.created_at, at());
+
+        let copy = copy_name(Path::new("NOTES"), "laptop", at());
+        assert_eq!(copy, Path::new("NOTES.conflict-laptop-20240102T030405Z"));
+        assert_eq!(
+            parse(Path::new("/recipes"), &copy).unwrap().path,
+            Path::new("NOTES")
+        );
+
+        assert!(parse(Path::new("/recipes"), Path::new("Soup.cook")).is_none());
+        assert!(parse(Path::new("/recipes"), Path::new("Soup.conflict-x.cook")).is_none());
+    }
+
+    #[test]
+    fn test_sanitize_host() {
+        assert_eq!(sanitize_host("Jos MacBook.local"), "Jos-MacBook");
+        assert_eq!(sanitize_host(""), "unknown");
+    }
+
+    #[test]
+    fn test_preserve_find_and_resolve() {
+        let temp_dir = TempDir::new().unwrap();
+        let folder = temp_dir.path();
+        std::fs::create_dir(folder.join("Soups")).unwrap();
+        std::fs::write(folder.join("Soups/Tomato.cook"), "Add @salt.\n").unwrap();
+
+        let conflict = preserve(folder, Path::new("Soups/Tomato.cook"), "laptop", at()).unwrap();
+        std::fs::write(folder.join("Soups/Tomato.cook"), "Add @pepper.\n").unwrap();
+        assert_eq!(find(folder, &IgnoreRules::none()), vec![conflict.clone()]);
+        let drafts = IgnoreRules::load(folder, &["Soups/".to_string()]);
+        assert!(find(folder, &drafts).is_empty());
+
+        let diff = diff(&conflict).unwrap();
+        assert!(diff.contains("-Add @pepper."));
+        assert!(diff.contains("+Add @salt."));
+
+        resolve(&conflict, Resolution::Copy).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(folder.join("Soups/Tomato.cook")).unwrap(),
+            "Add @salt.\n"
+        );
+        assert!(find(folder, &IgnoreRules::none()).is_empty());
+        assert!(resolve(&conflict, Resolution::Current).is_err());
+    }
+}
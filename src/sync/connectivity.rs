@@ -0,0 +1,216 @@
+//! Telling "no network" apart from "the server has a problem".
+//!
+//! Failed passes are classified by their cause. Network failures (DNS,
+//! refused connections, TLS, timeouts) put the folders into the Offline state
+//! instead of burning through retries; a single probe task then checks the
+//! sync endpoint until it answers and wakes every waiting folder at once.
+//! Server errors (HTTP 5xx) are retried with backoff, client errors (other
+//! HTTP 4xx) are not retried at all.
+
+use crate::error::SyncError;
+use log::{debug, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// How often the endpoint is probed while offline
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why a request failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The server could not be reached at all
+    Offline,
+    /// The server answered with a 5xx status; worth retrying
+    Server,
+    /// The server rejected the request; retrying will not help
+    Client,
+}
+
+impl FailureKind {
+    pub fn from_status(status: u16) -> Self {
+        if status >= 500 {
+            FailureKind::Server
+        } else {
+            FailureKind::Client
+        }
+    }
+
+    pub fn from_reqwest(e: &reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => Self::from_status(status.as_u16()),
+            // Connect errors cover DNS, refused connections and TLS handshakes;
+            // request and body errors are connections dropped halfway
+            None if e.is_connect() || e.is_timeout() || e.is_request() || e.is_body() => {
+                FailureKind::Offline
+            }
+            None => FailureKind::Server,
+        }
+    }
+
+    /// The SyncError for a failure of this kind
+    pub fn into_error(self, message: String) -> SyncError {
+        match self {
+            FailureKind::Offline => SyncError::Offline(message),
+            FailureKind::Server => SyncError::Server(message),
+            FailureKind::Client => SyncError::Rejected(message),
+        }
+    }
+
+    /// Kind of a failed pass, or `None` for errors that have nothing to do
+    /// with the network
+    pub fn of(e: &SyncError) -> Option<Self> {
+        match e {
+            SyncError::Offline(_) => Some(FailureKind::Offline),
+            SyncError::Server(_) => Some(FailureKind::Server),
+            SyncError::Rejected(_) => Some(FailureKind::Client),
+            SyncError::Network(e) => Some(Self::from_reqwest(e)),
+            _ => None,
+        }
+    }
+}
+
+/// HTTP status at the end of the sync client's "... failed with status: 503
+/// Service Unavailable" messages
+pub fn status_in_message(message: &str) -> Option<u16> {
+    let (_, rest) = message.rsplit_once("status: ")?;
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// Shared view of whether the sync endpoint is reachable
+pub struct ConnectivityMonitor {
+    endpoint: String,
+    client: reqwest::Client,
+    online: watch::Sender<bool>,
+    probing: AtomicBool,
+}
+
+impl ConnectivityMonitor {
+    pub fn new(endpoint: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(PROBE_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            endpoint: endpoint.to_string(),
+            client,
+            online: watch::Sender::new(true),
+            probing: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_online(&self) -> bool {
+        *self.online.borrow()
+    }
+
+    /// A pass got through, so the endpoint is reachable
+    pub fn report_online(&self) {
+        self.online
+            .send_if_modified(|online| !std::mem::replace(online, true));
+    }
+
+    /// A pass failed because the endpoint could not be reached. Starts the
+    /// probe task unless it is already running.
+    pub fn report_offline(self: &Arc<Self>) {
+        self.online
+            .send_if_modified(|online| std::mem::replace(online, false));
+
+        if self.probing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        info!("Sync endpoint unreachable, waiting for the connection to come back");
+
+        let monitor = Arc::clone(self);
+        tokio::spawn(async move {
+            while !monitor.is_online() {
+                if monitor.probe().await {
+                    info!("Sync endpoint reachable again");
+                    monitor.report_online();
+                    break;
+                }
+                tokio::time::sleep(PROBE_INTERVAL).await;
+            }
+            monitor.probing.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Resolves once the endpoint is reachable
+    pub async fn wait_online(&self) {
+        let mut online = self.online.subscribe();
+        // The sender lives as long as self, so this only ends when online
+        let _ = online.wait_for(|online| *online).await;
+    }
+
+    /// Whether the endpoint answers at all; any HTTP status counts
+    pub async fn probe(&self) -> bool {
+        match self.client.head(&self.endpoint).send().await {
+            Ok(_) => true,
+            Err(e) => {
+                debug!("Connectivity probe failed: {e}");
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_failure_kind_from_status() {
+        assert_eq!(FailureKind::from_status(503), FailureKind::Server);
+        assert_eq!(FailureKind::from_status(500), FailureKind::Server);
+        assert_eq!(FailureKind::from_status(404), FailureKind::Client);
+        assert_eq!(
+            status_in_message("Upload chunk failed with status: 503 Service Unavailable"),
+            Some(503)
+        );
+        assert_eq!(status_in_message("Can't parse the response"), None);
+    }
+
+    #[tokio::test]
+    async fn test_refused_connection_is_offline() {
+        // Nothing listens on port 1
+        let e = reqwest::get("http://127.0.0.1:1").await.unwrap_err();
+        assert_eq!(FailureKind::from_reqwest(&e), FailureKind::Offline);
+        assert_eq!(
+            FailureKind::of(&SyncError::Network(e)),
+            Some(FailureKind::Offline)
+        );
+        assert_eq!(FailureKind::of(&SyncError::AuthenticationRequired), None);
+
+        let monitor = ConnectivityMonitor::new("http://127.0.0.1:1");
+        assert!(!monitor.probe().await);
+    }
+
+    #[tokio::test]
+    async fn test_monitor_comes_back_online() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf);
+                // Any answer counts, even an error status
+                let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            }
+        });
+
+        let monitor = Arc::new(ConnectivityMonitor::new(&endpoint));
+        monitor.report_offline();
+        assert!(!monitor.is_online());
+
+        tokio::time::timeout(Duration::from_secs(10), monitor.wait_online())
+            .await
+            .unwrap();
+        assert!(monitor.is_online());
+    }
+}
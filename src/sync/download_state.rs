@@ -0,0 +1,183 @@
+//! Download progress, kept in the sync client's database.
+//!
+//! The client library resumes downloads from the highest journal id in its
+//! registry. Our own uploads also receive journal ids, so in upload-only mode
+//! (or when an upload lands between two remote changes) that would skip remote
+//! changes for good. The agent tracks how far it has actually downloaded instead,
+//! and which journal entries it uploaded or applied itself.
+
+use crate::error::Result;
+use cooklang_sync_client::remote::ResponseFileRecord;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+pub struct DownloadState {
+    conn: Connection,
+    namespace_id: i32,
+}
+
+impl DownloadState {
+    /// Open the state table next to the registry in `db_path`
+    pub fn open(db_path: &Path, namespace_id: i32) -> Result<Self> {
+        let conn = Connection::open(db_path)?;
+        // The registry connection pool writes to the same file
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS agent_download_state (
+                namespace_id INTEGER PRIMARY KEY NOT NULL,
+                jid INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS agent_known_entries (
+                namespace_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                jid INTEGER NOT NULL,
+                PRIMARY KEY (namespace_id, path, jid)
+            )",
+            [],
+        )?;
+
+        Ok(Self { conn, namespace_id })
+    }
+
+    /// Journal id downloads continue from, or `fallback` if none was stored yet
+    pub fn last_jid(&self, fallback: i32) -> Result<i32> {
+        let jid = self
+            .conn
+            .query_row(
+                "SELECT jid FROM agent_download_state WHERE namespace_id = ?1",
+                params![self.namespace_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(jid.unwrap_or(fallback))
+    }
+
+    /// Downloads continue after `jid`; entries up to it are not listed again,
+    /// so they are forgotten
+    pub fn set_last_jid(&self, jid: i32) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO agent_download_state (namespace_id, jid) VALUES (?1, ?2)
+             ON CONFLICT(namespace_id) DO UPDATE SET jid = excluded.jid",
+            params![self.namespace_id, jid],
+        )?;
+        self.conn.execute(
+            "DELETE FROM agent_known_entries WHERE namespace_id = ?1 AND jid <= ?2",
+            params![self.namespace_id, jid],
+        )?;
+        Ok(())
+    }
+
+    /// Remember that journal entry `jid` for `path` matches the local file,
+    /// because it was uploaded or applied from here
+    pub fn add_entry(&self, path: &str, jid: i32) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO agent_known_entries (namespace_id, path, jid)
+             VALUES (?1, ?2, ?3)",
+            params![self.namespace_id, path, jid],
+        )?;
+        Ok(())
+    }
+
+    /// Whether journal entry `jid` for `path` was uploaded or applied from here
+    pub fn has_entry(&self, path: &str, jid: i32) -> Result<bool> {
+        let found = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM agent_known_entries
+                 WHERE namespace_id = ?1 AND path = ?2 AND jid = ?3",
+                params![self.namespace_id, path, jid],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+}
+
+/// Keep only the newest journal entry for each path, in journal order
+pub fn latest_per_path(records: Vec<ResponseFileRecord>) -> Vec<ResponseFileRecord> {
+    let mut newest: HashMap<String, ResponseFileRecord> = HashMap::new();
+    for record in records {
+        match newest.get(&record.path) {
+            Some(existing) if existing.id >= record.id => {}
+            _ => {
+                newest.insert(record.path.clone(), record);
+            }
+        }
+    }
+
+    let mut records: Vec<_> = newest.into_values().collect();
+    records.sort_by_key(|r| r.id);
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: i32, path: &str, deleted: bool) -> ResponseFileRecord {
+        ResponseFileRecord {
+            id,
+            path: path.to_string(),
+            deleted,
+            chunk_ids: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_latest_per_path() {
+        let records = latest_per_path(vec![
+            record(3, "Soup.cook", false),
+            record(5, "Pasta.cook", false),
+            record(7, "Soup.cook", true),
+            record(6, "Pasta.cook", false),
+        ]);
+
+        let ids: Vec<_> = records.iter().map(|r| (r.id, r.deleted)).collect();
+        assert_eq!(ids, vec![(6, false), (7, true)]);
+    }
+
+    #[test]
+    fn test_last_jid_is_stored_per_namespace() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db.sqlite3");
+
+        let state = DownloadState::open(&db_path, 1).unwrap();
+        assert_eq!(state.last_jid(42).unwrap(), 42);
+
+        state.set_last_jid(10).unwrap();
+        state.set_last_jid(12).unwrap();
+        assert_eq!(state.last_jid(42).unwrap(), 12);
+
+        let reopened = DownloadState::open(&db_path, 1).unwrap();
+        assert_eq!(reopened.last_jid(0).unwrap(), 12);
+
+        let other = DownloadState::open(&db_path, 2).unwrap();
+        assert_eq!(other.last_jid(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_entries_are_kept_until_downloads_pass_them() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db.sqlite3");
+
+        let state = DownloadState::open(&db_path, 1).unwrap();
+        state.add_entry("Soup.cook", 9).unwrap();
+        state.add_entry("Pasta.cook", 12).unwrap();
+        assert!(state.has_entry("Soup.cook", 9).unwrap());
+        assert!(!state.has_entry("Soup.cook", 10).unwrap());
+        assert!(!state.has_entry("Pasta.cook", 9).unwrap());
+        assert!(!DownloadState::open(&db_path, 2)
+            .unwrap()
+            .has_entry("Soup.cook", 9)
+            .unwrap());
+
+        state.set_last_jid(10).unwrap();
+        assert!(!state.has_entry("Soup.cook", 9).unwrap());
+        assert!(state.has_entry("Pasta.cook", 12).unwrap());
+    }
+}
@@ -0,0 +1,767 @@
+//! Single sync passes built from the cooklang-sync-client building blocks.
+//!
+//! `cooklang_sync_client::run_async` owns its own watcher and timers and only
+//! returns when cancelled. The agent needs to decide itself when to sync, so it
+//! drives the same indexer/uploader/downloader steps one pass at a time.
+
+use super::bandwidth::Bandwidth;
+use super::conflicts::{self, Conflict};
+use super::connectivity::{status_in_message, FailureKind};
+use super::download_state::{latest_per_path, DownloadState};
+use super::ignore_rules::{index_once, IgnoreRules};
+use super::scope::RemoteScope;
+use super::status::{SyncPhase, SyncProgress};
+use super::status_listener::ProgressListener;
+use crate::config::settings::SyncDirection;
+use crate::error::{Result, SyncError};
+use crate::logging;
+use chrono::Utc;
+use cooklang_sync_client::chunker::{Chunker, InMemoryCache};
+use cooklang_sync_client::connection::{get_connection, get_connection_pool, ConnectionPool};
+use cooklang_sync_client::models::{CreateForm, DeleteForm, FileRecord};
+use cooklang_sync_client::registry;
+use cooklang_sync_client::remote::{CommitResultStatus, Remote};
+use cooklang_sync_client::{SyncStatus as ClientSyncStatus, SyncStatusListener};
+use futures_util::StreamExt;
+use log::{debug, trace, warn};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+// Same limits cooklang-sync-client uses for its own loop
+const CHUNK_CACHE_MAX_RECORDS: usize = 100_000;
+const CHUNK_CACHE_MAX_BYTES: u64 = 100_000_000_000;
+
+/// Remote polls that end without changes are repeated at most this often
+const MIN_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Chunks are uploaded in batches of roughly this size
+const MAX_UPLOAD_BATCH_BYTES: usize = 3_000_000;
+
+/// Uploading a file takes two rounds (missing chunks, then commit); allow a few
+/// more for files that change while uploading
+const MAX_UPLOAD_ROUNDS: usize = 5;
+
+/// What a single pass did, as paths relative to the recipes directory
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PassOutcome {
+    /// Local files were added, changed or removed since the last pass
+    pub local_changes: bool,
+    /// Local changes (including deletions) committed to the server
+    pub uploaded: Vec<String>,
+    /// Files written to the recipes directory from the server
+    pub downloaded: Vec<String>,
+    /// Files removed from the recipes directory because they were deleted remotely
+    pub removed: Vec<String>,
+    /// Local changes still waiting to be uploaded (always 0 when uploads are off)
+    pub pending_uploads: usize,
+    /// Local edits that collided with changes on the server during this pass
+    pub conflicts: Vec<Conflict>,
+}
+
+impl PassOutcome {
+    /// Whether anything moved in either direction
+    pub fn is_empty(&self) -> bool {
+        self.uploaded.is_empty() && self.downloaded.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Counts files and bytes of a running pass for a `ProgressListener`
+struct Progress {
+    listener: Option<Arc<dyn ProgressListener>>,
+    current: SyncProgress,
+}
+
+impl Progress {
+    fn new(listener: Option<Arc<dyn ProgressListener>>) -> Self {
+        Self {
+            listener,
+            current: SyncProgress::new(SyncPhase::Indexing),
+        }
+    }
+
+    fn start(&mut self, phase: SyncPhase, files_total: usize) {
+        self.current = SyncProgress {
+            files_total,
+            bytes_transferred: self.current.bytes_transferred,
+            ..SyncProgress::new(phase)
+        };
+        self.report();
+    }
+
+    fn file_done(&mut self) {
+        self.current.files_done += 1;
+        self.report();
+    }
+
+    fn add_bytes(&mut self, bytes: usize) {
+        self.current.bytes_transferred += bytes as u64;
+        self.report();
+    }
+
+    fn report(&self) {
+        if let Some(listener) = &self.listener {
+            listener.on_progress(&self.current);
+        }
+    }
+}
+
+pub struct SyncEngine {
+    pool: ConnectionPool,
+    chunker: Chunker,
+    remote: Remote,
+    storage_dir: PathBuf,
+    db_path: PathBuf,
+    scope: RemoteScope,
+    ignore: Arc<IgnoreRules>,
+    /// Rate limits; unlimited unless set
+    bandwidth: Arc<Bandwidth>,
+    namespace_id: i32,
+    jwt: String,
+    /// Names this machine in conflict copies
+    host: String,
+}
+
+impl SyncEngine {
+    pub fn new(
+        recipes_dir: &Path,
+        db_path: &Path,
+        sync_endpoint: &str,
+        jwt: &str,
+        namespace_id: i32,
+        scope: RemoteScope,
+    ) -> Result<Self> {
+        let pool = get_connection_pool(&db_path.to_string_lossy()).map_err(map_client_error)?;
+        let cache = InMemoryCache::new(CHUNK_CACHE_MAX_RECORDS, CHUNK_CACHE_MAX_BYTES);
+
+        Ok(Self {
+            pool,
+            chunker: Chunker::new(cache, recipes_dir.to_path_buf()),
+            remote: Remote::new(sync_endpoint, jwt),
+            storage_dir: recipes_dir.to_path_buf(),
+            db_path: db_path.to_path_buf(),
+            scope,
+            ignore: Arc::new(IgnoreRules::none()),
+            bandwidth: Arc::default(),
+            namespace_id,
+            jwt: jwt.to_string(),
+            host: conflicts::local_host(),
+        })
+    }
+
+    /// Re-read the folder's ignore file and apply `global_patterns` from the
+    /// settings to the following passes
+    pub fn reload_ignore_rules(&mut self, global_patterns: &[String]) {
+        self.ignore = Arc::new(IgnoreRules::load(&self.storage_dir, global_patterns));
+    }
+
+    /// Limit the transfers of the following passes
+    pub fn set_bandwidth(&mut self, bandwidth: Arc<Bandwidth>) {
+        self.bandwidth = bandwidth;
+    }
+
+    /// Token the remote client was created with
+    pub fn jwt(&self) -> &str {
+        &self.jwt
+    }
+
+    /// Index local files, upload local changes, then download remote ones.
+    /// `direction` decides which of the two transfer steps run; `progress`
+    /// receives file and byte counts along the way.
+    pub async fn run_pass(
+        &mut self,
+        direction: SyncDirection,
+        listener: Option<Arc<dyn SyncStatusListener>>,
+        progress: Option<Arc<dyn ProgressListener>>,
+    ) -> Result<PassOutcome> {
+        let mut progress = Progress::new(progress);
+        let result = self
+            .pass(direction, listener.as_deref(), &mut progress)
+            .await;
+
+        if let Some(cb) = &listener {
+            match &result {
+                Ok(_) => cb.on_complete(true, None),
+                Err(e) => cb.on_complete(false, Some(e.to_string())),
+            }
+        }
+
+        result
+    }
+
+    async fn pass(
+        &mut self,
+        direction: SyncDirection,
+        listener: Option<&dyn SyncStatusListener>,
+        progress: &mut Progress,
+    ) -> Result<PassOutcome> {
+        let notify = |status: ClientSyncStatus| {
+            if let Some(cb) = listener {
+                cb.on_status_changed(status);
+            }
+        };
+
+        notify(ClientSyncStatus::Indexing);
+        progress.start(SyncPhase::Indexing, 0);
+        let local_changes = {
+            // Walking the directory is blocking IO
+            let pool = self.pool.clone();
+            let dir = self.storage_dir.clone();
+            let namespace_id = self.namespace_id;
+            let ignore = Arc::clone(&self.ignore);
+            tokio::task::spawn_blocking(logging::in_sync_run(move || {
+                index_once(&pool, &dir, namespace_id, &ignore)
+            }))
+            .await
+            .map_err(|e| SyncError::Other(format!("Indexer task failed: {e}")))??
+        };
+
+        // Local changes are still indexed in download-only mode, so switching
+        // directions later uploads them
+        let to_upload = self.updated_locally()?;
+        let mut new_conflicts = vec![];
+        if direction.uploads() && !to_upload.is_empty() {
+            // Only both-way folders can collide; upload-only folders overwrite the server
+            let held_back = if direction.downloads() {
+                let (held_back, found) = self.check_conflicts(&to_upload).await?;
+                new_conflicts = found;
+                held_back
+            } else {
+                HashSet::new()
+            };
+
+            notify(ClientSyncStatus::Uploading);
+            progress.start(
+                SyncPhase::Uploading,
+                to_upload.difference(&held_back).count(),
+            );
+            let mut committed = false;
+            for _ in 0..MAX_UPLOAD_ROUNDS {
+                if self.upload_once(&held_back, progress).await? {
+                    committed = true;
+                    break;
+                }
+            }
+            if !committed {
+                warn!("Some local changes are still waiting to be uploaded");
+            }
+        }
+        let still_pending = self.updated_locally()?;
+        let uploaded = to_upload.difference(&still_pending).cloned().collect();
+
+        let (downloaded, removed) = if direction.downloads() {
+            notify(ClientSyncStatus::Downloading);
+            self.download(progress).await?
+        } else {
+            (vec![], vec![])
+        };
+        // Held-back paths are settled by the download
+        let still_pending = if new_conflicts.is_empty() {
+            still_pending
+        } else {
+            self.updated_locally()?
+        };
+
+        let outcome = PassOutcome {
+            local_changes,
+            uploaded,
+            downloaded,
+            removed,
+            // Local edits in download-only mode are kept, but not waiting for anything
+            pending_uploads: if direction.uploads() {
+                still_pending.len()
+            } else {
+                0
+            },
+            conflicts: new_conflicts,
+        };
+        debug!("Sync pass finished: {outcome:?}");
+
+        Ok(outcome)
+    }
+
+    /// Paths with local changes the server has not acknowledged yet
+    fn updated_locally(&self) -> Result<HashSet<String>> {
+        Ok(self
+            .pending_records()?
+            .into_iter()
+            .map(|r| r.path)
+            .collect())
+    }
+
+    /// Registry records waiting to be uploaded, minus paths owned by other
+    /// folders and ignored paths
+    fn pending_records(&self) -> Result<Vec<FileRecord>> {
+        let conn = &mut get_connection(&self.pool).map_err(map_client_error)?;
+        let records = registry::updated_locally(conn, self.namespace_id)
+            .map_err(|e| map_client_error(e.into()))?;
+        Ok(records
+            .into_iter()
+            .filter(|r| {
+                !self.scope.is_excluded(&r.path)
+                    && !self.ignore.is_ignored(Path::new(&r.path), false)
+            })
+            .collect())
+    }
+
+    /// Find pending local changes to paths that also changed on the server
+    /// since the last download.
+    ///
+    /// The server version wins: an edited local file is copied aside as a
+    /// conflict copy, and the path is held back from uploading so the
+    /// following download replaces it. A local deletion of a file edited
+    /// elsewhere is dropped the same way, which brings the file back.
+    /// Returns the held-back paths and the conflict copies made.
+    async fn check_conflicts(
+        &mut self,
+        pending: &HashSet<String>,
+    ) -> Result<(HashSet<String>, Vec<Conflict>)> {
+        let state = DownloadState::open(&self.db_path, self.namespace_id)?;
+        let since = {
+            let conn = &mut get_connection(&self.pool).map_err(map_client_error)?;
+            state.last_jid(registry::latest_jid(conn, self.namespace_id).unwrap_or(0))?
+        };
+        let listed = self.remote.list(since).await.map_err(map_client_error)?;
+
+        let mut held_back = HashSet::new();
+        let mut found = vec![];
+        for d in latest_per_path(listed) {
+            let Some(path) = self.scope.to_local(&d.path) else {
+                continue;
+            };
+            // Our own uploads are not concurrent changes
+            if !pending.contains(&path) || state.has_entry(&path, d.id)? {
+                continue;
+            }
+            held_back.insert(path.clone());
+
+            if !self.chunker.exists(&path) {
+                continue;
+            }
+            let local_chunks = self
+                .chunker
+                .hashify(&path)
+                .await
+                .map_err(map_client_error)?;
+            // Both sides made the same edit
+            if !d.deleted && local_chunks.join(",") == d.chunk_ids {
+                continue;
+            }
+
+            let conflict =
+                conflicts::preserve(&self.storage_dir, Path::new(&path), &self.host, Utc::now())?;
+            warn!(
+                "{path} changed here and on the server; kept this version as {}",
+                conflict.copy.display()
+            );
+            found.push(conflict);
+        }
+
+        Ok((held_back, found))
+    }
+
+    /// Commit pending local changes, uploading chunks the server asks for.
+    ///
+    /// Mirrors `cooklang_sync_client::syncer::check_upload_once`, but maps
+    /// paths into the folder's scope on the server and skips `held_back`
+    /// paths. Returns true once every other change was committed.
+    async fn upload_once(
+        &mut self,
+        held_back: &HashSet<String>,
+        progress: &mut Progress,
+    ) -> Result<bool> {
+        let conn = &mut get_connection(&self.pool).map_err(map_client_error)?;
+        let state = DownloadState::open(&self.db_path, self.namespace_id)?;
+        let to_upload: Vec<_> = self
+            .pending_records()?
+            .into_iter()
+            .filter(|r| !held_back.contains(&r.path))
+            .collect();
+
+        let mut batches: Vec<Vec<(String, Vec<u8>)>> = vec![vec![]];
+        let mut batch_size = 0;
+        let mut all_committed = true;
+
+        for f in &to_upload {
+            trace!("to upload {:?}", f);
+            let chunk_ids = if f.deleted {
+                vec![String::new()]
+            } else {
+                // Also warms up the cache
+                self.chunker
+                    .hashify(&f.path)
+                    .await
+                    .map_err(map_client_error)?
+            };
+
+            let result = self
+                .remote
+                .commit(
+                    &self.scope.to_remote(&f.path),
+                    f.deleted,
+                    &chunk_ids.join(","),
+                )
+                .await
+                .map_err(map_client_error)?;
+
+            match result {
+                CommitResultStatus::Success(jid) => {
+                    registry::update_jid(conn, f, jid).map_err(|e| map_client_error(e.into()))?;
+                    state.add_entry(&f.path, jid)?;
+                    progress.file_done();
+                }
+                CommitResultStatus::NeedChunks(chunks) => {
+                    all_committed = false;
+                    for c in chunks.split(',') {
+                        let data = self.chunker.read_chunk(c).map_err(map_client_error)?;
+                        batch_size += data.len();
+                        batches
+                            .last_mut()
+                            .expect("there is always a batch")
+                            .push((c.to_string(), data));
+
+                        if batch_size > MAX_UPLOAD_BATCH_BYTES {
+                            batches.push(vec![]);
+                            batch_size = 0;
+                        }
+                    }
+                }
+            }
+        }
+
+        for batch in batches.into_iter().filter(|b| !b.is_empty()) {
+            let bytes = batch.iter().map(|(_, data)| data.len()).sum();
+            self.bandwidth.upload(bytes).await;
+            self.remote
+                .upload_batch(batch)
+                .await
+                .map_err(map_client_error)?;
+            progress.add_bytes(bytes);
+        }
+
+        Ok(all_committed)
+    }
+
+    /// Apply remote changes since the last downloaded journal id.
+    ///
+    /// Mirrors `cooklang_sync_client::syncer::check_download_once`, but reports
+    /// which files were touched and skips changes that were uploaded from here.
+    /// Returns (written, removed) paths.
+    async fn download(&mut self, progress: &mut Progress) -> Result<(Vec<String>, Vec<String>)> {
+        let conn = &mut get_connection(&self.pool).map_err(map_client_error)?;
+        let state = DownloadState::open(&self.db_path, self.namespace_id)?;
+
+        // Installs from before the download state existed continue where the registry is
+        let since = state.last_jid(registry::latest_jid(conn, self.namespace_id).unwrap_or(0))?;
+        let listed = self.remote.list(since).await.map_err(map_client_error)?;
+        let Some(newest) = listed.iter().map(|d| d.id).max() else {
+            return Ok((vec![], vec![]));
+        };
+
+        let mut to_download = vec![];
+        for mut d in latest_per_path(listed) {
+            // Changes in other folders' parts of the server tree
+            let Some(local) = self.scope.to_local(&d.path) else {
+                continue;
+            };
+            d.path = local;
+            if !state.has_entry(&d.path, d.id)? {
+                to_download.push(d);
+            }
+        }
+
+        progress.start(SyncPhase::Downloading, to_download.len());
+
+        let mut download_queue: Vec<&str> = vec![];
+        for d in to_download.iter().filter(|d| !d.deleted) {
+            trace!("collecting needed chunks for {:?}", d.path);
+
+            // Warm up the cache with chunks from the old version of the file
+            if self.chunker.exists(&d.path) {
+                self.chunker
+                    .hashify(&d.path)
+                    .await
+                    .map_err(map_client_error)?;
+            }
+
+            for c in d.chunk_ids.split(',') {
+                if !self.chunker.check_chunk(c) {
+                    download_queue.push(c);
+                }
+            }
+        }
+
+        if !download_queue.is_empty() {
+            let mut downloaded = self.remote.download_batch(download_queue).await;
+            while let Some(result) = downloaded.next().await {
+                let (chunk_id, data) = result.map_err(map_client_error)?;
+                progress.add_bytes(data.len());
+                // Reading the stream more slowly slows down the transfer
+                self.bandwidth.download(data.len()).await;
+                self.chunker
+                    .save_chunk(&chunk_id, data)
+                    .map_err(map_client_error)?;
+            }
+        }
+
+        let mut written = vec![];
+        let mut removed = vec![];
+
+        for d in &to_download {
+            if d.deleted {
+                let form = DeleteForm {
+                    path: d.path.clone(),
+                    jid: Some(d.id),
+                    deleted: true,
+                    size: 0,
+                    modified_at: OffsetDateTime::now_utc(),
+                    namespace_id: self.namespace_id,
+                };
+                registry::delete(conn, &vec![form]).map_err(|e| map_client_error(e.into()))?;
+                if self.chunker.exists(&d.path) {
+                    self.chunker
+                        .delete(&d.path)
+                        .await
+                        .map_err(map_client_error)?;
+                    removed.push(d.path.clone());
+                }
+            } else {
+                let chunks: Vec<&str> = d.chunk_ids.split(',').collect();
+                self.chunker
+                    .save(&d.path, chunks)
+                    .await
+                    .map_err(map_client_error)?;
+
+                let form = self.file_record(&d.path, d.id)?;
+                registry::create(conn, &vec![form]).map_err(|e| map_client_error(e.into()))?;
+                written.push(d.path.clone());
+            }
+            state.add_entry(&d.path, d.id)?;
+            progress.file_done();
+        }
+
+        state.set_last_jid(newest)?;
+
+        Ok((written, removed))
+    }
+
+    /// Registry record for a file that now matches journal entry `jid`
+    fn file_record(&self, path: &str, jid: i32) -> Result<CreateForm> {
+        let metadata = self.storage_dir.join(path).metadata()?;
+
+        Ok(CreateForm {
+            jid: Some(jid),
+            path: path.to_string(),
+            deleted: false,
+            size: metadata.len() as i64,
+            modified_at: OffsetDateTime::from(metadata.modified()?),
+            namespace_id: self.namespace_id,
+        })
+    }
+
+    /// Long-poll the server until it has changes for this folder. A poll
+    /// also ends when it times out, so each one is followed by a look at the
+    /// journal, and only new entries end the wait.
+    pub async fn wait_remote_change(&self) -> Result<()> {
+        wait_for_change(
+            MIN_POLL_INTERVAL,
+            || async { self.remote.poll().await.map_err(map_client_error) },
+            || self.has_remote_changes(),
+        )
+        .await
+    }
+
+    /// Whether the server has journal entries in this folder's part of the
+    /// tree that were not applied or uploaded here
+    async fn has_remote_changes(&self) -> Result<bool> {
+        let state = DownloadState::open(&self.db_path, self.namespace_id)?;
+        let since = {
+            let conn = &mut get_connection(&self.pool).map_err(map_client_error)?;
+            state.last_jid(registry::latest_jid(conn, self.namespace_id).unwrap_or(0))?
+        };
+        let listed = self.remote.list(since).await.map_err(map_client_error)?;
+
+        for d in latest_per_path(listed) {
+            if let Some(path) = self.scope.to_local(&d.path) {
+                if !state.has_entry(&path, d.id)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Run `poll` until `changed` says there is a change, starting polls at
+/// most every `min_interval` so a server that answers at once is not hammered
+async fn wait_for_change<P, C>(
+    min_interval: std::time::Duration,
+    mut poll: impl FnMut() -> P,
+    mut changed: impl FnMut() -> C,
+) -> Result<()>
+where
+    P: std::future::Future<Output = Result<()>>,
+    C: std::future::Future<Output = Result<bool>>,
+{
+    loop {
+        let started = tokio::time::Instant::now();
+        poll().await?;
+        if changed().await? {
+            return Ok(());
+        }
+        trace!("Remote poll ended without changes");
+        tokio::time::sleep_until(started + min_interval).await;
+    }
+}
+
+pub(crate) fn map_client_error(e: cooklang_sync_client::errors::SyncError) -> SyncError {
+    use cooklang_sync_client::errors::SyncError as ClientError;
+
+    match e {
+        ClientError::Unauthorized => SyncError::AuthenticationRequired,
+        // Network failures and HTTP errors decide how the manager retries
+        ClientError::ReqwestError(err) => {
+            FailureKind::from_reqwest(&err).into_error(err.to_string())
+        }
+        ClientError::Unknown(message) => match status_in_message(&message) {
+            Some(status) => FailureKind::from_status(status).into_error(message),
+            None => SyncError::Other(format!("Sync failed: {message}")),
+        },
+        // The server answered with something that could not be used
+        ClientError::BodyExtractError | ClientError::BatchDownloadError(_) => {
+            SyncError::Server(e.to_string())
+        }
+        // Local trouble that may clear up, such as a file being written or a
+        // locked database
+        ClientError::IoError { .. }
+        | ClientError::IoErrorGeneric(_)
+        | ClientError::DBQueryError(_)
+        | ClientError::ConnectionInitError(_)
+        | ClientError::NotifyError(_)
+        | ClientError::GetFromCacheError
+        | ClientError::ChannelSendError(_) => SyncError::SyncClient(e.to_string()),
+        _ => SyncError::Other(format!("Sync failed: {e:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+
+    #[derive(Default)]
+    struct RecordingListener {
+        completions: StdMutex<Vec<bool>>,
+    }
+
+    impl SyncStatusListener for RecordingListener {
+        fn on_status_changed(&self, _status: ClientSyncStatus) {}
+
+        fn on_complete(&self, success: bool, _message: Option<String>) {
+            self.completions.lock().unwrap().push(success);
+        }
+    }
+
+    #[test]
+    fn test_unauthorized_maps_to_authentication_required() {
+        let e = map_client_error(cooklang_sync_client::errors::SyncError::Unauthorized);
+        assert!(matches!(e, SyncError::AuthenticationRequired));
+
+        let e = map_client_error(cooklang_sync_client::errors::SyncError::Unknown(
+            "boom".to_string(),
+        ));
+        assert!(matches!(e, SyncError::Other(_)));
+
+        let e = map_client_error(cooklang_sync_client::errors::SyncError::Unknown(
+            "Commit failed with status: 502 Bad Gateway".to_string(),
+        ));
+        assert!(matches!(e, SyncError::Server(_)));
+
+        let e = map_client_error(cooklang_sync_client::errors::SyncError::Unknown(
+            "Commit failed with status: 413 Payload Too Large".to_string(),
+        ));
+        assert!(matches!(e, SyncError::Rejected(_)));
+    }
+
+    #[tokio::test]
+    async fn test_pass_reports_unreachable_server() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let recipes_dir = temp_dir.path().join("recipes");
+        std::fs::create_dir(&recipes_dir).unwrap();
+        std::fs::write(recipes_dir.join("Soup.cook"), "Boil @water{1%l}.").unwrap();
+
+        // Nothing listens on port 1, so the upload step fails after indexing
+        let mut engine = SyncEngine::new(
+            &recipes_dir,
+            &temp_dir.path().join("db.sqlite3"),
+            "http://127.0.0.1:1",
+            "token",
+            1,
+            RemoteScope::default(),
+        )
+        .unwrap();
+
+        let listener = Arc::new(RecordingListener::default());
+        let result = engine
+            .run_pass(SyncDirection::Both, Some(listener.clone()), None)
+            .await;
+
+        assert!(matches!(result, Err(SyncError::Offline(_))));
+        assert_eq!(*listener.completions.lock().unwrap(), vec![false]);
+    }
+
+    #[tokio::test]
+    async fn test_upload_only_pass_never_contacts_server_for_downloads() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let recipes_dir = temp_dir.path().join("recipes");
+        std::fs::create_dir(&recipes_dir).unwrap();
+
+        let mut engine = SyncEngine::new(
+            &recipes_dir,
+            &temp_dir.path().join("db.sqlite3"),
+            "http://127.0.0.1:1",
+            "token",
+            1,
+            RemoteScope::default(),
+        )
+        .unwrap();
+
+        // Nothing to upload and downloads are off, so the unreachable server is never hit
+        let outcome = engine
+            .run_pass(SyncDirection::UploadOnly, None, None)
+            .await
+            .unwrap();
+        assert!(outcome.is_empty());
+        assert!(!outcome.local_changes);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_polls_do_not_end_the_wait() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Every poll "times out" at once; the journal shows a change on the third
+        let polls = AtomicUsize::new(0);
+        let checks = AtomicUsize::new(0);
+        wait_for_change(
+            std::time::Duration::ZERO,
+            || async {
+                polls.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            },
+            || async { Ok(checks.fetch_add(1, Ordering::SeqCst) == 2) },
+        )
+        .await
+        .unwrap();
+        assert_eq!(polls.load(Ordering::SeqCst), 3);
+
+        // A failing poll ends the wait, so the loop can stop relying on polls
+        let result = wait_for_change(
+            std::time::Duration::ZERO,
+            || async { Err(SyncError::Offline("down".to_string())) },
+            || async { Ok(false) },
+        )
+        .await;
+        assert!(matches!(result, Err(SyncError::Offline(_))));
+    }
+}
@@ -0,0 +1,350 @@
+//! Running user commands on sync events.
+//!
+//! Hooks are shell commands from the settings, e.g. rebuilding a static site
+//! after new recipes arrived. Each run gets the event as `COOK_SYNC_*`
+//! environment variables and as one JSON object on stdin. Hooks run in the
+//! background so a slow one never holds up syncing; at most
+//! `max_concurrent` run at a time and each is killed after `timeout_secs`.
+
+use crate::config::settings::HookSettings;
+use crate::error::{Result, SyncError};
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::{Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Longest hook output kept in the log
+const MAX_LOGGED_OUTPUT: usize = 1000;
+
+/// Something a hook can react to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    /// A pass transferred files; paths are relative to the folder
+    SyncComplete {
+        folder: PathBuf,
+        uploaded: Vec<String>,
+        downloaded: Vec<String>,
+        removed: Vec<String>,
+    },
+    FileDownloaded {
+        folder: PathBuf,
+        path: String,
+    },
+    /// The local version of `path` was kept as `copy`
+    Conflict {
+        folder: PathBuf,
+        path: PathBuf,
+        copy: PathBuf,
+        host: String,
+    },
+    /// A pass failed and is not retried
+    Error {
+        folder: PathBuf,
+        message: String,
+    },
+}
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::SyncComplete { .. } => "sync_complete",
+            HookEvent::FileDownloaded { .. } => "file_downloaded",
+            HookEvent::Conflict { .. } => "conflict",
+            HookEvent::Error { .. } => "error",
+        }
+    }
+
+    /// The command configured for this event, if any
+    fn command<'a>(&self, settings: &'a HookSettings) -> Option<&'a str> {
+        let command = match self {
+            HookEvent::SyncComplete { .. } => &settings.on_sync_complete,
+            HookEvent::FileDownloaded { .. } => &settings.on_file_downloaded,
+            HookEvent::Conflict { .. } => &settings.on_conflict,
+            HookEvent::Error { .. } => &settings.on_error,
+        };
+        command.as_deref().filter(|c| !c.trim().is_empty())
+    }
+
+    fn env(&self) -> Vec<(&'static str, String)> {
+        let folder = |folder: &PathBuf| ("COOK_SYNC_FOLDER", folder.display().to_string());
+        let mut env = vec![("COOK_SYNC_EVENT", self.name().to_string())];
+
+        match self {
+            HookEvent::SyncComplete {
+                folder: dir,
+                uploaded,
+                downloaded,
+                removed,
+            } => env.extend([
+                folder(dir),
+                ("COOK_SYNC_UPLOADED", uploaded.len().to_string()),
+                ("COOK_SYNC_DOWNLOADED", downloaded.len().to_string()),
+                ("COOK_SYNC_REMOVED", removed.len().to_string()),
+            ]),
+            HookEvent::FileDownloaded { folder: dir, path } => env.extend([
+                folder(dir),
+                ("COOK_SYNC_FILE", dir.join(path).display().to_string()),
+            ]),
+            HookEvent::Conflict {
+                folder: dir,
+                path,
+                copy,
+                host,
+            } => env.extend([
+                folder(dir),
+                ("COOK_SYNC_FILE", dir.join(path).display().to_string()),
+                (
+                    "COOK_SYNC_CONFLICT_COPY",
+                    dir.join(copy).display().to_string(),
+                ),
+                ("COOK_SYNC_CONFLICT_HOST", host.clone()),
+            ]),
+            HookEvent::Error {
+                folder: dir,
+                message,
+            } => env.extend([folder(dir), ("COOK_SYNC_ERROR", message.clone())]),
+        }
+        env
+    }
+}
+
+/// Runs hooks in the background, shared by all folders
+pub struct Hooks {
+    slots: Arc<Semaphore>,
+    /// Slots handed out so far, to follow changes of `max_concurrent`
+    limit: Mutex<usize>,
+    /// Hooks fired and not yet waited for
+    running: Mutex<JoinSet<()>>,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        let limit = HookSettings::default().max_concurrent;
+        Self {
+            slots: Arc::new(Semaphore::new(limit)),
+            limit: Mutex::new(limit),
+            running: Mutex::default(),
+        }
+    }
+}
+
+impl Hooks {
+    /// Run the hook for `event`, if one is configured. Returns right away.
+    pub fn fire(&self, settings: &HookSettings, event: HookEvent) {
+        let Some(command) = event.command(settings).map(str::to_string) else {
+            return;
+        };
+        self.resize(settings.max_concurrent);
+
+        let timeout = Duration::from_secs(settings.timeout_secs);
+        let slots = Arc::clone(&self.slots);
+        let mut running = self.running.lock().unwrap();
+        // Forget runs that are over, so a long-running agent keeps no trace of them
+        while running.try_join_next().is_some() {}
+        running.spawn(async move {
+            let Ok(_slot) = slots.acquire_owned().await else {
+                return;
+            };
+            let name = event.name();
+            debug!("Running {name} hook: {command}");
+
+            match run(&command, &event, timeout).await {
+                Ok(output) if output.status.success() => {
+                    info!("The {name} hook finished");
+                    log_output(name, "stdout", &output.stdout);
+                }
+                Ok(output) => {
+                    warn!("The {name} hook failed ({})", output.status);
+                    log_output(name, "stderr", &output.stderr);
+                }
+                Err(e) => warn!("The {name} hook did not run to completion: {e}"),
+            }
+        });
+    }
+
+    /// Wait for the hooks fired so far to finish, so a single sync run does
+    /// not end them by exiting
+    pub async fn wait(&self) {
+        let mut running = std::mem::take(&mut *self.running.lock().unwrap());
+        while running.join_next().await.is_some() {}
+    }
+
+    fn resize(&self, wanted: usize) {
+        let mut limit = self.limit.lock().unwrap();
+        if wanted > *limit {
+            self.slots.add_permits(wanted - *limit);
+            *limit = wanted;
+        } else if wanted < *limit {
+            // Only free slots can go; busy ones are taken on a later event
+            *limit -= self.slots.forget_permits(*limit - wanted);
+        }
+    }
+}
+
+/// Run `command` through the shell with `event` as input. The command is
+/// killed when it runs longer than `timeout`.
+async fn run(command: &str, event: &HookEvent, timeout: Duration) -> Result<Output> {
+    let input = serde_json::to_vec(event)?;
+    let mut child = shell(command)
+        .envs(event.env())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin = child.stdin.take();
+    let write_input = async move {
+        if let Some(mut stdin) = stdin {
+            // Hooks are free to ignore their input and exit early
+            let _ = stdin.write_all(&input).await;
+        }
+    };
+    // Written alongside reading the output, so a chatty hook cannot block
+    let finished = async { tokio::join!(write_input, child.wait_with_output()).1 };
+
+    match tokio::time::timeout(timeout, finished).await {
+        Ok(output) => Ok(output?),
+        Err(_) => Err(SyncError::Other(format!(
+            "killed after {}s",
+            timeout.as_secs()
+        ))),
+    }
+}
+
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    }
+
+    #[cfg(not(windows))]
+    {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
+
+fn log_output(name: &str, stream: &str, output: &[u8]) {
+    let output = String::from_utf8_lossy(output);
+    let output = output.trim();
+    if output.is_empty() {
+        return;
+    }
+    let start = output.len().saturating_sub(MAX_LOGGED_OUTPUT);
+    let start = (start..output.len())
+        .find(|i| output.is_char_boundary(*i))
+        .unwrap_or(output.len());
+    info!("{name} hook {stream}: {}", &output[start..]);
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn downloaded() -> HookEvent {
+        HookEvent::FileDownloaded {
+            folder: PathBuf::from("/recipes"),
+            path: "Soups/Pho.cook".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hook_gets_env_and_json() {
+        let output = run(
+            "echo \"$COOK_SYNC_EVENT $COOK_SYNC_FILE\"; cat",
+            &downloaded(),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let (env, json) = stdout.split_once('\n').unwrap();
+        assert_eq!(env, "file_downloaded /recipes/Soups/Pho.cook");
+        let json: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(json["event"], "file_downloaded");
+        assert_eq!(json["path"], "Soups/Pho.cook");
+    }
+
+    #[tokio::test]
+    async fn test_slow_hook_is_killed() {
+        let started = std::time::Instant::now();
+        let result = run("sleep 30", &downloaded(), Duration::from_millis(200)).await;
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // Failing commands are reported through their exit status
+        let output = run("exit 3", &downloaded(), Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_hooks_wait_for_a_free_slot() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let log = temp_dir.path().join("log");
+        // Each run notes when it starts and ends; with one slot they never overlap
+        let settings = HookSettings {
+            on_file_downloaded: Some(format!(
+                "echo start >> {0}; sleep 0.2; echo end >> {0}",
+                log.display()
+            )),
+            max_concurrent: 1,
+            ..Default::default()
+        };
+
+        let hooks = Hooks::default();
+        for _ in 0..3 {
+            hooks.fire(&settings, downloaded());
+        }
+        // Not configured, so nothing runs
+        hooks.fire(
+            &settings,
+            HookEvent::Error {
+                folder: PathBuf::from("/recipes"),
+                message: "failed".to_string(),
+            },
+        );
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        let lines = loop {
+            let lines = std::fs::read_to_string(&log).unwrap_or_default();
+            if lines.lines().count() == 6 || std::time::Instant::now() > deadline {
+                break lines;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+        assert_eq!(lines, "start\nend\n".repeat(3));
+    }
+
+    #[tokio::test]
+    async fn test_wait_lets_hooks_finish() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let log = temp_dir.path().join("log");
+        let settings = HookSettings {
+            on_file_downloaded: Some(format!("sleep 0.2; echo done >> {}", log.display())),
+            ..Default::default()
+        };
+
+        let hooks = Hooks::default();
+        hooks.fire(&settings, downloaded());
+        hooks.fire(&settings, downloaded());
+        hooks.wait().await;
+
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "done\n".repeat(2));
+    }
+}
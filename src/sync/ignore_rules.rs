@@ -0,0 +1,418 @@
+//! Files the agent leaves alone.
+//!
+//! Rules come from a `.cooksyncignore` file in the root of a synced folder and
+//! from the global patterns in the settings, both in gitignore syntax. The
+//! file is read after the global patterns, so it can re-include (`!pattern`)
+//! something they exclude. Ignored files are never indexed or uploaded;
+//! changes made to them on the server are still downloaded.
+
+use crate::error::Result;
+use cooklang_sync_client::chunker::{is_binary, is_text};
+use cooklang_sync_client::connection::{get_connection, ConnectionPool};
+use cooklang_sync_client::models::{CreateForm, DeleteForm, FileRecord};
+use cooklang_sync_client::registry;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use log::warn;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+use super::engine::map_client_error;
+
+/// Name of the ignore file in the root of a synced folder
+pub const IGNORE_FILE: &str = ".cooksyncignore";
+
+pub struct IgnoreRules {
+    matcher: Gitignore,
+}
+
+/// The rule that excludes a path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IgnoreReason {
+    /// Pattern as written by the user
+    pub pattern: String,
+    /// The ignore file the pattern comes from; `None` for the global patterns
+    pub file: Option<PathBuf>,
+}
+
+impl std::fmt::Display for IgnoreReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "'{}' in {}", self.pattern, file.display()),
+            None => write!(f, "'{}' in global ignore patterns", self.pattern),
+        }
+    }
+}
+
+/// A path inside a synced folder that is currently excluded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IgnoredPath {
+    /// Relative to the synced folder
+    pub path: String,
+    /// Everything below an ignored directory is ignored as well
+    pub is_dir: bool,
+    pub reason: IgnoreReason,
+}
+
+impl IgnoreRules {
+    /// Rules for the folder at `root`. Invalid patterns are logged and skipped
+    /// so that one typo does not stop syncing.
+    pub fn load(root: &Path, global_patterns: &[String]) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+
+        for pattern in global_patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                warn!("Skipping invalid ignore pattern '{pattern}': {e}");
+            }
+        }
+
+        let file = root.join(IGNORE_FILE);
+        if file.is_file() {
+            if let Some(e) = builder.add(&file) {
+                warn!("Problem reading {}: {e}", file.display());
+            }
+        }
+
+        let matcher = builder.build().unwrap_or_else(|e| {
+            warn!("Ignoring all ignore rules of {}: {e}", root.display());
+            Gitignore::empty()
+        });
+
+        Self { matcher }
+    }
+
+    /// Rules that ignore nothing
+    pub fn none() -> Self {
+        Self {
+            matcher: Gitignore::empty(),
+        }
+    }
+
+    /// Whether `path`, relative to the folder, is excluded by itself or
+    /// through one of its parent directories
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.reason(path, is_dir).is_some()
+    }
+
+    /// The rule excluding `path`, if any
+    pub fn reason(&self, path: &Path, is_dir: bool) -> Option<IgnoreReason> {
+        match self.matcher.matched_path_or_any_parents(path, is_dir) {
+            Match::Ignore(glob) => Some(IgnoreReason {
+                pattern: glob.original().to_string(),
+                file: glob.from().map(Path::to_path_buf),
+            }),
+            Match::None | Match::Whitelist(_) => None,
+        }
+    }
+
+    /// Ignored directories and syncable files below `root`. Ignored
+    /// directories are listed once, without their contents.
+    pub fn list_ignored(&self, root: &Path) -> Vec<IgnoredPath> {
+        let mut ignored = Vec::new();
+        self.collect_ignored(root, root, &mut ignored);
+        ignored.sort_by(|a, b| a.path.cmp(&b.path));
+        ignored
+    }
+
+    fn collect_ignored(&self, root: &Path, dir: &Path, ignored: &mut Vec<IgnoredPath>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_symlink() {
+                continue;
+            }
+            let is_dir = path.is_dir();
+            if !is_dir && !is_syncable(&path) {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+
+            match self.reason(relative, is_dir) {
+                Some(reason) => ignored.push(IgnoredPath {
+                    path: relative.to_string_lossy().into_owned(),
+                    is_dir,
+                    reason,
+                }),
+                None if is_dir => self.collect_ignored(root, &path, ignored),
+                None => {}
+            }
+        }
+    }
+}
+
+/// Files the sync client would pick up, ignore rules aside
+fn is_syncable(path: &Path) -> bool {
+    !path.is_symlink() && (is_text(path) || is_binary(path))
+}
+
+/// Bring the registry in line with the files on disk, skipping ignored ones.
+///
+/// Mirrors `cooklang_sync_client::indexer::check_index_once`, except that
+/// ignored directories are not walked and registry records of ignored paths
+/// are left alone: a file that becomes ignored is not deleted on the server.
+/// Unreadable folders and files are skipped, keeping their records as well.
+/// Returns whether anything changed.
+pub fn index_once(
+    pool: &ConnectionPool,
+    root: &Path,
+    namespace_id: i32,
+    rules: &IgnoreRules,
+) -> Result<bool> {
+    let conn = &mut get_connection(pool).map_err(map_client_error)?;
+
+    let from_db: HashMap<String, FileRecord> = registry::non_deleted(conn, namespace_id)
+        .map_err(|e| map_client_error(e.into()))?
+        .into_iter()
+        .filter(|r| !rules.is_ignored(Path::new(&r.path), false))
+        .map(|r| (r.path.clone(), r))
+        .collect();
+
+    let mut from_disk = HashMap::new();
+    let mut skipped = Vec::new();
+    collect_files(
+        root,
+        root,
+        namespace_id,
+        rules,
+        &mut from_disk,
+        &mut skipped,
+    )?;
+
+    let mut to_remove = Vec::new();
+    let mut to_add = Vec::new();
+
+    for (path, db_file) in &from_db {
+        match from_disk.get(path) {
+            Some(disk_file) if db_file != disk_file => to_add.push(disk_file.clone()),
+            Some(_) => {}
+            // Files that could not be read are not known to be gone
+            None if skipped.iter().any(|p| Path::new(path).starts_with(p)) => {}
+            None => to_remove.push(DeleteForm {
+                path: path.clone(),
+                jid: None,
+                size: db_file.size,
+                modified_at: db_file.modified_at,
+                deleted: true,
+                namespace_id,
+            }),
+        }
+    }
+    for (path, disk_file) in &from_disk {
+        if !from_db.contains_key(path) {
+            to_add.push(disk_file.clone());
+        }
+    }
+
+    if !to_remove.is_empty() {
+        registry::delete(conn, &to_remove).map_err(|e| map_client_error(e.into()))?;
+    }
+    if !to_add.is_empty() {
+        registry::create(conn, &to_add).map_err(|e| map_client_error(e.into()))?;
+    }
+
+    Ok(!to_remove.is_empty() || !to_add.is_empty())
+}
+
+/// Adds the syncable files below `dir` to `files`. Folders and files that
+/// cannot be read are logged and added to `skipped` instead, so one bad entry
+/// does not stop the whole index; only an unreadable `root` is an error.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    namespace_id: i32,
+    rules: &IgnoreRules,
+    files: &mut HashMap<String, CreateForm>,
+    skipped: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if dir == root => return Err(e.into()),
+        Err(e) => {
+            warn!("Skipping unreadable folder {}: {e}", dir.display());
+            skipped.extend(dir.strip_prefix(root).ok().map(Path::to_path_buf));
+            return Ok(());
+        }
+    };
+
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                // The entry's name is unknown, so keep everything in its folder
+                warn!("Skipping unreadable entry in {}: {e}", dir.display());
+                skipped.extend(dir.strip_prefix(root).ok().map(Path::to_path_buf));
+                continue;
+            }
+        };
+        if path.is_symlink() {
+            continue;
+        }
+        let is_dir = path.is_dir();
+        if !is_dir && !is_syncable(&path) {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if rules.is_ignored(relative, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            collect_files(root, &path, namespace_id, rules, files, skipped)?;
+        } else {
+            let (size, modified) = match path
+                .metadata()
+                .and_then(|metadata| Ok((metadata.len(), metadata.modified()?)))
+            {
+                Ok(found) => found,
+                Err(e) => {
+                    warn!("Skipping unreadable file {}: {e}", path.display());
+                    skipped.push(relative.to_path_buf());
+                    continue;
+                }
+            };
+            let relative = relative.to_string_lossy().into_owned();
+            files.insert(
+                relative.clone(),
+                CreateForm {
+                    jid: None,
+                    path: relative,
+                    deleted: false,
+                    size: size as i64,
+                    modified_at: OffsetDateTime::from(modified),
+                    namespace_id,
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cooklang_sync_client::connection::get_connection_pool;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "Mix @flour{200%g}.").unwrap();
+    }
+
+    fn indexed(pool: &ConnectionPool) -> Vec<String> {
+        let conn = &mut get_connection(pool).unwrap();
+        let mut paths: Vec<_> = registry::non_deleted(conn, 1)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.path)
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_rules_from_file_and_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join(IGNORE_FILE), "drafts/\n!keep.cook\n").unwrap();
+
+        let rules = IgnoreRules::load(root, &["*.cook".to_string()]);
+
+        let reason = rules.reason(Path::new("drafts/Soup.md"), false).unwrap();
+        assert_eq!(reason.pattern, "drafts/");
+        assert_eq!(
+            reason.file.as_deref(),
+            Some(root.join(IGNORE_FILE).as_path())
+        );
+
+        let reason = rules.reason(Path::new("Soup.cook"), false).unwrap();
+        assert_eq!(reason.pattern, "*.cook");
+        assert_eq!(reason.file, None);
+
+        // The file can re-include what the global patterns exclude
+        assert!(!rules.is_ignored(Path::new("keep.cook"), false));
+        assert!(!rules.is_ignored(Path::new("Soup.md"), false));
+        assert!(!IgnoreRules::none().is_ignored(Path::new("Soup.cook"), false));
+    }
+
+    #[test]
+    fn test_index_once_skips_ignored_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("recipes");
+        write(&root, "Soup.cook");
+        write(&root, "drafts/Pasta.cook");
+        write(&root, "images/big.jpg");
+        let pool =
+            get_connection_pool(&temp_dir.path().join("db.sqlite3").to_string_lossy()).unwrap();
+
+        let rules = IgnoreRules::load(&root, &["drafts/".to_string(), "images".to_string()]);
+        assert!(index_once(&pool, &root, 1, &rules).unwrap());
+        assert_eq!(indexed(&pool), vec!["Soup.cook"]);
+
+        let ignored: Vec<_> = rules
+            .list_ignored(&root)
+            .into_iter()
+            .map(|i| (i.path, i.is_dir))
+            .collect();
+        assert_eq!(
+            ignored,
+            vec![("drafts".to_string(), true), ("images".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn test_newly_ignored_file_is_not_deleted() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("recipes");
+        write(&root, "Soup.cook");
+        write(&root, "Pasta.cook");
+        let pool =
+            get_connection_pool(&temp_dir.path().join("db.sqlite3").to_string_lossy()).unwrap();
+
+        assert!(index_once(&pool, &root, 1, &IgnoreRules::none()).unwrap());
+
+        // Ignoring a file and removing it locally leaves its record untouched
+        let rules = IgnoreRules::load(&root, &["Pasta.cook".to_string()]);
+        std::fs::remove_file(root.join("Pasta.cook")).unwrap();
+        assert!(!index_once(&pool, &root, 1, &rules).unwrap());
+        assert_eq!(indexed(&pool), vec!["Pasta.cook", "Soup.cook"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_folder_is_skipped() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("recipes");
+        write(&root, "Soup.cook");
+        write(&root, "locked/Pasta.cook");
+        let pool =
+            get_connection_pool(&temp_dir.path().join("db.sqlite3").to_string_lossy()).unwrap();
+        assert!(index_once(&pool, &root, 1, &IgnoreRules::none()).unwrap());
+
+        let locked = root.join("locked");
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        let readable = std::fs::read_dir(&locked).is_ok();
+        let result = index_once(&pool, &root, 1, &IgnoreRules::none());
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        if readable {
+            // Permissions are not enforced, e.g. when running as root
+            return;
+        }
+
+        // The folder's files are kept rather than deleted
+        assert!(!result.unwrap());
+        assert_eq!(indexed(&pool), vec!["Soup.cook", "locked/Pasta.cook"]);
+    }
+}
@@ -1,345 +1,1064 @@
-use super::status::{SyncState, SyncStatus};
+use super::activity::{ActivityJournal, FileAction, FileActivity, SyncRun};
+use super::bandwidth::Bandwidth;
+use super::conflicts::{self, Conflict};
+use super::connectivity::ConnectivityMonitor;
+use super::engine::{PassOutcome, SyncEngine};
+use super::hooks::{HookEvent, Hooks};
+use super::ignore_rules::IgnoreRules;
+use super::retry::{RetryDecision, RetryPolicy};
+use super::schedule;
+use super::scope::RemoteScope;
+use super::status::{FolderState, PassCounts, PauseReason, SyncState, SyncStatus};
 use super::status_listener::SyncManagerListener;
+use super::watcher::RecipesWatcher;
 use crate::auth::AuthManager;
+use crate::config::settings::{MeteredPolicy, ScheduleSettings, SyncDirection, SyncRoot};
 use crate::config::Config;
 use crate::error::{Result, SyncError};
 use cooklang_sync_client::{extract_uid_from_jwt, SyncContext};
 use log::{debug, error, info, warn};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
-use tokio::sync::RwLock;
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
-use tokio::time::{interval, Duration};
+use tokio::time::Duration;
 
 pub struct SyncManager {
     auth: Arc<AuthManager>,
     config: Arc<Config>,
-    state: Arc<Mutex<SyncState>>,
-    sync_context: Arc<RwLock<Option<Arc<SyncContext>>>>,
-    sync_task: Arc<Mutex<Option<JoinHandle<()>>>>,
-    retry_policy: RetryPolicy,
+    /// One entry per synced folder, in settings order
+    folders: Mutex<Vec<Arc<FolderSync>>>,
+    /// Shared by all folders so one probe covers them while offline
+    connectivity: Arc<ConnectivityMonitor>,
+    /// Rate limits shared by all folders
+    bandwidth: Arc<Bandwidth>,
+    /// User commands run on sync events
+    hooks: Arc<Hooks>,
 }
 
-#[derive(Clone)]
-struct RetryPolicy {
-    max_retries: usize,
-    base_delay: Duration,
-    max_delay: Duration,
+/// Sync loop and live state of one synced folder
+struct FolderSync {
+    root: SyncRoot,
+    state: Arc<Mutex<SyncState>>,
+    context: Mutex<Option<Arc<SyncContext>>>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    /// Wakes the sync loop for local changes and explicit sync requests
+    trigger: Arc<Notify>,
+    passes: Mutex<PassCounts>,
 }
 
-impl Default for RetryPolicy {
-    fn default() -> Self {
+impl FolderSync {
+    /// A folder with the conflicts already on disk, outside of the folders
+    /// `ignore_patterns` exclude
+    fn new(root: SyncRoot, ignore_patterns: &[String]) -> Self {
+        let rules = IgnoreRules::load(&root.path, ignore_patterns);
+        let state = SyncState {
+            conflicts: conflicts::find(&root.path, &rules),
+            ..SyncState::default()
+        };
+
         Self {
-            max_retries: 5,
-            base_delay: Duration::from_secs(5),
-            max_delay: Duration::from_secs(300),
+            root,
+            state: Arc::new(Mutex::new(state)),
+            context: Mutex::new(None),
+            task: Arc::new(Mutex::new(None)),
+            trigger: Arc::new(Notify::new()),
+            passes: Mutex::new(PassCounts::default()),
+        }
+    }
+
+    fn folder_state(&self) -> FolderState {
+        FolderState {
+            path: self.root.path.clone(),
+            remote_folder: self.root.remote_folder.clone(),
+            state: self.state.lock().unwrap().clone(),
+            passes: *self.passes.lock().unwrap(),
+        }
+    }
+
+    fn count_pass(&self, succeeded: bool) {
+        let mut passes = self.passes.lock().unwrap();
+        passes.runs += 1;
+        if !succeeded {
+            passes.failures += 1;
+        }
+    }
+
+    /// Keep the counts of a successful pass. Conflict copies appear when the
+    /// pass makes them or downloads them from another machine, and disappear
+    /// when the user resolves them on any machine; the folder is not walked.
+    fn pass_finished(&self, outcome: &PassOutcome) {
+        let folder = &self.root.path;
+        let mut state = self.state.lock().unwrap();
+        state.items_synced =
+            outcome.uploaded.len() + outcome.downloaded.len() + outcome.removed.len();
+        state.items_pending = outcome.pending_uploads;
+
+        let downloaded = outcome
+            .downloaded
+            .iter()
+            .filter_map(|path| conflicts::parse(folder, Path::new(path)));
+        let mut found: Vec<_> = std::mem::take(&mut state.conflicts)
+            .into_iter()
+            .chain(outcome.conflicts.iter().cloned())
+            .chain(downloaded)
+            .filter(|conflict| folder.join(&conflict.copy).exists())
+            .collect();
+        conflicts::sort(&mut found);
+        state.conflicts = found;
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().status == SyncStatus::Paused
+    }
+
+    /// Paused by the user rather than held for a reason
+    fn is_paused_by_user(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.status == SyncStatus::Paused && state.paused_by.is_none()
+    }
+
+    fn is_running(&self) -> bool {
+        self.task.lock().unwrap().is_some()
+    }
+
+    fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.status = SyncStatus::Paused;
+        state.paused_by = None;
+    }
+
+    fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.status == SyncStatus::Paused || state.status == SyncStatus::Error {
+            state.status = SyncStatus::Idle;
+            state.error_message = None;
+            state.paused_by = None;
+            // Catch up on whatever changed while paused
+            self.trigger.notify_one();
+        }
+    }
+
+    /// Pause for `reason` unless already paused. A hold for the schedule
+    /// gives way, as it is re-applied once the other reason is gone.
+    fn hold(&self, reason: PauseReason) {
+        let mut state = self.state.lock().unwrap();
+        if state.status != SyncStatus::Paused || state.paused_by.is_some_and(|r| r.is_schedule()) {
+            state.status = SyncStatus::Paused;
+            state.paused_by = Some(reason);
+        }
+    }
+
+    /// Undo `hold`; a pause by the user or for another reason stays
+    fn release(&self, reason: PauseReason) {
+        if self.state.lock().unwrap().paused_by == Some(reason) {
+            self.resume();
         }
     }
-}
 
-impl RetryPolicy {
-    fn calculate_delay(&self, attempt: usize) -> Duration {
-        let delay_secs = self.base_delay.as_secs() * 2_u64.pow(attempt as u32);
-        let delay = Duration::from_secs(delay_secs);
-        std::cmp::min(delay, self.max_delay)
+    /// Undo a hold for the sync hours or a timed pause. Only called by the
+    /// sync loop, which runs a pass right after.
+    fn release_schedule(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.paused_by.is_some_and(|r| r.is_schedule()) {
+            state.status = SyncStatus::Idle;
+            state.paused_by = None;
+        }
+    }
+
+    fn wake(&self) {
+        if !self.is_paused() {
+            self.trigger.notify_one();
+        }
+    }
+
+    async fn stop(&self) {
+        // Cancel via context
+        if let Some(ctx) = self.context.lock().unwrap().take() {
+            ctx.cancel();
+            debug!("Cancellation signal sent for {}", self.root.path.display());
+        }
+
+        // Wait for sync task with timeout
+        let handle = self.task.lock().unwrap().take();
+        if let Some(handle) = handle {
+            info!(
+                "Waiting for sync task of {} to complete",
+                self.root.path.display()
+            );
+
+            // Give it 1 second to finish gracefully - if called from quit handler,
+            // the process will force exit after 500ms anyway
+            let timeout = Duration::from_millis(1000);
+            match tokio::time::timeout(timeout, handle).await {
+                Ok(Ok(())) => info!("Sync task completed gracefully"),
+                Ok(Err(e)) => warn!("Sync task panicked: {:?}", e),
+                Err(_) => {
+                    warn!(
+                        "Sync task did not complete within {:?}, cancellation signal sent",
+                        timeout
+                    );
+                }
+            }
+        }
+
+        // A paused folder stays paused across restarts of the sync loops
+        let mut state = self.state.lock().unwrap();
+        if state.status != SyncStatus::Paused {
+            state.status = SyncStatus::Idle;
+        }
     }
 }
 
 impl SyncManager {
     pub fn new(auth: Arc<AuthManager>, config: Arc<Config>) -> Self {
-        SyncManager {
+        let folders = {
+            let settings = config.settings();
+            let settings = settings.lock().unwrap();
+            settings
+                .roots()
+                .into_iter()
+                .map(|root| Arc::new(FolderSync::new(root, &settings.ignore_patterns)))
+                .collect()
+        };
+
+        let manager = SyncManager {
             auth,
             config,
-            state: Arc::new(Mutex::new(SyncState::default())),
-            sync_context: Arc::new(RwLock::new(None)),
-            sync_task: Arc::new(Mutex::new(None)),
-            retry_policy: RetryPolicy::default(),
+            folders: Mutex::new(folders),
+            connectivity: Arc::new(ConnectivityMonitor::new(
+                &crate::config::settings::Settings::get_sync_endpoint(),
+            )),
+            bandwidth: Arc::default(),
+            hooks: Arc::default(),
+        };
+        manager.restore_pauses();
+        manager
+    }
+
+    /// Pause the folders that were paused when the agent last stopped, so a
+    /// restart or update does not resume syncing behind the user's back
+    fn restore_pauses(&self) {
+        let schedule = self.config.settings().lock().unwrap().schedule.clone();
+        let hold = schedule::hold_at(&schedule, chrono::Local::now());
+
+        for folder in self.folders() {
+            if schedule.paused || schedule.paused_folders.contains(&folder.root.id) {
+                folder.pause();
+            } else if let Some(reason) = hold {
+                folder.hold(reason);
+            }
         }
     }
 
-    pub fn state(&self) -> Arc<Mutex<SyncState>> {
-        Arc::clone(&self.state)
+    /// Change the saved pause state, logging rather than failing when the
+    /// settings cannot be written
+    fn save_pauses(&self, update: impl FnOnce(&mut ScheduleSettings)) {
+        if let Err(e) = self.config.update_settings(|s| update(&mut s.schedule)) {
+            warn!("Failed to save the pause state: {e}");
+        }
     }
 
-    pub async fn start(&self) -> Result<()> {
+    /// Overall state of all folders, for places that show a single status
+    pub fn state(&self) -> SyncState {
+        SyncState::combine(&self.folder_states())
+    }
+
+    /// Live state of each synced folder
+    pub fn folder_states(&self) -> Vec<FolderState> {
+        self.folders().iter().map(|f| f.folder_state()).collect()
+    }
+
+    fn folders(&self) -> Vec<Arc<FolderSync>> {
+        self.folders.lock().unwrap().clone()
+    }
+
+    /// Match the folder list to the settings, keeping state of unchanged folders
+    fn refresh_folders(&self) -> Vec<Arc<FolderSync>> {
+        let (roots, ignore_patterns) = {
+            let settings = self.config.settings();
+            let settings = settings.lock().unwrap();
+            (settings.roots(), settings.ignore_patterns.clone())
+        };
+        let mut folders = self.folders.lock().unwrap();
+
+        let refreshed: Vec<_> = roots
+            .into_iter()
+            .map(|root| {
+                folders
+                    .iter()
+                    .find(|f| {
+                        f.root.id == root.id
+                            && f.root.path == root.path
+                            && f.root.remote_folder == root.remote_folder
+                    })
+                    .cloned()
+                    .unwrap_or_else(|| Arc::new(FolderSync::new(root, &ignore_patterns)))
+            })
+            .collect();
+
+        *folders = refreshed.clone();
+        refreshed
+    }
+
+    /// Folder synced from `path`
+    fn find_folder(&self, path: &Path) -> Result<Arc<FolderSync>> {
+        let wanted = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.folders()
+            .into_iter()
+            .find(|f| f.root.path == path || f.root.path.canonicalize().is_ok_and(|p| p == wanted))
+            .ok_or_else(|| {
+                SyncError::InvalidConfiguration(format!("Not a synced folder: {}", path.display()))
+            })
+    }
+
+    /// Ensure there is a session and at least one folder to sync
+    fn check_ready(&self) -> Result<Vec<SyncRoot>> {
         // Check authentication
         if !self.auth.is_authenticated() {
             return Err(SyncError::AuthenticationRequired);
         }
 
-        // Check if recipes directory is set
-        let recipes_dir = self.config.settings().lock().unwrap().recipes_dir.clone();
-        if recipes_dir.is_none() {
+        // Check if a recipes directory is set
+        let roots = self.config.settings().lock().unwrap().roots();
+        if roots.is_empty() {
             return Err(SyncError::InvalidConfiguration(
                 "Recipes directory not configured".to_string(),
             ));
         }
+        Ok(roots)
+    }
+
+    /// Start a sync loop for every configured folder, restarting running ones
+    pub async fn start(&self) -> Result<()> {
+        self.check_ready()?;
+
+        // Never run two loops for the same folder
+        for folder in self.folders() {
+            folder.stop().await;
+        }
 
+        let folders = self.refresh_folders();
+        self.restore_pauses();
+        let roots: Vec<SyncRoot> = folders.iter().map(|f| f.root.clone()).collect();
+        for folder in folders {
+            let scope = RemoteScope::for_root(&folder.root, &roots);
+            self.spawn_folder_loop(folder, scope);
+        }
+        self.apply_metered_policy();
+
+        Ok(())
+    }
+
+    fn spawn_folder_loop(&self, folder: Arc<FolderSync>, scope: RemoteScope) {
         // Create sync context with listener for status callbacks
         let sync_context = SyncContext::new();
-        let listener = Arc::new(SyncManagerListener::new(Arc::clone(&self.state)));
+        let listener = Arc::new(SyncManagerListener::new(Arc::clone(&folder.state)));
         sync_context.set_listener(listener);
 
         // Store context
-        *self.sync_context.write().await = Some(Arc::clone(&sync_context));
+        *folder.context.lock().unwrap() = Some(Arc::clone(&sync_context));
 
-        // Start sync loop
-        let state = Arc::clone(&self.state);
-        let auth = Arc::clone(&self.auth);
-        let config = Arc::clone(&self.config);
-        let recipes_dir = recipes_dir.unwrap();
-        let sync_task_clone = Arc::clone(&self.sync_task);
-        let retry_policy = self.retry_policy.clone();
+        let handle = tokio::spawn(run_folder_loop(
+            Arc::clone(&folder),
+            Arc::clone(&self.auth),
+            Arc::clone(&self.config),
+            scope,
+            Arc::clone(&self.connectivity),
+            Arc::clone(&self.bandwidth),
+            Arc::clone(&self.hooks),
+            sync_context,
+        ));
 
-        // Get cancellation token from context
-        let token = sync_context.token();
+        // Store the task handle
+        *folder.task.lock().unwrap() = Some(handle);
+    }
 
-        let handle = tokio::spawn(async move {
-            let interval_secs = config.settings().lock().unwrap().sync_interval_secs;
-            let mut interval = interval(Duration::from_secs(interval_secs));
-            let mut last_success = std::time::Instant::now();
-            let mut consecutive_failures = 0;
+    pub fn pause(&self) {
+        self.save_pauses(|s| s.paused = true);
+        for folder in self.folders() {
+            folder.pause();
+        }
+    }
 
-            // Run first sync immediately instead of waiting for the interval
-            let mut first_sync = true;
+    /// Resume every folder. Ends a timed pause early; sync hours still apply.
+    pub fn resume(&self) {
+        self.save_pauses(|s| {
+            s.paused = false;
+            s.paused_until = None;
+            s.paused_folders.clear();
+        });
+        for folder in self.folders() {
+            folder.resume();
+        }
+    }
 
-            loop {
-                // Check cancellation before each iteration
-                if token.is_cancelled() {
-                    info!("Sync loop cancelled");
+    /// Pause every folder until `until`; syncing resumes by itself then.
+    /// Replaces a pause of all folders, folders paused on their own stay so.
+    pub fn pause_until(&self, until: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        let paused_folders = {
+            let settings = self.config.settings();
+            let settings = settings.lock().unwrap();
+            settings.schedule.paused_folders.clone()
+        };
+        self.config.update_settings(|s| {
+            s.schedule.paused = false;
+            s.schedule.paused_until = Some(until);
+        })?;
+
+        for folder in self.folders() {
+            if paused_folders.contains(&folder.root.id) {
+                continue;
+            }
+            if folder.is_paused_by_user() {
+                folder.resume();
+            }
+            folder.hold(PauseReason::Timed { until });
+        }
+        info!("Sync paused until {}", until.with_timezone(&chrono::Local));
+        Ok(())
+    }
+
+    /// Make every sync loop re-check the sync hours and timed pause, e.g.
+    /// after the settings changed
+    pub fn apply_schedule(&self) {
+        for folder in self.folders() {
+            folder.trigger.notify_one();
+        }
+    }
+
+    /// The connection became metered or stopped being metered
+    pub fn set_metered(&self, metered: bool) {
+        if self.bandwidth.set_metered(metered) {
+            self.apply_metered_policy();
+        }
+    }
+
+    /// Pause, throttle or release the folders as the settings say for the
+    /// current connection
+    pub fn apply_metered_policy(&self) {
+        let settings = self.config.settings().lock().unwrap().bandwidth.clone();
+        self.bandwidth.configure(&settings);
+
+        let hold = self.bandwidth.is_metered() && settings.on_metered == MeteredPolicy::Pause;
+        for folder in self.folders() {
+            if hold {
+                folder.hold(PauseReason::MeteredNetwork);
+            } else {
+                folder.release(PauseReason::MeteredNetwork);
+            }
+        }
+    }
+
+    /// Pause a single folder, leaving the others syncing
+    pub fn pause_folder(&self, path: &Path) -> Result<()> {
+        let folder = self.find_folder(path)?;
+        let id = folder.root.id.clone();
+        self.save_pauses(|s| {
+            if !s.paused_folders.contains(&id) {
+                s.paused_folders.push(id);
+            }
+        });
+        folder.pause();
+        Ok(())
+    }
+
+    pub fn resume_folder(&self, path: &Path) -> Result<()> {
+        let folder = self.find_folder(path)?;
+        // A pause of all folders turns into pauses of the other ones
+        let others: Vec<String> = self
+            .folders()
+            .iter()
+            .map(|f| f.root.id.clone())
+            .filter(|id| *id != folder.root.id)
+            .collect();
+        self.save_pauses(|s| {
+            if s.paused {
+                s.paused = false;
+                s.paused_folders = others;
+            } else {
+                s.paused_folders.retain(|id| *id != folder.root.id);
+            }
+        });
+        folder.resume();
+        Ok(())
+    }
+
+    /// Run a pass soon in every running folder that is not paused
+    pub fn wake(&self) {
+        for folder in self.folders() {
+            folder.wake();
+        }
+    }
+
+    /// Run a sync pass right away, starting the sync loops if they are not running
+    pub async fn sync_now(&self) -> Result<()> {
+        let folders = self.folders();
+        if !folders.is_empty() && folders.iter().all(|f| f.is_paused()) {
+            return Err(SyncError::Other("Sync is paused".to_string()));
+        }
+
+        if folders.iter().any(|f| f.is_running()) {
+            self.wake();
+            Ok(())
+        } else {
+            self.start().await
+        }
+    }
+
+    /// Run a single sync pass over every folder in the foreground, without
+    /// starting the sync loops. Stops at the first folder that fails.
+    pub async fn run_once(&self) -> Result<Vec<(SyncRoot, PassOutcome)>> {
+        let result = self.run_passes_once().await;
+        // Hooks run in the background; the process would end them on exit
+        self.hooks.wait().await;
+        result
+    }
+
+    async fn run_passes_once(&self) -> Result<Vec<(SyncRoot, PassOutcome)>> {
+        let roots = self.check_ready()?;
+        let folders = self.refresh_folders();
+
+        // Metered connections throttle or pause a single run like the sync loops
+        if let Some(metered) = super::metered::system_probe().and_then(|probe| probe.is_metered()) {
+            self.bandwidth.set_metered(metered);
+        }
+        self.apply_metered_policy();
+        let on_metered = self.config.settings().lock().unwrap().bandwidth.on_metered;
+        if self.bandwidth.is_metered() && on_metered == MeteredPolicy::Pause {
+            return Err(SyncError::Other(
+                "Not syncing on a metered connection (on_metered is pause)".to_string(),
+            ));
+        }
+        let mut outcomes = Vec::new();
+
+        for folder in &folders {
+            let recipes_dir = &folder.root.path;
+            if !recipes_dir.is_dir() {
+                return Err(SyncError::InvalidConfiguration(format!(
+                    "Recipes directory does not exist: {}",
+                    recipes_dir.display()
+                )));
+            }
+
+            let context = SyncContext::new();
+            context.set_listener(Arc::new(SyncManagerListener::new(Arc::clone(
+                &folder.state,
+            ))));
+
+            let mut engine = None;
+            let started_at = chrono::Utc::now();
+            let result = crate::logging::sync_run(run_sync_pass(
+                &mut engine,
+                &self.auth,
+                &self.config,
+                folder,
+                &RemoteScope::for_root(&folder.root, &roots),
+                &self.bandwidth,
+                &context,
+            ))
+            .await;
+            record_run(&self.config, &folder.root, started_at, &result);
+            folder.count_pass(result.is_ok());
+            let outcome = match result {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    fire_error_hook(&self.hooks, &self.config, &folder.root, &e);
+                    return Err(e);
+                }
+            };
+            folder.pass_finished(&outcome);
+            announce_pass(&self.hooks, &self.config, &folder.root, &outcome);
+            outcomes.push((folder.root.clone(), outcome));
+        }
+
+        Ok(outcomes)
+    }
+
+    pub async fn stop(&self) -> Result<()> {
+        info!("Stopping sync manager");
+
+        for folder in self.folders() {
+            folder.stop().await;
+        }
+        // Drop folders that were removed from the settings
+        self.refresh_folders();
+
+        info!("Sync manager stopped");
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self.state().status,
+            SyncStatus::Syncing | SyncStatus::Idle | SyncStatus::Offline
+        )
+    }
+}
+
+/// Sync loop of one folder; runs until the folder's context is cancelled
+#[allow(clippy::too_many_arguments)]
+async fn run_folder_loop(
+    folder: Arc<FolderSync>,
+    auth: Arc<AuthManager>,
+    config: Arc<Config>,
+    scope: RemoteScope,
+    connectivity: Arc<ConnectivityMonitor>,
+    bandwidth: Arc<Bandwidth>,
+    hooks: Arc<Hooks>,
+    sync_context: Arc<SyncContext>,
+) {
+    let state = &folder.state;
+    let trigger = &folder.trigger;
+    // Get cancellation token from context
+    let token = sync_context.token();
+
+    // Local edits wake the loop through the watcher; it lives as long as the loop
+    let _watcher = match RecipesWatcher::start(&folder.root.path, Arc::clone(trigger)) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            warn!("File watcher unavailable, falling back to periodic sync: {e}");
+            None
+        }
+    };
+
+    let fallback_interval =
+        Duration::from_secs(config.settings().lock().unwrap().fallback_interval_secs);
+    let mut engine: Option<SyncEngine> = None;
+    // Only long-poll the server once a pass has shown it is reachable
+    let mut remote_poll_ok = false;
+    let mut last_success = std::time::Instant::now();
+    let mut consecutive_failures = 0;
+
+    // Run first sync immediately instead of waiting for a trigger
+    let mut first_sync = true;
+
+    loop {
+        // Check cancellation before each iteration
+        if token.is_cancelled() {
+            info!("Sync loop for {} cancelled", folder.root.path.display());
+            break;
+        }
+
+        // Sleep until something may have changed on either side
+        if !first_sync {
+            let poller = engine.as_ref().filter(|_| remote_poll_ok);
+            let remote_change = async move {
+                match poller {
+                    Some(engine) => engine.wait_remote_change().await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = trigger.notified() => debug!("Sync triggered by local change or request"),
+                result = remote_change => {
+                    if let Err(e) = result {
+                        debug!("Remote change notifications unavailable: {e}");
+                        remote_poll_ok = false;
+                    }
+                }
+                _ = tokio::time::sleep(fallback_interval) => debug!("Fallback sync interval elapsed"),
+                _ = token.cancelled() => {
+                    info!("Sync manager shutting down");
                     break;
                 }
+            }
+        }
+        first_sync = false;
 
-                // For first sync, skip the interval tick and run immediately
-                if !first_sync {
+        // Sync hours and timed pauses are checked before every pass
+        let schedule = config.settings().lock().unwrap().schedule.clone();
+        match schedule::hold_at(&schedule, chrono::Local::now()) {
+            Some(reason) => {
+                folder.hold(reason);
+                if state.lock().unwrap().paused_by == Some(reason) {
+                    let left = reason
+                        .until()
+                        .and_then(|until| (until - chrono::Utc::now()).to_std().ok())
+                        .unwrap_or_default();
                     tokio::select! {
-                        _ = interval.tick() => {},
+                        _ = tokio::time::sleep(left) => debug!("Schedule pause for {} over", folder.root.path.display()),
+                        _ = trigger.notified() => debug!("Schedule re-checked on request"),
                         _ = token.cancelled() => {
                             info!("Sync manager shutting down");
                             break;
                         }
                     }
+                    // Check again right away rather than waiting for a change
+                    first_sync = true;
+                    continue;
                 }
-                first_sync = false;
+            }
+            None => folder.release_schedule(),
+        }
 
-                // Check if we should sync
-                let should_sync = {
-                    let st = state.lock().unwrap();
-                    st.status != SyncStatus::Paused && auth.is_authenticated()
-                };
+        // Check if we should sync
+        let should_sync = {
+            let st = state.lock().unwrap();
+            st.status != SyncStatus::Paused && auth.is_authenticated()
+        };
 
-                if !should_sync {
-                    continue;
-                }
+        if !should_sync {
+            continue;
+        }
 
-                // Reset consecutive failures if enough time has passed since last success
-                // This handles the case where system woke from sleep or network recovered
-                let time_since_success = std::time::Instant::now().duration_since(last_success);
-                if time_since_success > retry_policy.max_delay * 2 && consecutive_failures > 0 {
-                    info!("Resetting retry counter after extended idle period ({:?} since last success)", time_since_success);
-                    consecutive_failures = 0;
-                    // Clear error state to allow retry
-                    state.lock().unwrap().clear_error();
+        // Read on every pass so changed retry settings apply without a restart
+        let retry_policy = RetryPolicy::from_settings(&config.settings().lock().unwrap().retry);
+
+        // Reset consecutive failures if enough time has passed since last success
+        // This handles the case where system woke from sleep or network recovered
+        let time_since_success = std::time::Instant::now().duration_since(last_success);
+        if time_since_success > retry_policy.max_delay * 2 && consecutive_failures > 0 {
+            info!(
+                "Resetting retry counter after extended idle period ({:?} since last success)",
+                time_since_success
+            );
+            consecutive_failures = 0;
+            // Clear error state to allow retry
+            state.lock().unwrap().clear_error();
+        }
+
+        // Retry loop for sync attempts; their log lines share a run id
+        crate::logging::sync_run(async {
+            let mut retry_attempt = 0;
+            let mut last_delay = None;
+            loop {
+                // Check cancellation before retry
+                if token.is_cancelled() {
+                    info!("Sync cancelled during retry");
+                    break;
                 }
 
-                // Retry loop for sync attempts
-                let mut retry_attempt = 0;
-                loop {
-                    // Check cancellation before retry
-                    if token.is_cancelled() {
-                        info!("Sync cancelled during retry");
+                let started_at = chrono::Utc::now();
+                let sync_result = run_sync_pass(
+                    &mut engine,
+                    &auth,
+                    &config,
+                    &folder,
+                    &scope,
+                    &bandwidth,
+                    &sync_context,
+                )
+                .await;
+                record_run(&config, &folder.root, started_at, &sync_result);
+                folder.count_pass(sync_result.is_ok());
+
+                match sync_result {
+                    Ok(outcome) => {
+                        debug!("Sync completed successfully: {outcome:?}");
+                        folder.pass_finished(&outcome);
+                        announce_pass(&hooks, &config, &folder.root, &outcome);
+                        // Success - reset counters and update last success time
+                        connectivity.report_online();
+                        last_success = std::time::Instant::now();
+                        consecutive_failures = 0;
+                        remote_poll_ok = true;
                         break;
                     }
+                    Err(e) => {
+                        error!("Sync failed: {e}");
 
-                    // Perform sync with cancellation support
-                    let sync_result = perform_sync_with_context(
-                        &auth,
-                        &config,
-                        &recipes_dir,
-                        Arc::clone(&sync_context),
-                    )
-                    .await;
-
-                    match sync_result {
-                        Ok(()) => {
-                            debug!("Sync completed successfully");
-                            // Success - reset counters and update last success time
-                            last_success = std::time::Instant::now();
-                            consecutive_failures = 0;
-                            break;
-                        }
-                        Err(e) => {
-                            error!("Sync failed: {e}");
-
-                            // Check if error is retriable
-                            let is_retriable =
-                                matches!(e, SyncError::Network(_) | SyncError::Other(_));
+                        match RetryDecision::for_error(&e) {
+                            // Retries cannot succeed without a connection; wait for
+                            // the endpoint to answer instead, then try again at once
+                            RetryDecision::WaitOnline => {
+                                {
+                                    let mut st = state.lock().unwrap();
+                                    st.set_offline();
+                                    st.last_error = Some(e.to_string());
+                                }
+                                connectivity.report_offline();
 
-                            if !is_retriable {
-                                // Non-retriable error - update state and break
-                                let mut st = state.lock().unwrap();
-                                match e {
-                                    SyncError::AuthenticationRequired => {
-                                        st.set_error("Authentication required".to_string());
-                                        // Clear session
-                                        let _ = auth.logout();
+                                tokio::select! {
+                                    _ = connectivity.wait_online() => {
+                                        let path = folder.root.path.display();
+                                        info!("Connection is back, syncing {path}");
+                                    }
+                                    _ = trigger.notified() => {
+                                        debug!("Sync requested while offline");
+                                    }
+                                    _ = token.cancelled() => {
+                                        info!("Sync cancelled while offline");
+                                        break;
                                     }
-                                    _ => st.set_error(e.to_string()),
                                 }
+                                if folder.is_paused() {
+                                    break;
+                                }
+                                continue;
+                            }
+                            RetryDecision::SignOut => {
+                                state
+                                    .lock()
+                                    .unwrap()
+                                    .set_error("Authentication required".to_string());
+                                // Clear session
+                                let _ = auth.logout();
+                                fire_error_hook(&hooks, &config, &folder.root, &e);
+                                consecutive_failures += 1;
+                                break;
+                            }
+                            RetryDecision::GiveUp => {
+                                state.lock().unwrap().set_error(e.to_string());
+                                fire_error_hook(&hooks, &config, &folder.root, &e);
                                 consecutive_failures += 1;
                                 break;
                             }
+                            RetryDecision::Backoff => {}
+                        }
 
-                            // Check if we should retry
-                            if retry_attempt >= retry_policy.max_retries {
-                                error!("Sync failed after {} retries: {}", retry_attempt, e);
-                                state.lock().unwrap().set_error(format!(
+                        // Check if we should retry
+                        if retry_attempt >= retry_policy.max_retries {
+                            error!("Sync failed after {} retries: {}", retry_attempt, e);
+                            {
+                                let mut st = state.lock().unwrap();
+                                st.set_error(format!(
                                     "Sync failed after {} retries",
                                     retry_attempt
                                 ));
-                                consecutive_failures += 1;
-                                break;
+                                // Keep the underlying cause for the status command
+                                st.last_error = Some(e.to_string());
                             }
+                            fire_error_hook(&hooks, &config, &folder.root, &e);
+                            consecutive_failures += 1;
+                            break;
+                        }
 
-                            // Calculate backoff delay
-                            let delay = retry_policy.calculate_delay(retry_attempt);
-                            warn!(
-                                "Sync failed (attempt {}/{}), retrying in {:?}: {}",
-                                retry_attempt + 1,
-                                retry_policy.max_retries,
-                                delay,
-                                e
-                            );
-
-                            retry_attempt += 1;
-
-                            // Wait with cancellation check
-                            tokio::select! {
-                                _ = tokio::time::sleep(delay) => {},
-                                _ = token.cancelled() => {
-                                    info!("Retry cancelled during backoff");
-                                    break;
-                                }
+                        let delay = retry_policy.next_delay(last_delay);
+                        last_delay = Some(delay);
+                        warn!(
+                            "Sync failed (attempt {}/{}), retrying in {:?}: {}",
+                            retry_attempt + 1,
+                            retry_policy.max_retries,
+                            delay,
+                            e
+                        );
+
+                        retry_attempt += 1;
+                        folder.passes.lock().unwrap().retries += 1;
+                        let retry_at = chrono::Utc::now()
+                            + chrono::Duration::from_std(delay).unwrap_or_default();
+                        state
+                            .lock()
+                            .unwrap()
+                            .set_retrying(retry_attempt, e.to_string(), retry_at);
+
+                        // Wait with cancellation check
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => {},
+                            _ = token.cancelled() => {
+                                info!("Retry cancelled during backoff");
+                                break;
                             }
                         }
                     }
                 }
             }
+        })
+        .await;
+    }
 
-            // Clear the task handle when done
-            *sync_task_clone.lock().unwrap() = None;
-        });
-
-        // Store the task handle
-        *self.sync_task.lock().unwrap() = Some(handle);
+    // Clear the task handle when done
+    *folder.task.lock().unwrap() = None;
+}
 
-        Ok(())
+/// Hooks for what a successful pass did: one run for the pass when files
+/// moved, and one per downloaded file and per conflict
+fn fire_pass_hooks(hooks: &Hooks, config: &Config, root: &SyncRoot, outcome: &PassOutcome) {
+    if outcome.is_empty() && outcome.conflicts.is_empty() {
+        return;
     }
+    let settings = config.settings().lock().unwrap().hooks.clone();
+    let folder = || root.path.clone();
 
-    pub fn pause(&self) {
-        self.state.lock().unwrap().status = SyncStatus::Paused;
+    for path in &outcome.downloaded {
+        hooks.fire(
+            &settings,
+            HookEvent::FileDownloaded {
+                folder: folder(),
+                path: path.clone(),
+            },
+        );
     }
-
-    pub fn resume(&self) {
-        let mut state = self.state.lock().unwrap();
-        if state.status == SyncStatus::Paused || state.status == SyncStatus::Error {
-            state.status = SyncStatus::Idle;
-            state.error_message = None;
-        }
+    for conflict in &outcome.conflicts {
+        hooks.fire(
+            &settings,
+            HookEvent::Conflict {
+                folder: folder(),
+                path: conflict.path.clone(),
+                copy: conflict.copy.clone(),
+                host: conflict.host.clone(),
+            },
+        );
     }
+    if !outcome.is_empty() {
+        hooks.fire(
+            &settings,
+            HookEvent::SyncComplete {
+                folder: folder(),
+                uploaded: outcome.uploaded.clone(),
+                downloaded: outcome.downloaded.clone(),
+                removed: outcome.removed.clone(),
+            },
+        );
+    }
+}
 
-    pub async fn stop(&self) -> Result<()> {
-        info!("Stopping sync manager");
-
-        // Cancel via context
-        if let Some(ctx) = self.sync_context.read().await.as_ref() {
-            ctx.cancel();
-            debug!("Cancellation signal sent");
-        }
-
-        // Wait for sync task with timeout
-        let handle = self.sync_task.lock().unwrap().take();
-        if let Some(handle) = handle {
-            info!("Waiting for sync task to complete");
-
-            // Give it 1 second to finish gracefully - if called from quit handler,
-            // the process will force exit after 500ms anyway
-            let timeout = Duration::from_millis(1000);
-            match tokio::time::timeout(timeout, handle).await {
-                Ok(Ok(())) => info!("Sync task completed gracefully"),
-                Ok(Err(e)) => warn!("Sync task panicked: {:?}", e),
-                Err(_) => {
-                    warn!(
-                        "Sync task did not complete within {:?}, cancellation signal sent",
-                        timeout
-                    );
-                }
-            }
-        }
-
-        // Clear context
-        *self.sync_context.write().await = None;
+fn fire_error_hook(hooks: &Hooks, config: &Config, root: &SyncRoot, e: &SyncError) {
+    let settings = config.settings().lock().unwrap().hooks.clone();
+    hooks.fire(
+        &settings,
+        HookEvent::Error {
+            folder: root.path.clone(),
+            message: e.to_string(),
+        },
+    );
+}
 
-        self.state.lock().unwrap().status = SyncStatus::Idle;
-        info!("Sync manager stopped");
-        Ok(())
+/// Tell the user and their hooks what a successful pass did, in the sync
+/// loops and single runs alike
+fn announce_pass(hooks: &Hooks, config: &Config, root: &SyncRoot, outcome: &PassOutcome) {
+    if config.settings().lock().unwrap().show_notifications {
+        notify_conflicts(&outcome.conflicts);
     }
+    fire_pass_hooks(hooks, config, root, outcome);
+}
 
-    pub fn is_running(&self) -> bool {
-        let state = self.state.lock().unwrap();
-        matches!(
-            state.status,
-            SyncStatus::Syncing | SyncStatus::Idle | SyncStatus::Offline
-        )
+/// One desktop notification per conflict found in a pass
+fn notify_conflicts(found: &[Conflict]) {
+    for conflict in found {
+        let message = format!(
+            "{} was also changed on another device. This computer's version was kept as {}.",
+            conflict.path.display(),
+            conflict.copy.display()
+        );
+        if let Err(e) = crate::notifications::show_notification("Sync conflict", &message) {
+            warn!("Failed to show conflict notification: {e}");
+        }
     }
 }
 
-async fn perform_sync_with_context(
+/// Run one sync pass, (re)creating the engine when the session token changed
+async fn run_sync_pass(
+    engine: &mut Option<SyncEngine>,
     auth: &AuthManager,
     config: &Config,
-    recipes_dir: &Path,
-    context: Arc<cooklang_sync_client::SyncContext>,
-) -> Result<()> {
+    folder: &FolderSync,
+    scope: &RemoteScope,
+    bandwidth: &Arc<Bandwidth>,
+    context: &SyncContext,
+) -> Result<PassOutcome> {
+    let root = &folder.root;
+    // Short-lived access tokens may have expired since the last pass
+    if let Err(e) = auth.refresh_if_needed().await {
+        warn!("Could not refresh session before sync: {e}");
+    }
+
     // Get current session
     let session = auth
         .get_session()
         .ok_or(SyncError::AuthenticationRequired)?;
 
-    let namespace_id = extract_uid_from_jwt(&session.jwt);
+    // Token refreshes need a new remote client
+    if engine.as_ref().is_none_or(|e| e.jwt() != session.jwt) {
+        let namespace_id = extract_uid_from_jwt(&session.jwt);
+        let sync_endpoint = crate::config::settings::Settings::get_sync_endpoint();
+        let db_path = config.paths().root_database_file(&root.id);
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
 
-    // Get config settings
-    let sync_endpoint = crate::config::settings::Settings::get_sync_endpoint();
+        *engine = Some(SyncEngine::new(
+            &root.path,
+            &db_path,
+            &sync_endpoint,
+            &session.jwt,
+            namespace_id,
+            scope.clone(),
+        )?);
+    }
 
-    // Get the db path
-    let db_path = config.paths().database_file.clone();
+    // Read on every pass so direction and ignore changes apply without
+    // restarting the loop
+    let direction = current_direction(config, root);
+    let (ignore_patterns, bandwidth_settings) = {
+        let settings = config.settings();
+        let settings = settings.lock().unwrap();
+        (settings.ignore_patterns.clone(), settings.bandwidth.clone())
+    };
+    bandwidth.configure(&bandwidth_settings);
+    info!(
+        "Starting sync for directory: {} ({direction})",
+        root.path.display()
+    );
 
-    // Perform full sync (upload and download)
-    info!("Starting sync for directory: {}", recipes_dir.display());
+    let engine = engine.as_mut().expect("engine was just created");
+    engine.reload_ignore_rules(&ignore_patterns);
+    engine.set_bandwidth(Arc::clone(bandwidth));
+    let progress = Arc::new(SyncManagerListener::new(Arc::clone(&folder.state)));
+    engine
+        .run_pass(direction, context.listener(), Some(progress))
+        .await
+}
 
-    let recipes_dir_str = recipes_dir.to_string_lossy().to_string();
-    let db_path_str = db_path.to_string_lossy().to_string();
+/// The folder's direction as currently configured
+fn current_direction(config: &Config, root: &SyncRoot) -> SyncDirection {
+    config
+        .settings()
+        .lock()
+        .unwrap()
+        .roots()
+        .into_iter()
+        .find(|r| r.id == root.id)
+        .map_or(root.sync_direction, |r| r.sync_direction)
+}
 
-    cooklang_sync_client::run_async(
-        context,
-        &recipes_dir_str,
-        &db_path_str,
-        &sync_endpoint,
-        &session.jwt,
-        namespace_id,
-        false, // both download and upload
-    )
-    .await
-    .map_err(|e| match e {
-        cooklang_sync_client::errors::SyncError::Unauthorized => SyncError::AuthenticationRequired,
-        cooklang_sync_client::errors::SyncError::ConnectionInitError(err) => {
-            SyncError::Other(format!("Connection error: {err}"))
+/// Add a finished pass to the activity journal. A journal problem is logged
+/// but does not fail the sync.
+fn record_run(
+    config: &Config,
+    root: &SyncRoot,
+    started_at: chrono::DateTime<chrono::Utc>,
+    result: &Result<PassOutcome>,
+) {
+    let (files, error) = match result {
+        Ok(outcome) => {
+            let files = [
+                (FileAction::Uploaded, &outcome.uploaded),
+                (FileAction::Downloaded, &outcome.downloaded),
+                (FileAction::Removed, &outcome.removed),
+            ]
+            .into_iter()
+            .flat_map(|(action, paths)| {
+                paths.iter().map(move |path| FileActivity {
+                    path: path.clone(),
+                    action,
+                })
+            })
+            .chain(outcome.conflicts.iter().map(|c| FileActivity {
+                path: c.path.to_string_lossy().into_owned(),
+                action: FileAction::Conflict,
+            }))
+            .collect();
+            (files, None)
         }
-        _ => SyncError::Other(format!("Sync failed: {e:?}")),
-    })?;
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
 
-    info!("Sync completed successfully");
+    let run = SyncRun {
+        folder: root.path.clone(),
+        started_at,
+        finished_at: chrono::Utc::now(),
+        direction: current_direction(config, root),
+        files,
+        error,
+    };
 
-    Ok(())
+    let activity_file = config.paths().activity_file.clone();
+    if let Err(e) = ActivityJournal::open(&activity_file).and_then(|mut j| j.record(&run)) {
+        warn!("Failed to record sync activity: {e}");
+    }
 }
 
 #[cfg(test)]
@@ -347,17 +1066,108 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_retry_policy_delay_calculation() {
-        let policy = RetryPolicy::default();
+    fn test_conflicts_follow_pass_outcomes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let copy = "Soup.conflict-laptop-20240102T030405Z.cook";
+        std::fs::write(root.join(copy), "Add @salt.").unwrap();
+        let folder = FolderSync::new(
+            SyncRoot::new(root.to_path_buf(), "Shared").unwrap(),
+            &["drafts/".to_string()],
+        );
+        assert_eq!(folder.state.lock().unwrap().conflicts.len(), 1);
+
+        // Copies made elsewhere arrive with downloads
+        let downloaded = "drafts/Pasta.conflict-desktop-20240103T030405Z.cook";
+        std::fs::create_dir(root.join("drafts")).unwrap();
+        std::fs::write(root.join(downloaded), "Boil.").unwrap();
+        folder.pass_finished(&PassOutcome {
+            downloaded: vec![downloaded.to_string()],
+            ..Default::default()
+        });
+        let copies = |folder: &FolderSync| -> Vec<_> {
+            let state = folder.state.lock().unwrap();
+            state.conflicts.iter().map(|c| c.copy.clone()).collect()
+        };
+        assert_eq!(
+            copies(&folder),
+            vec![
+                Path::new(copy).to_path_buf(),
+                Path::new(downloaded).to_path_buf()
+            ]
+        );
+
+        // Resolved copies are gone from disk
+        std::fs::remove_file(root.join(copy)).unwrap();
+        folder.pass_finished(&PassOutcome::default());
+        assert_eq!(copies(&folder), vec![Path::new(downloaded).to_path_buf()]);
+    }
+
+    #[test]
+    fn test_metered_hold_leaves_user_pause_alone() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let folder = FolderSync::new(
+            SyncRoot::new(temp_dir.path().to_path_buf(), "Shared").unwrap(),
+            &[],
+        );
+        folder.state.lock().unwrap().status = SyncStatus::Idle;
+
+        folder.hold(PauseReason::MeteredNetwork);
+        assert!(folder.is_paused());
+        assert_eq!(
+            folder.state.lock().unwrap().status_text(),
+            "Paused: metered network"
+        );
+        folder.release(PauseReason::MeteredNetwork);
+        assert!(!folder.is_paused());
+
+        // Paused by the user before and after the connection became metered
+        folder.pause();
+        folder.hold(PauseReason::MeteredNetwork);
+        folder.release(PauseReason::MeteredNetwork);
+        assert!(folder.is_paused());
+        assert_eq!(folder.state.lock().unwrap().paused_by, None);
+    }
+
+    #[test]
+    fn test_schedule_hold_gives_way() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let folder = FolderSync::new(
+            SyncRoot::new(temp_dir.path().to_path_buf(), "Shared").unwrap(),
+            &[],
+        );
+        folder.state.lock().unwrap().status = SyncStatus::Idle;
+        let until = chrono::Utc::now() + chrono::Duration::minutes(30);
+
+        // A newer schedule hold replaces an older one, a metered hold both
+        folder.hold(PauseReason::OutsideSyncHours { until });
+        folder.hold(PauseReason::Timed { until });
+        assert_eq!(
+            folder.state.lock().unwrap().paused_by,
+            Some(PauseReason::Timed { until })
+        );
+        folder.hold(PauseReason::MeteredNetwork);
+        folder.release_schedule();
+        assert_eq!(
+            folder.state.lock().unwrap().paused_by,
+            Some(PauseReason::MeteredNetwork)
+        );
 
-        // Test exponential backoff
-        assert_eq!(policy.calculate_delay(0), Duration::from_secs(5));
-        assert_eq!(policy.calculate_delay(1), Duration::from_secs(10));
-        assert_eq!(policy.calculate_delay(2), Duration::from_secs(20));
-        assert_eq!(policy.calculate_delay(3), Duration::from_secs(40));
+        // A user pause stays put
+        folder.pause();
+        folder.hold(PauseReason::Timed { until });
+        folder.release_schedule();
+        assert!(folder.is_paused());
+        assert_eq!(folder.state.lock().unwrap().paused_by, None);
 
-        // Test max delay cap
-        assert!(policy.calculate_delay(10) <= policy.max_delay);
+        folder.resume();
+        folder.hold(PauseReason::Timed { until });
+        assert_eq!(
+            folder.state.lock().unwrap().status_text(),
+            "Paused: 30m left"
+        );
+        folder.release_schedule();
+        assert!(!folder.is_paused());
     }
 
     #[tokio::test]
@@ -0,0 +1,182 @@
+//! Noticing metered connections such as phone hotspots.
+//!
+//! On Linux NetworkManager knows whether the primary connection is metered and
+//! exposes it on the system D-Bus. Other platforms have no probe yet, and an
+//! unknown answer counts as not metered. The reading is polled rather than
+//! subscribed to, which keeps the D-Bus side to a single property read.
+
+use log::info;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the connection is checked
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Source of the "is this connection metered" answer
+pub trait MeteredProbe: Send + Sync {
+    /// `None` when the answer is unknown
+    fn is_metered(&self) -> Option<bool>;
+}
+
+/// The probe for this platform, if there is one
+pub fn system_probe() -> Option<Arc<dyn MeteredProbe>> {
+    #[cfg(target_os = "linux")]
+    {
+        network_manager::NetworkManager::connect().map(|nm| Arc::new(nm) as Arc<dyn MeteredProbe>)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Poll `probe` every `interval` and call `on_change` whenever the connection
+/// becomes metered or stops being metered. Runs until the task is dropped.
+pub async fn watch(
+    probe: Arc<dyn MeteredProbe>,
+    interval: Duration,
+    on_change: impl Fn(bool) + Send + 'static,
+) {
+    let mut metered = false;
+
+    loop {
+        let reading = {
+            let probe = Arc::clone(&probe);
+            // D-Bus calls block
+            tokio::task::spawn_blocking(move || probe.is_metered())
+                .await
+                .ok()
+                .flatten()
+        };
+
+        let now = reading.unwrap_or(false);
+        if now != metered {
+            metered = now;
+            info!(
+                "Network connection is {}metered",
+                if metered { "" } else { "no longer " }
+            );
+            on_change(metered);
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod network_manager {
+    use super::MeteredProbe;
+    use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+    use dbus::blocking::SyncConnection;
+    use log::debug;
+    use std::time::Duration;
+
+    const BUS_NAME: &str = "org.freedesktop.NetworkManager";
+    const OBJECT_PATH: &str = "/org/freedesktop/NetworkManager";
+    const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+    pub struct NetworkManager {
+        connection: SyncConnection,
+    }
+
+    impl NetworkManager {
+        /// `None` without a system bus (containers, some servers)
+        pub fn connect() -> Option<Self> {
+            match SyncConnection::new_system() {
+                Ok(connection) => Some(Self { connection }),
+                Err(e) => {
+                    debug!("System D-Bus unavailable, metered connections not detected: {e}");
+                    None
+                }
+            }
+        }
+    }
+
+    impl MeteredProbe for NetworkManager {
+        fn is_metered(&self) -> Option<bool> {
+            let proxy = self
+                .connection
+                .with_proxy(BUS_NAME, OBJECT_PATH, CALL_TIMEOUT);
+            match proxy.get::<u32>(BUS_NAME, "Metered") {
+                Ok(value) => from_nm_metered(value),
+                Err(e) => {
+                    debug!("Could not read NetworkManager's metered state: {e}");
+                    None
+                }
+            }
+        }
+    }
+
+    /// NetworkManager's NMMetered: 0 unknown, 1 yes, 2 no, 3 guessed yes,
+    /// 4 guessed no
+    pub fn from_nm_metered(value: u32) -> Option<bool> {
+        match value {
+            1 | 3 => Some(true),
+            2 | 4 => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Stands in for NetworkManager with a scripted list of readings
+    struct ScriptedProbe {
+        readings: Mutex<Vec<Option<bool>>>,
+    }
+
+    impl MeteredProbe for ScriptedProbe {
+        fn is_metered(&self) -> Option<bool> {
+            let mut readings = self.readings.lock().unwrap();
+            if readings.len() > 1 {
+                readings.remove(0)
+            } else {
+                readings[0]
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_reports_changes_only() {
+        let probe = Arc::new(ScriptedProbe {
+            readings: Mutex::new(vec![
+                Some(false),
+                Some(true),
+                Some(true),
+                // Unknown counts as not metered
+                None,
+                Some(false),
+            ]),
+        });
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let task = tokio::spawn(watch(probe, Duration::from_millis(5), move |metered| {
+            let _ = tx.send(metered);
+        }));
+
+        let mut changes = Vec::new();
+        for _ in 0..2 {
+            let change = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await;
+            changes.push(change.unwrap().unwrap());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        task.abort();
+
+        assert_eq!(changes, vec![true, false]);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_network_manager_metered_values() {
+        use network_manager::from_nm_metered;
+        assert_eq!(from_nm_metered(1), Some(true));
+        assert_eq!(from_nm_metered(3), Some(true));
+        assert_eq!(from_nm_metered(4), Some(false));
+        assert_eq!(from_nm_metered(0), None);
+    }
+}
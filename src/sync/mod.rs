@@ -1,6 +1,20 @@
+pub mod activity;
+pub mod bandwidth;
+pub mod conflicts;
+pub mod connectivity;
+pub mod download_state;
+pub mod engine;
+pub mod hooks;
+pub mod ignore_rules;
 pub mod manager;
+pub mod metered;
+pub mod report;
+pub mod retry;
+pub mod schedule;
+pub mod scope;
 pub mod status;
 pub mod status_listener;
+pub mod watcher;
 
 #[cfg(test)]
 mod manager_test;
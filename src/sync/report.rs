@@ -0,0 +1,287 @@
+//! Machine-readable status report printed by `cook-sync status --json`.
+//!
+//! Monitoring scripts depend on this layout. Add new fields freely, but bump
+//! `STATUS_SCHEMA_VERSION` before renaming or removing any existing one.
+
+use super::conflicts::Conflict;
+use super::status::{FolderState, PassCounts, PauseReason, SyncProgress, SyncState, SyncStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Version of the JSON layout below
+pub const STATUS_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub schema_version: u32,
+    pub cli_version: String,
+    pub running: bool,
+    pub pid: Option<u32>,
+    /// False when the daemon is running but did not answer on the control channel
+    pub live: bool,
+    pub authenticated: bool,
+    pub user: Option<String>,
+    /// The main synced folder only; `folders` lists the path of every folder
+    pub recipes_dir: Option<PathBuf>,
+    pub status: Option<ReportStatus>,
+    /// Why the agent paused by itself; null when running or paused by the user
+    pub paused_by: Option<PauseReason>,
+    pub last_sync: Option<DateTime<Utc>>,
+    pub last_sync_age_secs: Option<i64>,
+    pub retry_attempt: usize,
+    /// When a failed pass is tried again; null unless waiting to retry
+    pub next_retry_at: Option<DateTime<Utc>>,
+    pub next_retry_in_secs: Option<i64>,
+    pub error_message: Option<String>,
+    pub last_error: Option<String>,
+    pub items_synced: usize,
+    pub items_pending: usize,
+    /// Phase, file counts and bytes of the running pass; null between passes
+    pub progress: Option<SyncProgress>,
+    /// Status of each synced folder; the fields above summarize all of them
+    pub folders: Vec<FolderReport>,
+    /// Unresolved conflict copies in all synced folders
+    pub conflicts: Vec<Conflict>,
+}
+
+/// `SyncStatus` as spelled in the report, in snake_case like the other
+/// values; the control channel keeps its own spelling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    Starting,
+    Syncing,
+    Idle,
+    Paused,
+    Error,
+    Offline,
+}
+
+impl From<SyncStatus> for ReportStatus {
+    fn from(status: SyncStatus) -> Self {
+        match status {
+            SyncStatus::Starting => Self::Starting,
+            SyncStatus::Syncing => Self::Syncing,
+            SyncStatus::Idle => Self::Idle,
+            SyncStatus::Paused => Self::Paused,
+            SyncStatus::Error => Self::Error,
+            SyncStatus::Offline => Self::Offline,
+        }
+    }
+}
+
+impl std::fmt::Display for ReportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            Self::Starting => SyncStatus::Starting,
+            Self::Syncing => SyncStatus::Syncing,
+            Self::Idle => SyncStatus::Idle,
+            Self::Paused => SyncStatus::Paused,
+            Self::Error => SyncStatus::Error,
+            Self::Offline => SyncStatus::Offline,
+        };
+        status.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderReport {
+    pub path: PathBuf,
+    /// Folder on the server; empty for the account root
+    pub remote_folder: String,
+    pub status: ReportStatus,
+    pub paused_by: Option<PauseReason>,
+    pub last_sync: Option<DateTime<Utc>>,
+    pub last_sync_age_secs: Option<i64>,
+    pub error_message: Option<String>,
+    pub items_pending: usize,
+    pub progress: Option<SyncProgress>,
+    /// Passes run, failed and retried since the agent started
+    pub passes: PassCounts,
+}
+
+impl FolderReport {
+    fn new(folder: &FolderState, now: DateTime<Utc>) -> Self {
+        Self {
+            path: folder.path.clone(),
+            remote_folder: folder.remote_folder.clone(),
+            status: folder.state.status.into(),
+            paused_by: folder.state.paused_by,
+            last_sync: folder.state.last_sync,
+            last_sync_age_secs: age_secs(folder.state.last_sync, now),
+            error_message: folder.state.error_message.clone(),
+            items_pending: folder.state.items_pending,
+            progress: folder.state.progress.clone(),
+            passes: folder.passes,
+        }
+    }
+}
+
+fn age_secs(time: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<i64> {
+    time.map(|t| (now - t).num_seconds().max(0))
+}
+
+impl StatusReport {
+    /// Report for an agent that is not running at all
+    pub fn not_running(authenticated: bool, user: Option<String>) -> Self {
+        Self {
+            schema_version: STATUS_SCHEMA_VERSION,
+            cli_version: env!("CARGO_PKG_VERSION").to_string(),
+            running: false,
+            pid: None,
+            live: false,
+            authenticated,
+            user,
+            recipes_dir: None,
+            status: None,
+            paused_by: None,
+            last_sync: None,
+            last_sync_age_secs: None,
+            retry_attempt: 0,
+            next_retry_at: None,
+            next_retry_in_secs: None,
+            error_message: None,
+            last_error: None,
+            items_synced: 0,
+            items_pending: 0,
+            progress: None,
+            folders: Vec::new(),
+            conflicts: Vec::new(),
+        }
+    }
+
+    /// Fill in the sync fields from the daemon's live state
+    pub fn with_state(mut self, state: &SyncState, now: DateTime<Utc>) -> Self {
+        self.live = true;
+        self.status = Some(state.status.into());
+        self.paused_by = state.paused_by;
+        self.last_sync = state.last_sync;
+        self.last_sync_age_secs = age_secs(state.last_sync, now);
+        self.retry_attempt = state.retry_attempt;
+        self.next_retry_at = state.next_retry_at;
+        self.next_retry_in_secs = state
+            .next_retry_at
+            .map(|at| (at - now).num_seconds().max(0));
+        self.error_message = state.error_message.clone();
+        self.last_error = state.last_error.clone();
+        self.items_synced = state.items_synced;
+        self.items_pending = state.items_pending;
+        self.progress = state.progress.clone();
+        self.conflicts = state.conflicts.clone();
+        self
+    }
+
+    /// Fill in the per-folder states reported by the daemon
+    pub fn with_folders(mut self, folders: &[FolderState], now: DateTime<Utc>) -> Self {
+        self.folders = folders.iter().map(|f| FolderReport::new(f, now)).collect();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_running_report_has_no_live_state() {
+        let report = StatusReport::not_running(false, None);
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["schema_version"], STATUS_SCHEMA_VERSION);
+        assert_eq!(json["running"], false);
+        assert_eq!(json["live"], false);
+        assert!(json["status"].is_null());
+        assert!(json["last_sync"].is_null());
+    }
+
+    #[test]
+    fn test_report_from_live_state() {
+        let now = Utc::now();
+        let state = SyncState {
+            status: SyncStatus::Error,
+            last_sync: Some(now - chrono::Duration::seconds(90)),
+            error_message: Some("Sync failed after 5 retries".to_string()),
+            retry_attempt: 3,
+            next_retry_at: Some(now + chrono::Duration::seconds(40)),
+            last_error: Some("Connection error: refused".to_string()),
+            ..Default::default()
+        };
+
+        let mut report = StatusReport::not_running(true, Some("a@b.c".to_string()));
+        report.running = true;
+        let report = report.with_state(&state, now);
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["live"], true);
+        assert_eq!(json["status"], "error");
+        assert_eq!(json["last_sync_age_secs"], 90);
+        assert_eq!(json["retry_attempt"], 3);
+        assert_eq!(json["next_retry_in_secs"], 40);
+        assert_eq!(json["last_error"], "Connection error: refused");
+        assert_eq!(json["user"], "a@b.c");
+        assert_eq!(json["folders"], serde_json::json!([]));
+        assert!(json["progress"].is_null());
+    }
+
+    #[test]
+    fn test_report_includes_progress() {
+        let state = SyncState {
+            status: SyncStatus::Syncing,
+            progress: Some(SyncProgress {
+                files_done: 3,
+                files_total: 17,
+                bytes_transferred: 4096,
+                ..SyncProgress::new(crate::sync::status::SyncPhase::Uploading)
+            }),
+            items_pending: 14,
+            ..Default::default()
+        };
+
+        let report = StatusReport::not_running(true, None).with_state(&state, Utc::now());
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["progress"]["phase"], "uploading");
+        assert_eq!(json["progress"]["files_done"], 3);
+        assert_eq!(json["progress"]["files_total"], 17);
+        assert_eq!(json["progress"]["bytes_transferred"], 4096);
+        assert_eq!(json["items_pending"], 14);
+    }
+
+    #[test]
+    fn test_report_lists_folders() {
+        let now = Utc::now();
+        let folders = vec![
+            FolderState {
+                path: PathBuf::from("/home/me/Recipes"),
+                remote_folder: String::new(),
+                state: SyncState {
+                    status: SyncStatus::Idle,
+                    last_sync: Some(now - chrono::Duration::seconds(30)),
+                    ..Default::default()
+                },
+                passes: Default::default(),
+            },
+            FolderState {
+                path: PathBuf::from("/home/me/Shared"),
+                remote_folder: "Shared".to_string(),
+                state: SyncState {
+                    status: SyncStatus::Paused,
+                    ..Default::default()
+                },
+                passes: Default::default(),
+            },
+        ];
+
+        let report = StatusReport::not_running(true, None)
+            .with_state(&SyncState::combine(&folders), now)
+            .with_folders(&folders, now);
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["folders"][0]["path"], "/home/me/Recipes");
+        assert_eq!(json["folders"][0]["last_sync_age_secs"], 30);
+        assert_eq!(json["folders"][1]["remote_folder"], "Shared");
+        assert_eq!(json["folders"][1]["status"], "paused");
+        assert!(json["folders"][1]["last_sync"].is_null());
+    }
+}
@@ -0,0 +1,164 @@
+//! What happens after a failed sync pass.
+//!
+//! Each error class gets its own decision: without a connection the folder
+//! waits until the endpoint answers again, an expired session signs the user
+//! out, errors that may clear up on their own (server errors, a file being
+//! written, a busy database) are retried with backoff, and everything else is
+//! reported and left until the next change or fallback pass.
+//!
+//! Backoff uses decorrelated jitter: each wait is picked at random between the
+//! base delay and three times the previous wait, capped at the maximum. Agents
+//! that lost the server at the same moment therefore come back spread out
+//! rather than all at once.
+
+use crate::config::settings::RetrySettings;
+use crate::error::SyncError;
+use std::time::Duration;
+
+use super::connectivity::FailureKind;
+
+/// How the sync loop reacts to a failed pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Wait until the sync endpoint is reachable, then try again at once
+    WaitOnline,
+    /// Try again after a backoff delay
+    Backoff,
+    /// The session is no longer valid; sign out and stop
+    SignOut,
+    /// Retrying will not help; report the error and stop
+    GiveUp,
+}
+
+impl RetryDecision {
+    /// Decision table for every error a pass can end with
+    pub fn for_error(e: &SyncError) -> Self {
+        if let Some(kind) = FailureKind::of(e) {
+            return match kind {
+                FailureKind::Offline => RetryDecision::WaitOnline,
+                FailureKind::Server => RetryDecision::Backoff,
+                FailureKind::Client => RetryDecision::GiveUp,
+            };
+        }
+
+        match e {
+            SyncError::AuthenticationRequired => RetryDecision::SignOut,
+            // Local trouble such as a file being written or a locked database
+            SyncError::Io(_) | SyncError::Database(_) | SyncError::SyncClient(_) => {
+                RetryDecision::Backoff
+            }
+            _ => RetryDecision::GiveUp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: usize,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from_settings(&RetrySettings::default())
+    }
+}
+
+impl RetryPolicy {
+    /// The policy for `settings`, kept within the bounds `validate` enforces
+    pub fn from_settings(settings: &RetrySettings) -> Self {
+        let base_delay_secs = settings
+            .base_delay_secs
+            .clamp(1, RetrySettings::MAX_DELAY_SECS);
+        Self {
+            max_retries: settings.max_retries.min(RetrySettings::MAX_RETRIES),
+            base_delay: Duration::from_secs(base_delay_secs),
+            max_delay: Duration::from_secs(
+                settings
+                    .max_delay_secs
+                    .clamp(base_delay_secs, RetrySettings::MAX_DELAY_SECS),
+            ),
+        }
+    }
+
+    /// Wait before the next attempt, given the wait before the previous one
+    /// (`None` for the first retry)
+    pub fn next_delay(&self, previous: Option<Duration>) -> Duration {
+        let low = self.base_delay.as_millis() as u64;
+        let high = previous
+            .map_or(low, |previous| previous.as_millis() as u64)
+            .saturating_mul(3)
+            .max(low);
+        let cap = self.max_delay.as_millis() as u64;
+
+        Duration::from_millis(fastrand::u64(low..=high).min(cap))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delays_stay_between_base_and_cap() {
+        let policy = RetryPolicy::default();
+
+        let mut previous = None;
+        for _ in 0..50 {
+            let delay = policy.next_delay(previous);
+            let upper = previous.map_or(policy.base_delay * 3, |p| p * 3);
+            assert!(delay >= policy.base_delay);
+            assert!(delay <= upper.min(policy.max_delay));
+            previous = Some(delay);
+        }
+
+        // Identical failures do not produce identical waits
+        let delays: std::collections::HashSet<_> =
+            (0..20).map(|_| policy.next_delay(None)).collect();
+        assert!(delays.len() > 1);
+    }
+
+    #[test]
+    fn test_policy_stays_within_bounds() {
+        let policy = RetryPolicy::from_settings(&RetrySettings {
+            max_retries: 1_000_000,
+            base_delay_secs: 0,
+            max_delay_secs: 0,
+        });
+
+        assert_eq!(policy.max_retries, RetrySettings::MAX_RETRIES);
+        assert_eq!(policy.base_delay, Duration::from_secs(1));
+        assert_eq!(policy.max_delay, Duration::from_secs(1));
+        assert!(policy.next_delay(None) >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_decision_table() {
+        let decide = RetryDecision::for_error;
+        assert_eq!(
+            decide(&SyncError::Offline("refused".into())),
+            RetryDecision::WaitOnline
+        );
+        assert_eq!(
+            decide(&SyncError::Server("503".into())),
+            RetryDecision::Backoff
+        );
+        assert_eq!(
+            decide(&SyncError::SyncClient("database is locked".into())),
+            RetryDecision::Backoff
+        );
+        assert_eq!(
+            decide(&SyncError::Rejected("413".into())),
+            RetryDecision::GiveUp
+        );
+        assert_eq!(
+            decide(&SyncError::AuthenticationRequired),
+            RetryDecision::SignOut
+        );
+        assert_eq!(
+            decide(&SyncError::Other("Sync failed: bad data".into())),
+            RetryDecision::GiveUp
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Sync hours and timed pauses.
+//!
+//! Both come from the settings and are checked by each folder's sync loop
+//! before every pass. While one of them applies, the folder shows as paused
+//! with the reason and the time left, and the loop sleeps until the pause
+//! runs out instead of waiting for the user to resume.
+
+use super::status::PauseReason;
+use crate::config::settings::{ScheduleSettings, SyncWindow};
+use chrono::{DateTime, Days, NaiveTime, TimeZone, Utc};
+
+/// When "until tomorrow" ends
+const MORNING: NaiveTime = NaiveTime::from_hms_opt(7, 0, 0).unwrap();
+
+/// Why syncing is on hold at `now`, or `None` when it may go ahead.
+/// A timed pause wins over the sync hours.
+pub fn hold_at<Tz: TimeZone>(
+    schedule: &ScheduleSettings,
+    now: DateTime<Tz>,
+) -> Option<PauseReason> {
+    let now_utc = now.with_timezone(&Utc);
+    if let Some(until) = schedule.paused_until.filter(|until| *until > now_utc) {
+        return Some(PauseReason::Timed { until });
+    }
+
+    let time = now.time();
+    if schedule.sync_windows.is_empty() || schedule.sync_windows.iter().any(|w| w.contains(time)) {
+        return None;
+    }
+
+    let until = schedule
+        .sync_windows
+        .iter()
+        .map(|window| next_at(&now, window.start))
+        .min()?;
+    Some(PauseReason::OutsideSyncHours { until })
+}
+
+/// The first time after `now` the clock shows `time`
+fn next_at<Tz: TimeZone>(now: &DateTime<Tz>, time: NaiveTime) -> DateTime<Utc> {
+    let today = now.date_naive();
+    // Two days ahead covers a time skipped by a daylight saving change
+    (0..3)
+        .filter_map(|days| today.checked_add_days(Days::new(days)))
+        .filter_map(|date| {
+            now.timezone()
+                .from_local_datetime(&date.and_time(time))
+                .earliest()
+        })
+        .find(|at| at > now)
+        .map_or_else(
+            || now.with_timezone(&Utc) + chrono::Duration::days(1),
+            |at| at.with_timezone(&Utc),
+        )
+}
+
+/// End of a pause given as a duration (`30m`, `1h`, `2d`), a time of day
+/// (`14:00`, the next time the clock shows it) or `tomorrow` (07:00)
+pub fn parse_pause_end(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+    pause_end_at(value, chrono::Local::now())
+}
+
+fn pause_end_at<Tz: TimeZone>(
+    value: &str,
+    now: DateTime<Tz>,
+) -> std::result::Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("tomorrow") {
+        return Ok(next_at(&now, MORNING));
+    }
+    if let Ok(time) = NaiveTime::parse_from_str(value, "%H:%M") {
+        return Ok(next_at(&now, time));
+    }
+
+    let invalid = || {
+        format!("'{value}' is not a duration like 30m, 2h or 1d, a time like 14:00 or 'tomorrow'")
+    };
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let count: i64 = value[..value.len() - unit.len_utf8()]
+        .parse()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(invalid)?;
+    let duration = match unit {
+        'm' => chrono::Duration::minutes(count),
+        'h' => chrono::Duration::hours(count),
+        'd' => chrono::Duration::days(count),
+        _ => return Err(invalid()),
+    };
+
+    Ok(now.with_timezone(&Utc) + duration)
+}
+
+/// Sync windows from a comma-separated list such as `07:00-12:00,14:00-23:00`
+pub fn parse_sync_windows(value: &str) -> std::result::Result<Vec<SyncWindow>, String> {
+    value
+        .split(',')
+        .filter(|window| !window.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        format!("2026-03-10T{time}:00Z").parse().unwrap()
+    }
+
+    fn schedule(windows: &str) -> ScheduleSettings {
+        ScheduleSettings {
+            sync_windows: parse_sync_windows(windows).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sync_hours() {
+        let day = schedule("07:00-23:00");
+        assert_eq!(hold_at(&day, at("12:00")), None);
+        assert_eq!(
+            hold_at(&day, at("23:30")),
+            Some(PauseReason::OutsideSyncHours {
+                until: "2026-03-11T07:00:00Z".parse().unwrap()
+            })
+        );
+        assert_eq!(
+            hold_at(&day, at("06:59")),
+            Some(PauseReason::OutsideSyncHours { until: at("07:00") })
+        );
+
+        // Spanning midnight, and the earliest of several starts
+        let nights = schedule("22:00-02:00, 05:00-06:00");
+        assert_eq!(hold_at(&nights, at("01:00")), None);
+        assert_eq!(
+            hold_at(&nights, at("03:00")),
+            Some(PauseReason::OutsideSyncHours { until: at("05:00") })
+        );
+
+        assert_eq!(hold_at(&schedule(""), at("03:00")), None);
+        assert!(parse_sync_windows("07:00-23").is_err());
+    }
+
+    #[test]
+    fn test_timed_pause_runs_out() {
+        let mut paused = schedule("07:00-23:00");
+        paused.paused_until = Some(at("13:00"));
+
+        assert_eq!(
+            hold_at(&paused, at("12:00")),
+            Some(PauseReason::Timed { until: at("13:00") })
+        );
+        assert_eq!(hold_at(&paused, at("13:00")), None);
+    }
+
+    #[test]
+    fn test_pause_end() {
+        let now = at("12:00");
+        assert_eq!(pause_end_at("30m", now), Ok(at("12:30")));
+        assert_eq!(pause_end_at("1h", now), Ok(at("13:00")));
+        assert_eq!(
+            pause_end_at("tomorrow", now),
+            "2026-03-11T07:00:00Z"
+                .parse::<DateTime<Utc>>()
+                .map_err(|e| e.to_string())
+        );
+        assert_eq!(pause_end_at("14:30", now), Ok(at("14:30")));
+        assert_eq!(pause_end_at("tomorrow", at("02:00")), Ok(at("07:00")));
+
+        for invalid in ["", "h", "0m", "-5m", "5w", "5é", "soon"] {
+            assert!(pause_end_at(invalid, now).is_err(), "{invalid}");
+        }
+    }
+}
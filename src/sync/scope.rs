@@ -0,0 +1,122 @@
+//! Mapping between paths in a local folder and paths on the server.
+//!
+//! The server keeps one file tree per account. The main recipes folder syncs
+//! with the root of that tree; every further folder syncs with its own
+//! subfolder, which the main folder then leaves alone.
+
+use crate::config::settings::SyncRoot;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteScope {
+    /// Server folder the local folder maps to; empty for the account root
+    folder: String,
+    /// Server folders owned by other local folders, skipped in both directions
+    excluded: Vec<String>,
+}
+
+impl RemoteScope {
+    /// Scope of `root`, given every folder that is synced
+    pub fn for_root(root: &SyncRoot, all: &[SyncRoot]) -> Self {
+        let excluded = if root.remote_folder.is_empty() {
+            all.iter()
+                .filter(|r| !r.remote_folder.is_empty())
+                .map(|r| r.remote_folder.clone())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            folder: root.remote_folder.clone(),
+            excluded,
+        }
+    }
+
+    /// Server path for a path relative to the local folder
+    pub fn to_remote(&self, local: &str) -> String {
+        let local = local.replace('\\', "/");
+        if self.folder.is_empty() {
+            local
+        } else {
+            format!("{}/{}", self.folder, local)
+        }
+    }
+
+    /// Local path for a server path, or `None` if it belongs to another folder
+    pub fn to_local(&self, remote: &str) -> Option<String> {
+        if self.folder.is_empty() {
+            (!self.is_excluded(remote)).then(|| remote.to_string())
+        } else {
+            strip_folder(remote, &self.folder).map(str::to_string)
+        }
+    }
+
+    /// Whether a local path is shadowed by another folder and must not be uploaded
+    pub fn is_excluded(&self, local: &str) -> bool {
+        let local = local.replace('\\', "/");
+        self.excluded
+            .iter()
+            .any(|folder| strip_folder(&local, folder).is_some())
+    }
+}
+
+/// `path` relative to `folder`, if it lies inside it
+fn strip_folder<'a>(path: &'a str, folder: &str) -> Option<&'a str> {
+    path.strip_prefix(folder)?
+        .strip_prefix('/')
+        .filter(|rest| !rest.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn root(id: &str, remote_folder: &str) -> SyncRoot {
+        SyncRoot {
+            id: id.to_string(),
+            path: PathBuf::from(format!("/home/me/{id}")),
+            remote_folder: remote_folder.to_string(),
+            sync_direction: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_main_folder_skips_other_folders() {
+        let roots = [root(SyncRoot::MAIN_ID, ""), root("shared", "Shared/Team")];
+        let scope = RemoteScope::for_root(&roots[0], &roots);
+
+        assert_eq!(scope.to_remote("Soup.cook"), "Soup.cook");
+        assert_eq!(scope.to_local("Soup.cook").as_deref(), Some("Soup.cook"));
+        assert_eq!(scope.to_local("Shared/Team/Soup.cook"), None);
+        assert_eq!(
+            scope.to_local("Shared/Teammates.cook").as_deref(),
+            Some("Shared/Teammates.cook")
+        );
+
+        assert!(scope.is_excluded("Shared/Team/Soup.cook"));
+        assert!(!scope.is_excluded("Shared/Soup.cook"));
+    }
+
+    #[test]
+    fn test_extra_folder_maps_into_its_remote_folder() {
+        let roots = [root(SyncRoot::MAIN_ID, ""), root("shared", "Shared")];
+        let scope = RemoteScope::for_root(&roots[1], &roots);
+
+        assert_eq!(
+            scope.to_remote("Pasta/Carbonara.cook"),
+            "Shared/Pasta/Carbonara.cook"
+        );
+        assert_eq!(
+            scope.to_remote("Pasta\\Carbonara.cook"),
+            "Shared/Pasta/Carbonara.cook"
+        );
+        assert_eq!(
+            scope.to_local("Shared/Pasta/Carbonara.cook").as_deref(),
+            Some("Pasta/Carbonara.cook")
+        );
+        assert_eq!(scope.to_local("Soup.cook"), None);
+        assert_eq!(scope.to_local("SharedSoup.cook"), None);
+        assert!(!scope.is_excluded("Pasta/Carbonara.cook"));
+    }
+}
@@ -1,5 +1,7 @@
+use super::conflicts::Conflict;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SyncStatus {
@@ -24,15 +26,140 @@ impl fmt::Display for SyncStatus {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Why a folder is paused when the user did not pause it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    MeteredNetwork,
+    /// Paused for a while from the tray or `cook-sync pause --for`
+    Timed {
+        until: chrono::DateTime<chrono::Utc>,
+    },
+    /// Outside the configured sync hours
+    OutsideSyncHours {
+        until: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+impl PauseReason {
+    /// When the pause ends by itself, if it does
+    pub fn until(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        match self {
+            PauseReason::MeteredNetwork => None,
+            PauseReason::Timed { until } | PauseReason::OutsideSyncHours { until } => Some(*until),
+        }
+    }
+
+    /// Whether the schedule rules are behind it
+    pub fn is_schedule(&self) -> bool {
+        matches!(
+            self,
+            PauseReason::Timed { .. } | PauseReason::OutsideSyncHours { .. }
+        )
+    }
+}
+
+impl fmt::Display for PauseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PauseReason::MeteredNetwork => write!(f, "metered network"),
+            PauseReason::Timed { until } => write!(f, "{} left", remaining(*until)),
+            PauseReason::OutsideSyncHours { until } => {
+                write!(f, "outside sync hours, {} left", remaining(*until))
+            }
+        }
+    }
+}
+
+/// Time until `until`, e.g. "25m" or "8h 5m"
+fn remaining(until: chrono::DateTime<chrono::Utc>) -> String {
+    let minutes = ((until - chrono::Utc::now()).num_seconds().max(0) + 59) / 60;
+    match minutes {
+        m if m < 60 => format!("{m}m"),
+        m => format!("{}h {}m", m / 60, m % 60),
+    }
+}
+
+/// Step of a sync pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    Indexing,
+    Uploading,
+    Downloading,
+}
+
+impl fmt::Display for SyncPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncPhase::Indexing => write!(f, "Indexing"),
+            SyncPhase::Uploading => write!(f, "Uploading"),
+            SyncPhase::Downloading => write!(f, "Downloading"),
+        }
+    }
+}
+
+/// How far the running pass has got
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncProgress {
+    pub phase: SyncPhase,
+    /// Files of the current phase that are done
+    pub files_done: usize,
+    /// Files the current phase will handle (0 while indexing)
+    pub files_total: usize,
+    /// Chunk data sent or received so far in this pass
+    pub bytes_transferred: u64,
+}
+
+impl SyncProgress {
+    pub fn new(phase: SyncPhase) -> Self {
+        Self {
+            phase,
+            files_done: 0,
+            files_total: 0,
+            bytes_transferred: 0,
+        }
+    }
+}
+
+/// Short form for status lines, e.g. "Uploading 3/17"
+impl fmt::Display for SyncProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.files_total > 0 {
+            write!(f, "{} {}/{}", self.phase, self.files_done, self.files_total)
+        } else {
+            write!(f, "{}", self.phase)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncState {
     pub status: SyncStatus,
     pub last_sync: Option<chrono::DateTime<chrono::Utc>>,
     pub error_message: Option<String>,
-    #[allow(dead_code)]
+    /// Set while paused automatically; `None` when the user paused
+    #[serde(default)]
+    pub paused_by: Option<PauseReason>,
+    /// Files transferred by the last completed pass
     pub items_synced: usize,
-    #[allow(dead_code)]
+    /// Local changes the last completed pass left waiting to be uploaded
     pub items_pending: usize,
+    /// Progress of the running pass; `None` between passes
+    #[serde(default)]
+    pub progress: Option<SyncProgress>,
+    /// Current retry attempt of the ongoing sync (0 when not retrying)
+    #[serde(default)]
+    pub retry_attempt: usize,
+    /// When the failed pass is tried again; `None` unless waiting to retry
+    #[serde(default)]
+    pub next_retry_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Most recent error, kept after the agent recovers so it can be inspected
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// Unresolved conflict copies in the folder
+    #[serde(default)]
+    pub conflicts: Vec<Conflict>,
 }
 
 impl Default for SyncState {
@@ -41,8 +168,14 @@ impl Default for SyncState {
             status: SyncStatus::Starting,
             last_sync: None,
             error_message: None,
+            paused_by: None,
             items_synced: 0,
             items_pending: 0,
+            progress: None,
+            retry_attempt: 0,
+            next_retry_at: None,
+            last_error: None,
+            conflicts: Vec::new(),
         }
     }
 }
@@ -51,19 +184,48 @@ impl SyncState {
     pub fn set_syncing(&mut self) {
         self.status = SyncStatus::Syncing;
         self.error_message = None;
+        self.next_retry_at = None;
     }
 
     pub fn set_idle(&mut self) {
         self.status = SyncStatus::Idle;
         self.last_sync = Some(chrono::Utc::now());
         self.error_message = None;
+        self.progress = None;
+        self.retry_attempt = 0;
+        self.next_retry_at = None;
     }
 
     pub fn set_error(&mut self, message: String) {
         self.status = SyncStatus::Error;
+        self.progress = None;
+        self.next_retry_at = None;
+        self.last_error = Some(message.clone());
         self.error_message = Some(message);
     }
 
+    /// Record a failed attempt that is about to be retried at `retry_at`
+    pub fn set_retrying(
+        &mut self,
+        attempt: usize,
+        message: String,
+        retry_at: chrono::DateTime<chrono::Utc>,
+    ) {
+        self.retry_attempt = attempt;
+        self.next_retry_at = Some(retry_at);
+        self.last_error = Some(message);
+    }
+
+    /// "retrying at 14:05:10" while waiting to retry, in local time
+    pub fn retry_note(&self) -> Option<String> {
+        self.next_retry_at.map(|at| {
+            format!(
+                "retrying at {}",
+                at.with_timezone(&chrono::Local).format("%H:%M:%S")
+            )
+        })
+    }
+
     pub fn clear_error(&mut self) {
         if self.status == SyncStatus::Error {
             self.status = SyncStatus::Idle;
@@ -71,14 +233,112 @@ impl SyncState {
         }
     }
 
-    #[allow(dead_code)]
     pub fn set_offline(&mut self) {
         self.status = SyncStatus::Offline;
+        self.progress = None;
+        self.next_retry_at = None;
         self.error_message = Some("No internet connection".to_string());
     }
 
+    /// Status for display, e.g. "Paused: metered network"
+    pub fn status_text(&self) -> String {
+        match (self.status, self.paused_by) {
+            (SyncStatus::Paused, Some(reason)) => format!("Paused: {reason}"),
+            (status, _) => status.to_string(),
+        }
+    }
+
     #[allow(dead_code)]
     pub fn is_active(&self) -> bool {
         matches!(self.status, SyncStatus::Syncing | SyncStatus::Idle)
     }
+
+    /// Summary of several folders' states for places that show a single status.
+    ///
+    /// Problems win over activity, activity over rest; the folder that synced
+    /// longest ago determines `last_sync` so a stuck folder is not hidden.
+    pub fn combine(folders: &[FolderState]) -> SyncState {
+        let Some(first) = folders.first() else {
+            return SyncState::default();
+        };
+        if folders.len() == 1 {
+            return first.state.clone();
+        }
+
+        let any = |status: SyncStatus| folders.iter().any(|f| f.state.status == status);
+        let status = [
+            SyncStatus::Error,
+            SyncStatus::Offline,
+            SyncStatus::Syncing,
+            SyncStatus::Starting,
+        ]
+        .into_iter()
+        .find(|s| any(*s))
+        .unwrap_or(
+            if folders.iter().all(|f| f.state.status == SyncStatus::Paused) {
+                SyncStatus::Paused
+            } else {
+                SyncStatus::Idle
+            },
+        );
+
+        let last_sync = folders
+            .iter()
+            .map(|f| f.state.last_sync)
+            .collect::<Option<Vec<_>>>()
+            .and_then(|times| times.into_iter().min());
+
+        // Name the folder so a message is actionable with several of them
+        let labelled =
+            |f: &FolderState, message: &String| format!("{}: {message}", f.path.display());
+
+        SyncState {
+            status,
+            last_sync,
+            paused_by: (status == SyncStatus::Paused)
+                .then(|| folders.iter().find_map(|f| f.state.paused_by))
+                .flatten(),
+            error_message: folders
+                .iter()
+                .find_map(|f| f.state.error_message.as_ref().map(|m| labelled(f, m))),
+            items_synced: folders.iter().map(|f| f.state.items_synced).sum(),
+            items_pending: folders.iter().map(|f| f.state.items_pending).sum(),
+            // Folders sync one pass at a time each; show the first that is busy
+            progress: folders.iter().find_map(|f| f.state.progress.clone()),
+            retry_attempt: folders
+                .iter()
+                .map(|f| f.state.retry_attempt)
+                .max()
+                .unwrap_or(0),
+            // The earliest retry is the next thing that happens
+            next_retry_at: folders.iter().filter_map(|f| f.state.next_retry_at).min(),
+            last_error: folders
+                .iter()
+                .find_map(|f| f.state.last_error.as_ref().map(|m| labelled(f, m))),
+            conflicts: folders
+                .iter()
+                .flat_map(|f| f.state.conflicts.iter().cloned())
+                .collect(),
+        }
+    }
+}
+
+/// Live state of one synced folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderState {
+    pub path: PathBuf,
+    /// Folder on the server; empty for the account root
+    pub remote_folder: String,
+    pub state: SyncState,
+    #[serde(default)]
+    pub passes: PassCounts,
+}
+
+/// Sync passes of a folder since the agent started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PassCounts {
+    pub runs: u64,
+    pub failures: u64,
+    /// Failed passes tried again after a backoff
+    pub retries: u64,
 }
@@ -1,8 +1,14 @@
-use super::status::SyncState;
+use super::status::{SyncPhase, SyncProgress, SyncState};
 use cooklang_sync_client::{SyncStatus as ClientSyncStatus, SyncStatusListener};
 use log::{debug, error, info, warn};
 use std::sync::{Arc, Mutex};
 
+/// Receives file and byte counts from the sync engine while a pass runs;
+/// the client library's listener only knows about phases
+pub trait ProgressListener: Send + Sync {
+    fn on_progress(&self, progress: &SyncProgress);
+}
+
 /// Listener that receives status updates from the sync client and updates our internal state
 pub struct SyncManagerListener {
     state: Arc<Mutex<SyncState>>,
@@ -12,6 +18,16 @@ impl SyncManagerListener {
     pub fn new(state: Arc<Mutex<SyncState>>) -> Self {
         Self { state }
     }
+
+    /// Enter `phase`; bytes carry over from earlier phases of the same pass
+    fn start_phase(state: &mut SyncState, phase: SyncPhase) {
+        state.set_syncing();
+        let bytes_transferred = state.progress.as_ref().map_or(0, |p| p.bytes_transferred);
+        state.progress = Some(SyncProgress {
+            bytes_transferred,
+            ..SyncProgress::new(phase)
+        });
+    }
 }
 
 impl SyncStatusListener for SyncManagerListener {
@@ -29,15 +45,17 @@ impl SyncStatusListener for SyncManagerListener {
             }
             ClientSyncStatus::Indexing => {
                 debug!("Sync status: Indexing");
-                state.set_syncing();
+                // A new pass starts with indexing
+                state.progress = None;
+                Self::start_phase(&mut state, SyncPhase::Indexing);
             }
             ClientSyncStatus::Downloading => {
                 debug!("Sync status: Downloading");
-                state.set_syncing();
+                Self::start_phase(&mut state, SyncPhase::Downloading);
             }
             ClientSyncStatus::Uploading => {
                 debug!("Sync status: Uploading");
-                state.set_syncing();
+                Self::start_phase(&mut state, SyncPhase::Uploading);
             }
             ClientSyncStatus::Error { message } => {
                 error!("Sync status: Error - {}", message);
@@ -60,6 +78,12 @@ impl SyncStatusListener for SyncManagerListener {
     }
 }
 
+impl ProgressListener for SyncManagerListener {
+    fn on_progress(&self, progress: &SyncProgress) {
+        self.state.lock().unwrap().progress = Some(progress.clone());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::status::SyncStatus;
@@ -89,6 +113,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_progress_follows_phases() {
+        let state = Arc::new(Mutex::new(SyncState::default()));
+        let listener = SyncManagerListener::new(Arc::clone(&state));
+
+        listener.on_status_changed(ClientSyncStatus::Indexing);
+        listener.on_status_changed(ClientSyncStatus::Uploading);
+        listener.on_progress(&SyncProgress {
+            files_done: 3,
+            files_total: 17,
+            bytes_transferred: 2048,
+            ..SyncProgress::new(SyncPhase::Uploading)
+        });
+        let progress = state.lock().unwrap().progress.clone().unwrap();
+        assert_eq!(progress.to_string(), "Uploading 3/17");
+        assert_eq!(state.lock().unwrap().status, SyncStatus::Syncing);
+
+        // Bytes keep counting across phases of a pass
+        listener.on_status_changed(ClientSyncStatus::Downloading);
+        let progress = state.lock().unwrap().progress.clone().unwrap();
+        assert_eq!(progress.phase, SyncPhase::Downloading);
+        assert_eq!(progress.bytes_transferred, 2048);
+        assert_eq!(progress.to_string(), "Downloading");
+
+        listener.on_complete(true, None);
+        assert!(state.lock().unwrap().progress.is_none());
+    }
+
     #[test]
     fn test_completion_callbacks() {
         let state = Arc::new(Mutex::new(SyncState::default()));
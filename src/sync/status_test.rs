@@ -196,4 +196,71 @@ mod tests {
         assert!(second_sync_time.is_some());
         assert!(second_sync_time > first_sync_time);
     }
+
+    fn folder(path: &str, status: SyncStatus) -> super::super::status::FolderState {
+        super::super::status::FolderState {
+            path: std::path::PathBuf::from(path),
+            remote_folder: String::new(),
+            state: SyncState {
+                status,
+                last_sync: Some(chrono::Utc::now()),
+                ..Default::default()
+            },
+            passes: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_combined_state_prefers_problems() {
+        let mut broken = folder("/shared", SyncStatus::Error);
+        broken.state.error_message = Some("Connection refused".to_string());
+        broken.state.last_sync = None;
+
+        let combined = SyncState::combine(&[folder("/main", SyncStatus::Idle), broken]);
+        assert_eq!(combined.status, SyncStatus::Error);
+        assert_eq!(
+            combined.error_message.as_deref(),
+            Some("/shared: Connection refused")
+        );
+        // One folder never synced, so neither has "everything"
+        assert!(combined.last_sync.is_none());
+    }
+
+    #[test]
+    fn test_combined_state_paused_only_when_all_paused() {
+        let some = SyncState::combine(&[
+            folder("/main", SyncStatus::Paused),
+            folder("/shared", SyncStatus::Idle),
+        ]);
+        assert_eq!(some.status, SyncStatus::Idle);
+
+        let all = SyncState::combine(&[
+            folder("/main", SyncStatus::Paused),
+            folder("/shared", SyncStatus::Paused),
+        ]);
+        assert_eq!(all.status, SyncStatus::Paused);
+        assert!(all.last_sync.is_some());
+    }
+
+    #[test]
+    fn test_retry_time_until_next_pass() {
+        let now = chrono::Utc::now();
+        let mut state = SyncState::default();
+        state.set_error("Server error: 503".to_string());
+        state.set_retrying(1, "Server error: 503".to_string(), now);
+        assert_eq!(state.next_retry_at, Some(now));
+        assert!(state.retry_note().unwrap().starts_with("retrying at "));
+
+        let mut first = folder("/main", SyncStatus::Error);
+        first.state = state.clone();
+        let mut later = folder("/shared", SyncStatus::Error);
+        later.state.next_retry_at = Some(now + chrono::Duration::seconds(60));
+        let combined = SyncState::combine(&[first, later]);
+        assert_eq!(combined.next_retry_at, Some(now));
+
+        // The retry pass starting clears it
+        state.set_syncing();
+        assert!(state.next_retry_at.is_none());
+        assert!(state.retry_note().is_none());
+    }
 }
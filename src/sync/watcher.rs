@@ -0,0 +1,206 @@
+//! Watches the recipes directory and turns bursts of filesystem events into a
+//! single sync trigger.
+//!
+//! Editors tend to produce several events per save (temp file, rename, chmod),
+//! so events are debounced: the trigger fires once the directory has been quiet
+//! for `DEBOUNCE`, or after `MAX_DEBOUNCE_DELAY` if changes keep coming.
+
+use crate::error::{Result, SyncError};
+use cooklang_sync_client::chunker::{is_binary, is_text};
+use log::{debug, warn};
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{timeout, Duration, Instant};
+
+/// Quiet period after the last event before a sync is triggered
+pub const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Upper bound on how long a continuous stream of events can delay a sync
+pub const MAX_DEBOUNCE_DELAY: Duration = Duration::from_secs(3);
+
+/// Keeps the OS watcher alive; dropping it stops watching
+pub struct RecipesWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl RecipesWatcher {
+    /// Start watching `dir` recursively, notifying `trigger` once per burst of changes
+    pub fn start(dir: &Path, trigger: Arc<Notify>) -> Result<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+                Ok(event) if is_relevant(&event) => {
+                    let _ = tx.send(());
+                }
+                Ok(_) => {}
+                Err(e) => warn!("File watcher error: {e}"),
+            })
+            .map_err(|e| SyncError::Other(format!("Could not create file watcher: {e}")))?;
+
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| SyncError::Other(format!("Could not watch {}: {e}", dir.display())))?;
+
+        debug!("Watching {} for changes", dir.display());
+
+        // Ends by itself once the watcher (and with it the sender) is dropped
+        tokio::spawn(debounce(rx, trigger, DEBOUNCE, MAX_DEBOUNCE_DELAY));
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+/// Collapse events arriving on `rx` into one `trigger` notification per burst
+pub(crate) async fn debounce(
+    mut rx: mpsc::UnboundedReceiver<()>,
+    trigger: Arc<Notify>,
+    quiet: Duration,
+    max_delay: Duration,
+) {
+    while rx.recv().await.is_some() {
+        let deadline = Instant::now() + max_delay;
+
+        loop {
+            let wait = quiet.min(deadline.saturating_duration_since(Instant::now()));
+            match timeout(wait, rx.recv()).await {
+                Ok(Some(())) if Instant::now() < deadline => continue,
+                Ok(Some(())) => break,
+                // Watcher stopped, nobody is waiting for the sync anymore
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+
+        debug!("Local changes detected, triggering sync");
+        trigger.notify_one();
+    }
+}
+
+/// Whether an event can affect what gets synced
+fn is_relevant(event: &Event) -> bool {
+    let kind_matches = match event.kind {
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Any => true,
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Modify(_) => true,
+        EventKind::Access(_) | EventKind::Other => false,
+    };
+
+    // Paths without an extension may be directories being created, moved or removed
+    kind_matches
+        && event
+            .paths
+            .iter()
+            .any(|p| is_text(p) || is_binary(p) || p.extension().is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, MetadataKind, RemoveKind};
+    use std::path::PathBuf;
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn test_relevant_events() {
+        assert!(is_relevant(&event(
+            EventKind::Create(CreateKind::File),
+            "/r/Pasta.cook"
+        )));
+        assert!(is_relevant(&event(
+            EventKind::Remove(RemoveKind::Folder),
+            "/r/Breakfast"
+        )));
+        assert!(is_relevant(&event(
+            EventKind::Modify(ModifyKind::Any),
+            "/r/photo.JPG"
+        )));
+
+        // Reads and permission changes don't change content
+        assert!(!is_relevant(&event(
+            EventKind::Access(AccessKind::Any),
+            "/r/Pasta.cook"
+        )));
+        assert!(!is_relevant(&event(
+            EventKind::Modify(ModifyKind::Metadata(MetadataKind::Permissions)),
+            "/r/Pasta.cook"
+        )));
+
+        // Files the indexer never uploads
+        assert!(!is_relevant(&event(
+            EventKind::Create(CreateKind::File),
+            "/r/.Pasta.cook.swp"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_debounce_collapses_bursts() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let trigger = Arc::new(Notify::new());
+        tokio::spawn(debounce(
+            rx,
+            Arc::clone(&trigger),
+            Duration::from_millis(50),
+            Duration::from_secs(2),
+        ));
+
+        for _ in 0..5 {
+            tx.send(()).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        timeout(Duration::from_secs(1), trigger.notified())
+            .await
+            .expect("Burst should trigger a sync");
+        assert!(
+            timeout(Duration::from_millis(200), trigger.notified())
+                .await
+                .is_err(),
+            "A single burst must trigger only one sync"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_debounce_fires_during_continuous_changes() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let trigger = Arc::new(Notify::new());
+        tokio::spawn(debounce(
+            rx,
+            Arc::clone(&trigger),
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+        ));
+
+        let sender = tokio::spawn(async move {
+            for _ in 0..40 {
+                tx.send(()).unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        // Events never pause for the quiet period, the max delay must kick in
+        timeout(Duration::from_millis(600), trigger.notified())
+            .await
+            .expect("Continuous changes should not postpone sync forever");
+        sender.abort();
+    }
+
+    #[tokio::test]
+    async fn test_watcher_triggers_on_recipe_change() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let trigger = Arc::new(Notify::new());
+        let _watcher = RecipesWatcher::start(temp_dir.path(), Arc::clone(&trigger)).unwrap();
+
+        std::fs::write(temp_dir.path().join("Soup.cook"), "Boil @water{1%l}.").unwrap();
+
+        timeout(Duration::from_secs(5), trigger.notified())
+            .await
+            .expect("Writing a recipe should trigger a sync");
+    }
+}
@@ -2,10 +2,13 @@
 // This works directly with GNOME's AppIndicator extension via D-Bus
 
 use crate::auth::AuthManager;
+use crate::config::settings::SyncDirection;
 use crate::config::Config;
 use crate::error::{Result, SyncError};
+use crate::sync::conflicts::Conflict;
 use crate::sync::{SyncManager, SyncStatus};
 use log::{debug, error, info, warn};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tokio::runtime::Handle;
@@ -15,6 +18,8 @@ use tokio::runtime::Handle;
 pub enum TrayEvent {
     Quit,
     ToggleSync,
+    /// Pause until the end given as a `cook-sync pause --for` value
+    PauseFor(&'static str),
     SetFolder,
     OpenFolder,
     OpenWeb,
@@ -22,6 +27,9 @@ pub enum TrayEvent {
     About,
     ToggleAutoStart,
     LoginLogout,
+    SetSyncDirection(SyncDirection),
+    SwitchProfile(String),
+    OpenConflict(PathBuf),
 }
 
 // Shared state between the tray and the application
@@ -33,6 +41,11 @@ struct TrayState {
 
     // Tray state
     status_text: Arc<Mutex<String>>,
+    /// One line per synced folder, only filled when there are several
+    folder_statuses: Arc<Mutex<Vec<String>>>,
+    conflicts: Arc<Mutex<Vec<Conflict>>>,
+    /// Lines of the "Recent activity" submenu, newest first
+    recent_activity: Arc<Mutex<Vec<String>>>,
     folder_path: Arc<Mutex<Option<String>>>,
     user_email: Arc<Mutex<Option<String>>>,
     is_logged_in: Arc<Mutex<bool>>,
@@ -58,17 +71,23 @@ impl TrayState {
             "cook-sync-dark"
         };
 
+        // A pause saved before the last restart is in place already
+        let sync_paused = sync_manager.state().status == SyncStatus::Paused;
+
         Self {
             sync_manager,
             auth_manager,
             config,
             runtime_handle,
             status_text: Arc::new(Mutex::new("Starting".to_string())),
+            folder_statuses: Arc::new(Mutex::new(Vec::new())),
+            conflicts: Arc::new(Mutex::new(Vec::new())),
+            recent_activity: Arc::new(Mutex::new(Vec::new())),
             folder_path: Arc::new(Mutex::new(None)),
             user_email: Arc::new(Mutex::new(None)),
             is_logged_in: Arc::new(Mutex::new(false)),
             auto_start_enabled: Arc::new(Mutex::new(auto_start_enabled)),
-            sync_paused: Arc::new(Mutex::new(false)),
+            sync_paused: Arc::new(Mutex::new(sync_paused)),
             icon_name: Arc::new(Mutex::new(icon_name.to_string())),
             shutdown_signal: Arc::new(AtomicBool::new(false)),
         }
@@ -83,8 +102,7 @@ impl TrayState {
                 std::process::exit(0);
             }
             TrayEvent::ToggleSync => {
-                let state = self.sync_manager.state();
-                let is_paused = state.lock().unwrap().status == SyncStatus::Paused;
+                let is_paused = self.sync_manager.state().status == SyncStatus::Paused;
                 if is_paused {
                     self.sync_manager.resume();
                     *self.sync_paused.lock().unwrap() = false;
@@ -94,6 +112,15 @@ impl TrayState {
                 }
                 // Menu will auto-update on next click
             }
+            TrayEvent::PauseFor(length) => {
+                let result = crate::sync::schedule::parse_pause_end(length)
+                    .map_err(SyncError::Other)
+                    .and_then(|until| self.sync_manager.pause_until(until));
+                match result {
+                    Ok(()) => *self.sync_paused.lock().unwrap() = true,
+                    Err(e) => error!("Failed to pause sync: {}", e),
+                }
+            }
             TrayEvent::SetFolder => {
                 let config_clone = Arc::clone(&self.config);
                 let sync_manager_clone = Arc::clone(&self.sync_manager);
@@ -138,6 +165,11 @@ impl TrayState {
                     warn!("No recipes folder configured");
                 }
             }
+            TrayEvent::OpenConflict(copy) => {
+                if let Err(e) = open::that(&copy) {
+                    error!("Failed to open conflict copy {}: {}", copy.display(), e);
+                }
+            }
             TrayEvent::OpenWeb => {
                 if let Err(e) = open::that("https://cook.md") {
                     error!("Failed to open cook.md: {}", e);
@@ -278,8 +310,8 @@ impl TrayState {
                                             info!("Logged in as: {}", email);
                                         }
 
-                                        // Start sync manager if recipes folder is configured
-                                        if config.settings().lock().unwrap().recipes_dir.is_some() {
+                                        // Start sync manager if a folder is configured
+                                        if !config.settings().lock().unwrap().roots().is_empty() {
                                             if let Err(e) = sync_manager.start().await {
                                                 error!("Failed to start sync manager: {}", e);
                                             }
@@ -296,6 +328,47 @@ impl TrayState {
                     // Menu will auto-update on next click
                 });
             }
+            TrayEvent::SetSyncDirection(direction) => {
+                info!("Setting sync direction to: {}", direction);
+                if let Err(e) = self.config.update_settings(|s| {
+                    s.sync_direction = direction;
+                }) {
+                    error!("Failed to update sync direction: {}", e);
+                    return;
+                }
+                self.sync_manager.wake();
+            }
+            TrayEvent::SwitchProfile(name) => {
+                if name == self.config.profile() {
+                    return;
+                }
+                info!("Switching to profile: {}", name);
+                let auth_manager = Arc::clone(&self.auth_manager);
+                let sync_manager = Arc::clone(&self.sync_manager);
+                let config = Arc::clone(&self.config);
+                let runtime_handle = self.runtime_handle.clone();
+                let user_email_clone = Arc::clone(&self.user_email);
+                let is_logged_in_clone = Arc::clone(&self.is_logged_in);
+
+                std::thread::spawn(move || {
+                    runtime_handle.block_on(async {
+                        if let Err(e) = crate::daemon::switch_profile(
+                            &config,
+                            &auth_manager,
+                            &sync_manager,
+                            &name,
+                        )
+                        .await
+                        {
+                            error!("Failed to switch profile: {}", e);
+                        }
+                    });
+
+                    let session = auth_manager.get_session();
+                    *is_logged_in_clone.lock().unwrap() = session.is_some();
+                    *user_email_clone.lock().unwrap() = session.and_then(|s| s.email);
+                });
+            }
         }
     }
 }
@@ -346,15 +419,91 @@ impl ksni::Tray for CookSyncTray {
         let is_logged_in = *state.is_logged_in.lock().unwrap();
         let auto_start = *state.auto_start_enabled.lock().unwrap();
         let sync_paused = *state.sync_paused.lock().unwrap();
+        let sync_direction = state.config.settings().lock().unwrap().sync_direction;
+        let folder_statuses = state.folder_statuses.lock().unwrap().clone();
+        let conflicts = state.conflicts.lock().unwrap().clone();
+        let recent_activity = state.recent_activity.lock().unwrap().clone();
+        let profiles = state
+            .config
+            .profiles()
+            .map(|p| p.names())
+            .unwrap_or_default();
+        let active_profile = state.config.profile();
+
+        // Status (disabled, just for display)
+        let mut items: Vec<ksni::MenuItem<Self>> = vec![ksni::menu::StandardItem {
+            label: format!("Status: {}", status_text),
+            enabled: false,
+            ..Default::default()
+        }
+        .into()];
 
-        vec![
-            // Status (disabled, just for display)
+        // Per-folder status (disabled, just for display)
+        items.extend(folder_statuses.into_iter().map(|label| {
             ksni::menu::StandardItem {
-                label: format!("Status: {}", status_text),
+                label,
                 enabled: false,
                 ..Default::default()
             }
+            .into()
+        }));
+
+        // Conflict copies, opened on click so the user can compare them
+        if !conflicts.is_empty() {
+            items.push(
+                ksni::menu::SubMenu {
+                    label: format!("Conflicts ({})", conflicts.len()),
+                    submenu: conflicts
+                        .iter()
+                        .map(|conflict| {
+                            let copy = conflict.copy_abs();
+                            ksni::menu::StandardItem {
+                                label: format!(
+                                    "{} (from {})",
+                                    conflict.path.display(),
+                                    conflict.host
+                                ),
+                                activate: Box::new(move |this: &mut Self| {
+                                    this.state
+                                        .handle_event(TrayEvent::OpenConflict(copy.clone()));
+                                }),
+                                ..Default::default()
+                            }
+                            .into()
+                        })
+                        .collect(),
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+
+        // What the last sync runs did (disabled, just for display)
+        let recent_activity = if recent_activity.is_empty() {
+            vec!["No activity yet".to_string()]
+        } else {
+            recent_activity
+        };
+        items.push(
+            ksni::menu::SubMenu {
+                label: "Recent activity".to_string(),
+                submenu: recent_activity
+                    .into_iter()
+                    .map(|label| {
+                        ksni::menu::StandardItem {
+                            label,
+                            enabled: false,
+                            ..Default::default()
+                        }
+                        .into()
+                    })
+                    .collect(),
+                ..Default::default()
+            }
             .into(),
+        );
+
+        items.extend([
             // Sync toggle
             ksni::menu::StandardItem {
                 label: if sync_paused {
@@ -369,6 +518,25 @@ impl ksni::Tray for CookSyncTray {
                 ..Default::default()
             }
             .into(),
+            // Timed pauses that end by themselves
+            ksni::menu::SubMenu {
+                label: "Pause for".to_string(),
+                submenu: super::PAUSE_CHOICES
+                    .iter()
+                    .map(|&(label, length)| {
+                        ksni::menu::StandardItem {
+                            label: label.to_string(),
+                            activate: Box::new(move |this: &mut Self| {
+                                this.state.handle_event(TrayEvent::PauseFor(length));
+                            }),
+                            ..Default::default()
+                        }
+                        .into()
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+            .into(),
             ksni::menu::MenuItem::Separator,
             // User info (disabled, just for display)
             ksni::menu::StandardItem {
@@ -390,6 +558,47 @@ impl ksni::Tray for CookSyncTray {
                 ..Default::default()
             }
             .into(),
+        ]);
+
+        // Profile switcher, once there is more than one profile
+        if profiles.len() > 1 {
+            items.push(
+                ksni::menu::SubMenu {
+                    label: format!("Profile: {active_profile}"),
+                    submenu: vec![ksni::menu::RadioGroup {
+                        selected: profiles
+                            .iter()
+                            .position(|p| *p == active_profile)
+                            .unwrap_or(0),
+                        select: Box::new(move |this: &mut Self, index| {
+                            // The menu is rebuilt after every change, so the list is current
+                            let profiles = this
+                                .state
+                                .config
+                                .profiles()
+                                .map(|p| p.names())
+                                .unwrap_or_default();
+                            if let Some(name) = profiles.get(index) {
+                                this.state
+                                    .handle_event(TrayEvent::SwitchProfile(name.clone()));
+                            }
+                        }),
+                        options: profiles
+                            .iter()
+                            .map(|name| ksni::menu::RadioItem {
+                                label: name.clone(),
+                                ..Default::default()
+                            })
+                            .collect(),
+                    }
+                    .into()],
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+
+        items.extend([
             ksni::menu::MenuItem::Separator,
             // Folder info (disabled, just for display)
             ksni::menu::StandardItem {
@@ -416,6 +625,32 @@ impl ksni::Tray for CookSyncTray {
                 ..Default::default()
             }
             .into(),
+            // Sync direction
+            ksni::menu::SubMenu {
+                label: "Sync direction".to_string(),
+                submenu: vec![ksni::menu::RadioGroup {
+                    selected: SyncDirection::ALL
+                        .iter()
+                        .position(|d| *d == sync_direction)
+                        .unwrap_or(0),
+                    select: Box::new(|this: &mut Self, index| {
+                        if let Some(direction) = SyncDirection::ALL.get(index) {
+                            this.state
+                                .handle_event(TrayEvent::SetSyncDirection(*direction));
+                        }
+                    }),
+                    options: SyncDirection::ALL
+                        .iter()
+                        .map(|d| ksni::menu::RadioItem {
+                            label: d.label().to_string(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                }
+                .into()],
+                ..Default::default()
+            }
+            .into(),
             ksni::menu::MenuItem::Separator,
             // Open web
             ksni::menu::StandardItem {
@@ -464,7 +699,9 @@ impl ksni::Tray for CookSyncTray {
                 ..Default::default()
             }
             .into(),
-        ]
+        ]);
+
+        items
     }
 }
 
@@ -536,6 +773,9 @@ impl SystemTray {
         let user_email_arc = Arc::clone(&self.state.user_email);
         let is_logged_in_arc = Arc::clone(&self.state.is_logged_in);
         let folder_path_arc = Arc::clone(&self.state.folder_path);
+        let folder_statuses_arc = Arc::clone(&self.state.folder_statuses);
+        let conflicts_arc = Arc::clone(&self.state.conflicts);
+        let recent_activity_arc = Arc::clone(&self.state.recent_activity);
         let sync_paused_arc = Arc::clone(&self.state.sync_paused);
         let shutdown_signal = Arc::clone(&self.state.shutdown_signal);
 
@@ -543,13 +783,22 @@ impl SystemTray {
             while !shutdown_signal.load(Ordering::Relaxed) {
                 // Read current state
                 let sync_state = sync_manager.state();
-                let sync_state_lock = sync_state.lock().unwrap();
-                let raw_status = sync_state_lock.status;
-                let error_message = sync_state_lock.error_message.clone();
-                drop(sync_state_lock);
+                let raw_status = sync_state.status;
+                // A failed pass waiting for its retry says when that is
+                let error_message = match (&sync_state.error_message, sync_state.retry_note()) {
+                    (Some(message), Some(note)) => Some(format!("{message}, {note}")),
+                    (message, _) => message.clone(),
+                };
+                // "Paused", or why the agent paused by itself
+                let paused_text = sync_state.status_text();
+                let progress = sync_state.progress.map(|p| p.to_string());
+                *conflicts_arc.lock().unwrap() = sync_state.conflicts;
+                *recent_activity_arc.lock().unwrap() =
+                    crate::sync::activity::recent_menu_lines(&config.paths().activity_file);
+                let folder_states = sync_manager.folder_states();
 
                 let has_auth = auth_manager.is_authenticated();
-                let has_folder = config.settings().lock().unwrap().recipes_dir.is_some();
+                let has_folder = !config.settings().lock().unwrap().roots().is_empty();
 
                 // Determine display status (match macOS/Windows logic)
                 let status_text = if !has_auth && !has_folder {
@@ -562,8 +811,8 @@ impl SystemTray {
                     match raw_status {
                         SyncStatus::Starting => "Starting",
                         SyncStatus::Idle => "Idle",
-                        SyncStatus::Syncing => "Syncing",
-                        SyncStatus::Paused => "Paused",
+                        SyncStatus::Syncing => progress.as_deref().unwrap_or("Syncing"),
+                        SyncStatus::Paused => paused_text.as_str(),
                         SyncStatus::Offline => "Offline",
                         SyncStatus::Error => error_message.as_deref().unwrap_or("Error"),
                     }
@@ -571,6 +820,26 @@ impl SystemTray {
 
                 *status_text_arc.lock().unwrap() = status_text.to_string();
 
+                // With several folders, show how each of them is doing
+                *folder_statuses_arc.lock().unwrap() = if folder_states.len() > 1 {
+                    folder_states
+                        .iter()
+                        .map(|f| {
+                            let status = match f.state.status {
+                                SyncStatus::Error => f
+                                    .state
+                                    .error_message
+                                    .clone()
+                                    .unwrap_or_else(|| "Error".to_string()),
+                                _ => f.state.status_text(),
+                            };
+                            format!("{}: {}", f.path.display(), status)
+                        })
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+
                 // Update auth state
                 if let Some(session) = auth_manager.get_session() {
                     *user_email_arc.lock().unwrap() = session.email.clone();
@@ -1,16 +1,35 @@
+use crate::config::settings::SyncDirection;
+use crate::sync::conflicts::Conflict;
+use crate::sync::status::{PauseReason, SyncProgress};
 use crate::sync::SyncStatus;
-use tray_icon::menu::{CheckMenuItem, Menu, MenuId, MenuItem, PredefinedMenuItem};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tray_icon::menu::{CheckMenuItem, Menu, MenuId, MenuItem, PredefinedMenuItem, Submenu};
 
 pub struct TrayMenu {
     pub menu: Menu,
     pub status_item: MenuItem,
     pub folder_item: MenuItem,
+    /// Status of each synced folder, only shown when there are several
+    pub folders_menu: Submenu,
+    /// Unresolved conflicts; each entry opens its conflict copy
+    pub conflicts_menu: Submenu,
+    conflicts: Mutex<Vec<(PathBuf, MenuItem)>>,
+    /// What the last sync runs did
+    pub activity_menu: Submenu,
+    activity: Mutex<Vec<String>>,
     pub sync_toggle: MenuItem,
+    /// Timed pauses, with their `cook-sync pause --for` values
+    pause_for: Vec<(&'static str, MenuItem)>,
     pub user_item: MenuItem,
     pub login_logout: MenuItem,
+    /// Profile switcher, enabled once there is more than one profile
+    pub profiles_menu: Submenu,
+    profiles: Mutex<Vec<(String, CheckMenuItem)>>,
     pub auto_start: CheckMenuItem,
     pub set_folder: MenuItem,
     pub open_folder: MenuItem,
+    pub sync_direction: Vec<(SyncDirection, CheckMenuItem)>,
     pub open_web: MenuItem,
     pub check_updates: MenuItem,
     pub about: MenuItem,
@@ -19,28 +38,56 @@ pub struct TrayMenu {
 
 impl Default for TrayMenu {
     fn default() -> Self {
-        Self::new(true)
+        Self::new(true, SyncDirection::default())
     }
 }
 
 impl TrayMenu {
-    pub fn new(auto_start_enabled: bool) -> Self {
+    pub fn new(auto_start_enabled: bool, sync_direction: SyncDirection) -> Self {
         let menu = Menu::new();
 
         // Status and folder info
         let status_item = MenuItem::new("Status: Starting 🟠", false, None);
         let folder_item = MenuItem::new("Not configured", false, None);
+        let folders_menu = Submenu::new("Folders", false);
+        let conflicts_menu = Submenu::new("Conflicts", false);
+        let activity_menu = Submenu::new("Recent activity", true);
+        activity_menu
+            .append(&MenuItem::new("No activity yet", false, None))
+            .unwrap();
 
         // Sync control
         let sync_toggle = MenuItem::new("Pause Sync", true, None);
+        let pause_menu = Submenu::new("Pause for", true);
+        let pause_for: Vec<_> = super::PAUSE_CHOICES
+            .into_iter()
+            .map(|(label, length)| (length, MenuItem::new(label, true, None)))
+            .collect();
+        for (_, item) in &pause_for {
+            pause_menu.append(item).unwrap();
+        }
 
         // User account
         let user_item = MenuItem::new("Not logged in", false, None);
         let login_logout = MenuItem::new("Login", true, None);
+        let profiles_menu = Submenu::new("Profile", false);
 
         // Settings
         let auto_start =
             CheckMenuItem::new("Start on system startup", true, auto_start_enabled, None);
+        let direction_menu = Submenu::new("Sync direction", true);
+        let sync_direction: Vec<_> = SyncDirection::ALL
+            .into_iter()
+            .map(|d| {
+                (
+                    d,
+                    CheckMenuItem::new(d.label(), true, d == sync_direction, None),
+                )
+            })
+            .collect();
+        for (_, item) in &sync_direction {
+            direction_menu.append(item).unwrap();
+        }
 
         // Actions
         let set_folder = MenuItem::new("Set recipes folder...", true, None);
@@ -52,14 +99,20 @@ impl TrayMenu {
 
         // Build menu
         menu.append(&status_item).unwrap();
+        menu.append(&conflicts_menu).unwrap();
+        menu.append(&activity_menu).unwrap();
         menu.append(&sync_toggle).unwrap();
+        menu.append(&pause_menu).unwrap();
         menu.append(&PredefinedMenuItem::separator()).unwrap();
         menu.append(&user_item).unwrap();
         menu.append(&login_logout).unwrap();
+        menu.append(&profiles_menu).unwrap();
         menu.append(&PredefinedMenuItem::separator()).unwrap();
         menu.append(&folder_item).unwrap();
+        menu.append(&folders_menu).unwrap();
         menu.append(&set_folder).unwrap();
         menu.append(&open_folder).unwrap();
+        menu.append(&direction_menu).unwrap();
         menu.append(&PredefinedMenuItem::separator()).unwrap();
         menu.append(&open_web).unwrap();
         menu.append(&auto_start).unwrap();
@@ -72,12 +125,21 @@ impl TrayMenu {
             menu,
             status_item,
             folder_item,
+            folders_menu,
+            conflicts_menu,
+            conflicts: Mutex::new(Vec::new()),
+            activity_menu,
+            activity: Mutex::new(Vec::new()),
             sync_toggle,
+            pause_for,
             user_item,
             login_logout,
+            profiles_menu,
+            profiles: Mutex::new(Vec::new()),
             auto_start,
             set_folder,
             open_folder,
+            sync_direction,
             open_web,
             check_updates,
             about,
@@ -85,12 +147,24 @@ impl TrayMenu {
         }
     }
 
-    pub fn update_status(&self, status: SyncStatus, error_msg: Option<&str>) {
+    pub fn update_status(
+        &self,
+        status: SyncStatus,
+        error_msg: Option<&str>,
+        progress: Option<&SyncProgress>,
+        paused_by: Option<PauseReason>,
+    ) {
         let (indicator, text) = match status {
             SyncStatus::Starting => ("🟠", "Starting".to_string()),
             SyncStatus::Idle => ("🟢", "Idle".to_string()),
-            SyncStatus::Syncing => ("🟢", "Syncing".to_string()),
-            SyncStatus::Paused => ("🟠", "Paused".to_string()),
+            SyncStatus::Syncing => (
+                "🟢",
+                progress.map_or_else(|| "Syncing".to_string(), ToString::to_string),
+            ),
+            SyncStatus::Paused => (
+                "🟠",
+                paused_by.map_or_else(|| "Paused".to_string(), |r| format!("Paused: {r}")),
+            ),
             SyncStatus::Offline => ("🟠", "Offline".to_string()),
             SyncStatus::Error => ("🔴", error_msg.unwrap_or("Error").to_string()),
         };
@@ -119,11 +193,66 @@ impl TrayMenu {
         }
     }
 
+    /// Pause length chosen with the menu item with `id`, if it is one of ours
+    pub fn pause_length_for(&self, id: &MenuId) -> Option<&'static str> {
+        self.pause_for
+            .iter()
+            .find(|(_, item)| item.id() == id)
+            .map(|(length, _)| *length)
+    }
+
     #[allow(dead_code)]
     pub fn set_auto_start(&self, enabled: bool) {
         self.auto_start.set_checked(enabled);
     }
 
+    /// Check the item for `direction` and uncheck the others
+    pub fn set_sync_direction(&self, direction: SyncDirection) {
+        for (d, item) in &self.sync_direction {
+            item.set_checked(*d == direction);
+        }
+    }
+
+    /// Direction selected by the menu item with `id`, if it is one of ours
+    pub fn sync_direction_for(&self, id: &MenuId) -> Option<SyncDirection> {
+        self.sync_direction
+            .iter()
+            .find(|(_, item)| item.id() == id)
+            .map(|(d, _)| *d)
+    }
+
+    /// Show `names` in the profile switcher with `active` checked
+    pub fn update_profiles(&self, names: &[String], active: &str) {
+        let mut profiles = self.profiles.lock().unwrap();
+
+        if !profiles.iter().map(|(name, _)| name).eq(names.iter()) {
+            for (_, item) in profiles.drain(..) {
+                let _ = self.profiles_menu.remove(&item);
+            }
+            for name in names {
+                let item = CheckMenuItem::new(name.as_str(), true, false, None);
+                let _ = self.profiles_menu.append(&item);
+                profiles.push((name.clone(), item));
+            }
+        }
+
+        for (name, item) in profiles.iter() {
+            item.set_checked(name == active);
+        }
+        self.profiles_menu.set_text(format!("Profile: {active}"));
+        self.profiles_menu.set_enabled(names.len() > 1);
+    }
+
+    /// Profile selected by the menu item with `id`, if it is one of ours
+    pub fn profile_for(&self, id: &MenuId) -> Option<String> {
+        self.profiles
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, item)| item.id() == id)
+            .map(|(name, _)| name.clone())
+    }
+
     pub fn get_menu_id(&self, item: &MenuItem) -> MenuId {
         item.id().clone()
     }
@@ -152,4 +281,72 @@ impl TrayMenu {
             None => self.folder_item.set_text("Not configured"),
         }
     }
+
+    /// Replace the per-folder status lines
+    pub fn update_folders(&self, lines: &[String]) {
+        for item in self.folders_menu.items() {
+            let _ = self.folders_menu.remove(item.as_ref());
+        }
+        for line in lines {
+            let _ = self
+                .folders_menu
+                .append(&MenuItem::new(line.as_str(), false, None));
+        }
+        self.folders_menu.set_enabled(!lines.is_empty());
+    }
+
+    /// Replace the recent activity lines
+    pub fn update_activity(&self, lines: &[String]) {
+        let mut current = self.activity.lock().unwrap();
+        if current.as_slice() == lines {
+            return;
+        }
+        *current = lines.to_vec();
+
+        for item in self.activity_menu.items() {
+            let _ = self.activity_menu.remove(item.as_ref());
+        }
+        if lines.is_empty() {
+            let _ = self
+                .activity_menu
+                .append(&MenuItem::new("No activity yet", false, None));
+        }
+        for line in lines {
+            let _ = self
+                .activity_menu
+                .append(&MenuItem::new(line.as_str(), false, None));
+        }
+    }
+
+    /// Replace the conflict entries
+    pub fn update_conflicts(&self, conflicts: &[Conflict]) {
+        let mut items = self.conflicts.lock().unwrap();
+
+        let copies: Vec<PathBuf> = conflicts.iter().map(Conflict::copy_abs).collect();
+        if !items.iter().map(|(copy, _)| copy).eq(copies.iter()) {
+            for (_, item) in items.drain(..) {
+                let _ = self.conflicts_menu.remove(&item);
+            }
+            for (conflict, copy) in conflicts.iter().zip(copies) {
+                let label = format!("{} (from {})", conflict.path.display(), conflict.host);
+                let item = MenuItem::new(label, true, None);
+                let _ = self.conflicts_menu.append(&item);
+                items.push((copy, item));
+            }
+        }
+
+        self.conflicts_menu
+            .set_text(format!("Conflicts ({})", conflicts.len()));
+        self.conflicts_menu.set_enabled(!conflicts.is_empty());
+    }
+
+    /// Conflict copy opened by the menu item with `id`, if it is one of ours
+    pub fn conflict_for(&self, id: &MenuId) -> Option<PathBuf> {
+        self.conflicts
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, item)| item.id() == id)
+            .map(|(copy, _)| copy.clone())
+    }
 }
@@ -4,6 +4,14 @@
 
 pub mod about;
 
+/// Entries of the "Pause for" submenu, as label and `cook-sync pause --for`
+/// value
+pub const PAUSE_CHOICES: [(&str, &str); 3] = [
+    ("30 minutes", "30m"),
+    ("1 hour", "1h"),
+    ("Until tomorrow", "tomorrow"),
+];
+
 // Platform-specific menu module
 #[cfg(not(target_os = "linux"))]
 pub mod menu;
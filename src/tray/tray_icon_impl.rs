@@ -3,6 +3,7 @@
 
 use super::menu::TrayMenu;
 use crate::auth::AuthManager;
+use crate::config::settings::SyncDirection;
 use crate::config::Config;
 use crate::error::{Result, SyncError};
 use crate::platform::{ThemeChange, ThemeWatcher};
@@ -17,6 +18,8 @@ use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
 pub enum TrayEvent {
     Quit,
     ToggleSync,
+    /// Pause until the end given as a `cook-sync pause --for` value
+    PauseFor(&'static str),
     SetFolder,
     OpenFolder,
     OpenWeb,
@@ -25,6 +28,9 @@ pub enum TrayEvent {
     ToggleAutoStart,
     UpdateStatus,
     LoginLogout,
+    SetSyncDirection(SyncDirection),
+    SwitchProfile(String),
+    OpenConflict(std::path::PathBuf),
     ThemeChanged(ThemeChange),
 }
 
@@ -95,7 +101,10 @@ impl SystemTray {
 
         // Create menu with actual auto-start state
         debug!("Creating tray menu...");
-        let menu = TrayMenu::new(auto_start_enabled);
+        let sync_direction = config.settings().lock().unwrap().sync_direction;
+        let menu = TrayMenu::new(auto_start_enabled, sync_direction);
+        // A pause saved before the last restart is in place already
+        menu.update_sync_toggle(sync_manager.state().status == SyncStatus::Paused);
         debug!("Tray menu created successfully");
 
         // Create tray icon
@@ -186,8 +195,7 @@ impl SystemTray {
                         event_loop.exit();
                     }
                     TrayEvent::ToggleSync => {
-                        let state = sync_manager.state();
-                        let is_paused = state.lock().unwrap().status == SyncStatus::Paused;
+                        let is_paused = sync_manager.state().status == SyncStatus::Paused;
                         if is_paused {
                             sync_manager.resume();
                         } else {
@@ -198,6 +206,16 @@ impl SystemTray {
                         // Trigger immediate status update to refresh menu
                         event_loop_proxy.send_event(TrayEvent::UpdateStatus).ok();
                     }
+                    TrayEvent::PauseFor(length) => {
+                        let result = crate::sync::schedule::parse_pause_end(length)
+                            .map_err(SyncError::Other)
+                            .and_then(|until| sync_manager.pause_until(until));
+                        match result {
+                            Ok(()) => menu.update_sync_toggle(true),
+                            Err(e) => error!("Failed to pause sync: {e}"),
+                        }
+                        event_loop_proxy.send_event(TrayEvent::UpdateStatus).ok();
+                    }
                     TrayEvent::SetFolder => {
                         // Use native file dialog to select folder
                         // Spawn in a separate thread to avoid blocking the event loop
@@ -253,6 +271,12 @@ impl SystemTray {
                             });
                         }
                     }
+                    TrayEvent::OpenConflict(copy) => {
+                        // Spawn in separate thread to avoid blocking event loop
+                        std::thread::spawn(move || {
+                            let _ = open::that(copy);
+                        });
+                    }
                     TrayEvent::OpenWeb => {
                         // Spawn in separate thread to avoid blocking event loop
                         std::thread::spawn(|| {
@@ -364,15 +388,59 @@ impl SystemTray {
                             );
                         }
                     }
+                    TrayEvent::SetSyncDirection(direction) => {
+                        info!("Setting sync direction to: {direction}");
+                        // Check menu items toggle themselves; keep exactly one checked
+                        menu.set_sync_direction(direction);
+                        match config.update_settings(|s| s.sync_direction = direction) {
+                            Ok(()) => sync_manager.wake(),
+                            Err(e) => error!("Failed to update sync direction: {e}"),
+                        }
+                    }
+                    TrayEvent::SwitchProfile(name) => {
+                        let active = config.profile();
+                        // Check menu items toggle themselves; keep exactly one checked
+                        let names = config.profiles().map(|p| p.names()).unwrap_or_default();
+                        menu.update_profiles(&names, &active);
+                        if name != active {
+                            info!("Switching to profile: {name}");
+                            let auth_manager_clone = Arc::clone(&auth_manager);
+                            let sync_manager_clone = Arc::clone(&sync_manager);
+                            let config_clone = Arc::clone(&config);
+                            let event_proxy_clone = event_loop_proxy.clone();
+                            runtime_handle.clone().spawn(async move {
+                                if let Err(e) = crate::daemon::switch_profile(
+                                    &config_clone,
+                                    &auth_manager_clone,
+                                    &sync_manager_clone,
+                                    &name,
+                                )
+                                .await
+                                {
+                                    error!("Failed to switch profile: {e}");
+                                }
+                                event_proxy_clone.send_event(TrayEvent::UpdateStatus).ok();
+                            });
+                        }
+                    }
                     TrayEvent::UpdateStatus => {
                         // Update menu based on current state
-                        let sync_state_mutex = sync_manager.state();
-                        let sync_state = sync_state_mutex.lock().unwrap();
+                        let sync_state = sync_manager.state();
 
                         // Check for configuration issues
                         let has_auth = auth_manager.get_session().is_some();
-                        let folder_path = config.settings().lock().unwrap().recipes_dir.clone();
-                        let has_folder = folder_path.is_some();
+                        let (folder_path, has_folder) = {
+                            let settings = config.settings();
+                            let settings = settings.lock().unwrap();
+                            (settings.recipes_dir.clone(), !settings.roots().is_empty())
+                        };
+
+                        // A failed pass waiting for its retry says when that is
+                        let error_message =
+                            match (&sync_state.error_message, sync_state.retry_note()) {
+                                (Some(message), Some(note)) => Some(format!("{message}, {note}")),
+                                (message, _) => message.clone(),
+                            };
 
                         // Determine status and error message
                         let (display_status, error_msg) = if !has_auth && !has_folder {
@@ -382,10 +450,16 @@ impl SystemTray {
                         } else if !has_folder {
                             (SyncStatus::Error, Some("No folder selected"))
                         } else {
-                            (sync_state.status, sync_state.error_message.as_deref())
+                            (sync_state.status, error_message.as_deref())
                         };
 
-                        menu.update_status(display_status, error_msg);
+                        menu.update_status(
+                            display_status,
+                            error_msg,
+                            sync_state.progress.as_ref(),
+                            sync_state.paused_by,
+                        );
+                        menu.update_sync_toggle(sync_state.status == SyncStatus::Paused);
 
                         if let Some(session) = auth_manager.get_session() {
                             menu.update_user(session.email.as_deref());
@@ -396,6 +470,35 @@ impl SystemTray {
                         // Update folder display
                         menu.update_folder(folder_path.as_deref());
 
+                        // With several folders, show how each of them is doing
+                        let folder_states = sync_manager.folder_states();
+                        let folder_lines: Vec<String> = if folder_states.len() > 1 {
+                            folder_states
+                                .iter()
+                                .map(|f| {
+                                    let status = match f.state.status {
+                                        SyncStatus::Error => f
+                                            .state
+                                            .error_message
+                                            .clone()
+                                            .unwrap_or_else(|| "Error".to_string()),
+                                        _ => f.state.status_text(),
+                                    };
+                                    format!("{}: {}", f.path.display(), status)
+                                })
+                                .collect()
+                        } else {
+                            Vec::new()
+                        };
+                        menu.update_folders(&folder_lines);
+                        menu.update_conflicts(&sync_state.conflicts);
+                        menu.update_activity(&crate::sync::activity::recent_menu_lines(
+                            &config.paths().activity_file,
+                        ));
+
+                        let profiles = config.profiles().map(|p| p.names()).unwrap_or_default();
+                        menu.update_profiles(&profiles, &config.profile());
+
                         // Update tray icon if status changed
                         let mut last_status_guard = state.last_status.lock().unwrap();
                         let status_changed = *last_status_guard != display_status;
@@ -416,11 +519,7 @@ impl SystemTray {
                         debug!("Received theme change event: {:?}", theme);
 
                         // Get current status to update icon with new theme
-                        let current_status = {
-                            let state = sync_manager.state();
-                            let state_guard = state.lock().unwrap();
-                            state_guard.status
-                        };
+                        let current_status = sync_manager.state().status;
 
                         // Determine if we're in dark mode based on the theme change
                         let is_dark = matches!(theme, crate::platform::ThemeChange::Dark);
@@ -476,13 +575,8 @@ impl SystemTray {
                                     Ok(()) => {
                                         info!("Login completed successfully");
 
-                                        // Start sync manager if recipes folder is configured
-                                        if config_clone
-                                            .settings()
-                                            .lock()
-                                            .unwrap()
-                                            .recipes_dir
-                                            .is_some()
+                                        // Start sync manager if a folder is configured
+                                        if !config_clone.settings().lock().unwrap().roots().is_empty()
                                         {
                                             if let Err(e) = sync_manager_clone.start().await {
                                                 error!(
@@ -522,6 +616,20 @@ impl SystemTray {
                     event_loop_proxy.send_event(TrayEvent::ToggleAutoStart).ok();
                 } else if event.id == menu.get_menu_id(&menu.login_logout) {
                     event_loop_proxy.send_event(TrayEvent::LoginLogout).ok();
+                } else if let Some(length) = menu.pause_length_for(&event.id) {
+                    event_loop_proxy.send_event(TrayEvent::PauseFor(length)).ok();
+                } else if let Some(direction) = menu.sync_direction_for(&event.id) {
+                    event_loop_proxy
+                        .send_event(TrayEvent::SetSyncDirection(direction))
+                        .ok();
+                } else if let Some(name) = menu.profile_for(&event.id) {
+                    event_loop_proxy
+                        .send_event(TrayEvent::SwitchProfile(name))
+                        .ok();
+                } else if let Some(copy) = menu.conflict_for(&event.id) {
+                    event_loop_proxy
+                        .send_event(TrayEvent::OpenConflict(copy))
+                        .ok();
                 }
             }
         });
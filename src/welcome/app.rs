@@ -1,4 +1,5 @@
 // Welcome screen application using egui
+use crate::config::settings::SyncDirection;
 use crate::error::Result;
 use crate::welcome::components::{render_action_buttons, render_brand_header, render_setup_steps};
 use crate::welcome::state::WelcomeState;
@@ -33,6 +34,7 @@ pub struct WelcomeResult {
     pub recipes_dir: Option<PathBuf>,
     pub auto_start: bool,
     pub auto_update: bool,
+    pub sync_direction: SyncDirection,
 }
 
 pub struct WelcomeApp {
@@ -57,6 +59,7 @@ impl WelcomeApp {
             recipes_dir: None,
             auto_start: true,
             auto_update: true,
+            sync_direction: SyncDirection::default(),
         })))
     }
 
@@ -129,6 +132,7 @@ impl eframe::App for WelcomeApp {
                                 result.recipes_dir = self.state.recipes_dir.clone();
                                 result.auto_start = self.state.auto_start;
                                 result.auto_update = self.state.auto_update;
+                                result.sync_direction = self.state.sync_direction;
                             }
                             self.state.request_close();
                         }
@@ -168,6 +172,7 @@ pub fn show_welcome_screen() -> Result<WelcomeResult> {
         recipes_dir: None,
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::default(),
     }));
     let result_clone = result_ref.clone();
 
@@ -1,4 +1,5 @@
 // Setup steps component - authentication, directory, preferences
+use crate::config::settings::SyncDirection;
 use crate::welcome::state::WelcomeState;
 use crate::welcome::style::{self, sizing, spacing, typography, ColorPalette};
 use eframe::egui;
@@ -451,6 +452,30 @@ fn render_preferences_checkboxes(
                 .color(palette.text_primary),
         );
     });
+
+    ui.add_space(spacing::MEDIUM);
+
+    // Sync direction, for read-only mirrors and publish-only machines
+    ui.horizontal(|ui| {
+        ui.add_space(indent);
+        ui.label(
+            egui::RichText::new("Sync direction")
+                .size(typography::BUTTON_TEXT_SIZE)
+                .color(palette.text_primary),
+        );
+    });
+    for direction in SyncDirection::ALL {
+        ui.horizontal(|ui| {
+            ui.add_space(indent);
+            ui.radio_value(
+                &mut state.sync_direction,
+                direction,
+                egui::RichText::new(direction.label())
+                    .size(typography::BUTTON_TEXT_SIZE)
+                    .color(palette.text_primary),
+            );
+        });
+    }
 }
 
 /// Render a custom square checkbox matching Figma design
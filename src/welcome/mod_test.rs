@@ -2,6 +2,7 @@
 // These tests are written following TDD - they should all fail until the implementation is complete
 
 use super::*;
+use crate::config::settings::SyncDirection;
 
 #[test]
 fn test_welcome_app_struct_exists() {
@@ -29,6 +30,7 @@ fn test_welcome_result_struct_exists() {
         recipes_dir: None,
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::Both,
     };
 
     assert!(!result.login_requested);
@@ -45,6 +47,7 @@ fn test_welcome_result_with_login_requested() {
         recipes_dir: None,
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::Both,
     };
 
     assert!(
@@ -64,6 +67,7 @@ fn test_welcome_result_with_recipes_dir() {
         recipes_dir: Some(test_path.clone()),
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::Both,
     };
 
     assert!(result.recipes_dir.is_some());
@@ -81,6 +85,7 @@ fn test_welcome_result_with_both_fields_set() {
         recipes_dir: Some(test_path.clone()),
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::Both,
     };
 
     assert!(result.login_requested);
@@ -155,6 +160,7 @@ mod welcome_result_tests {
             recipes_dir: None,
             auto_start: true,
             auto_update: true,
+            sync_direction: SyncDirection::Both,
         };
 
         assert!(!result.login_requested, "Default should not request login");
@@ -172,6 +178,7 @@ mod welcome_result_tests {
             recipes_dir: None,
             auto_start: true,
             auto_update: true,
+            sync_direction: SyncDirection::Both,
         };
 
         assert!(result.login_requested);
@@ -188,6 +195,7 @@ mod welcome_result_tests {
             recipes_dir: Some(PathBuf::from("/test")),
             auto_start: true,
             auto_update: true,
+            sync_direction: SyncDirection::Both,
         };
 
         assert!(!result.login_requested);
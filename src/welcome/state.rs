@@ -1,7 +1,10 @@
 // State management for the welcome screen
 use crate::api::CookApi;
 use crate::auth::AuthManager;
-use crate::config::{self, settings::Settings};
+use crate::config::{
+    self,
+    settings::{Settings, SyncDirection},
+};
 use crate::error::Result;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
@@ -31,6 +34,7 @@ pub struct WelcomeState {
     pub preferences_expanded: bool,
     pub auto_start: bool,
     pub auto_update: bool,
+    pub sync_direction: SyncDirection,
 
     // UI state
     pub should_close: bool,
@@ -48,6 +52,7 @@ impl Default for WelcomeState {
             preferences_expanded: false, // Hidden until Steps 1 & 2 complete (per Figma)
             auto_start: true,            // Default to enabled
             auto_update: true,           // Default to enabled
+            sync_direction: SyncDirection::Both,
             should_close: false,
         }
     }
@@ -202,6 +207,7 @@ mod tests {
         assert!(!state.can_proceed());
         assert!(state.auto_start);
         assert!(state.auto_update); // Defaults to true
+        assert_eq!(state.sync_direction, SyncDirection::Both);
     }
 
     #[test]
@@ -266,6 +266,7 @@ fn test_second_run_skips_welcome() {
 #[test]
 fn test_welcome_result_can_store_user_choices() {
     // Test that WelcomeResult can capture user's choices from welcome screen
+    use cook_sync::config::settings::SyncDirection;
     use cook_sync::welcome::WelcomeResult;
     use std::path::PathBuf;
 
@@ -275,6 +276,7 @@ fn test_welcome_result_can_store_user_choices() {
         recipes_dir: None,
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::Both,
     };
     assert!(result_login.login_requested);
     assert!(result_login.recipes_dir.is_none());
@@ -286,6 +288,7 @@ fn test_welcome_result_can_store_user_choices() {
         recipes_dir: Some(test_dir.clone()),
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::Both,
     };
     assert!(!result_dir.login_requested);
     assert_eq!(result_dir.recipes_dir, Some(test_dir));
@@ -297,6 +300,7 @@ fn test_welcome_result_can_store_user_choices() {
         recipes_dir: Some(test_dir2.clone()),
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::Both,
     };
     assert!(result_both.login_requested);
     assert_eq!(result_both.recipes_dir, Some(test_dir2));
@@ -307,6 +311,7 @@ fn test_welcome_result_can_store_user_choices() {
         recipes_dir: None,
         auto_start: true,
         auto_update: true,
+        sync_direction: SyncDirection::Both,
     };
     assert!(!result_neither.login_requested);
     assert!(result_neither.recipes_dir.is_none());